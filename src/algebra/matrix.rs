@@ -2,7 +2,7 @@ use super::MatLike;
 use rand::{distributions::uniform::SampleUniform, Rng};
 use std::fmt::Display;
 use std::ops::{Index, IndexMut};
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Debug)]
 pub struct Matrix<T> {
     data: Vec<T>,
     w: usize,
@@ -22,6 +22,12 @@ impl<T> MatLike for Matrix<T> {
     }
 }
 
+impl<T> Matrix<T> {
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, T> {
+        self.data.iter_mut()
+    }
+}
+
 // row major
 impl<T> Index<(usize, usize)> for Matrix<T> {
     type Output = T;
@@ -140,6 +146,17 @@ impl<T: Copy + Clone + PartialOrd> Matrix<T> {
         }
     }
 
+    /// New matrix made of the given rows, in the given order.
+    pub fn select_rows(&self, rows: &[usize]) -> Matrix<T> {
+        Matrix::new(
+            rows.iter()
+                .flat_map(|&i| self.data[(self.w * i)..(self.w * (i + 1))].iter().copied())
+                .collect(),
+            self.w,
+            rows.len(),
+        )
+    }
+
     pub fn clone_row(&self, i: usize) -> Matrix<T> {
         Matrix::new(
             self.data[(self.w * i)..(self.w * (i + 1))].to_vec(),
@@ -151,7 +168,11 @@ impl<T: Copy + Clone + PartialOrd> Matrix<T> {
 
 impl<T: Copy + SampleUniform + Clone + PartialOrd> Matrix<T> {
     pub fn random(low: T, high: T, w: usize, h: usize) -> Matrix<T> {
-        let mut rng = rand::thread_rng();
+        Self::random_with(low, high, w, h, &mut rand::thread_rng())
+    }
+
+    /// `random`, drawn from `rng` so it can be seeded
+    pub fn random_with<R: Rng>(low: T, high: T, w: usize, h: usize, rng: &mut R) -> Matrix<T> {
         Self {
             data: (0..(w * h)).map(|_| rng.gen_range(low..high)).collect(),
             w,
@@ -159,3 +180,25 @@ impl<T: Copy + SampleUniform + Clone + PartialOrd> Matrix<T> {
         }
     }
 }
+
+impl Matrix<f64> {
+    /// Every element drawn from a normal distribution, via the Box-Muller transform
+    pub fn random_normal_with<R: Rng>(
+        mean: f64,
+        std_dev: f64,
+        w: usize,
+        h: usize,
+        rng: &mut R,
+    ) -> Matrix<f64> {
+        let data = (0..(w * h))
+            .map(|_| {
+                // 1 - u is in (0, 1], so its ln is finite
+                let u1: f64 = 1.0 - rng.gen::<f64>();
+                let u2: f64 = rng.gen();
+                let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+                mean + std_dev * z
+            })
+            .collect();
+        Self { data, w, h }
+    }
+}
@@ -54,6 +54,37 @@ impl<T: Mul<Output = T> + Copy + Clone + PartialOrd + SampleUniform> Matrix<T> {
     }
 }
 
+impl<T: Add<Output = T> + Copy + Clone + Default + PartialOrd> Matrix<T> {
+    /// Adds `vector` (a row or col vec of length `self.w()`) to every row.
+    pub fn add_to_rows(&self, vector: &Matrix<T>) -> Matrix<T> {
+        assert_eq!(
+            vector.len(),
+            self.w(),
+            "vector len does not match width (vector len: {}, w: {})",
+            vector.len(),
+            self.w()
+        );
+        let vector: Vec<T> = vector.iter().copied().collect();
+        Matrix::<T>::new(
+            self.iter()
+                .enumerate()
+                .map(|(i, x)| *x + vector[i % self.w()])
+                .collect(),
+            self.w(),
+            self.h(),
+        )
+    }
+
+    /// Sum of every row, as a row vec.
+    pub fn sum_rows(&self) -> Matrix<T> {
+        let mut sums = vec![T::default(); self.w()];
+        for (i, x) in self.iter().enumerate() {
+            sums[i % self.w()] = sums[i % self.w()] + *x;
+        }
+        Matrix::<T>::new(sums, self.w(), 1)
+    }
+}
+
 macro_rules! mat_mat_add {
     ($type: ty, $name: ident, $op: tt) => {
         type Output = Matrix<T>;
@@ -1,7 +1,4 @@
-use std::{
-    collections::{HashMap, HashSet},
-    fs::read_to_string,
-};
+use std::{collections::HashMap, fs::read_to_string};
 
 use crate::algebra::Matrix;
 
@@ -59,7 +56,6 @@ impl Dataset {
             }
             c = file_iter.next().unwrap();
         }
-        println!("{:?}", keys);
         let mut container: Vec<Vec<DataType>> = Vec::new();
         let mut cur_line: Vec<DataType> = Vec::new();
         let mut cur_entry = String::new();
@@ -162,71 +158,4 @@ impl Dataset {
                 .collect(),
         )
     }
-
-    fn integer_encode(&mut self, col: &String) {
-        let unique: Vec<String> = self
-            .unique_values(col, 0)
-            .unwrap()
-            .into_iter()
-            .map(|x| {
-                if let DataType::Categorical(y) = x {
-                    y
-                } else {
-                    unreachable!()
-                }
-            })
-            .collect();
-        let mut unique_hashmap: HashMap<String, usize> = HashMap::new();
-        for i in unique.into_iter().enumerate() {
-            unique_hashmap.insert(i.1, i.0);
-        }
-        col.clone().push_str(" - encoded");
-        self.data.insert(col.to_string(), self.container.len());
-        self.container.push(
-            self.get_col(col)
-                .unwrap()
-                .iter()
-                .map(|x| {
-                    if let DataType::Categorical(y) = x {
-                        DataType::Numerical(
-                            *unique_hashmap.get(y).expect("failed to get {y}") as f64
-                        )
-                    } else {
-                        unreachable!()
-                    }
-                })
-                .collect(),
-        )
-    }
-
-    fn unique_values(&self, col_name: &str, accuracy: i32) -> Option<Vec<DataType>> {
-        let mut unique = HashSet::<String>::new();
-        let col = self.get_col(col_name)?;
-        let is_num = match col[0] {
-            DataType::Categorical(_) => false,
-            DataType::Numerical(_) => true,
-        };
-        for i in col {
-            unique.insert(match i {
-                DataType::Categorical(x) => x.to_string(),
-                DataType::Numerical(x) => Self::string_with_accuracy(*x, accuracy),
-            });
-        }
-        Some(
-            unique
-                .into_iter()
-                .map(|x| {
-                    if is_num {
-                        DataType::Numerical(x.parse().unwrap())
-                    } else {
-                        DataType::Categorical(x)
-                    }
-                })
-                .collect(),
-        )
-    }
-
-    fn string_with_accuracy(x: f64, accuracy: i32) -> String {
-        ((x * 10_f64.powi(accuracy)).round() / 10_f64.powi(accuracy)).to_string()
-    }
 }
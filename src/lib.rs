@@ -1,3 +1,4 @@
 pub mod algebra;
 pub mod data;
+pub mod metrics;
 pub mod nn;
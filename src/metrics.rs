@@ -0,0 +1,378 @@
+//! Scores for predictions against targets, both one case per row, eg. the output of
+//! `FFNet::predict` against one-hot targets from `Dataset::to_matrix`.
+//!
+//! Classification metrics read a single column as a binary problem, where a value above 0.5 is
+//! the positive class 1, and several columns as one class per column, picked by the largest value.
+
+use crate::algebra::{MatLike, Matrix};
+
+// smallest probability log_loss takes the log of
+const MIN_PROB: f64 = 1e-15;
+
+fn check_shapes(pred: &Matrix<f64>, y: &Matrix<f64>) {
+    assert_eq!(
+        (pred.w(), pred.h()),
+        (y.w(), y.h()),
+        "predictions and targets have different shapes"
+    );
+}
+
+/// Number of classes in a matrix with one case per row
+pub fn class_count(m: &Matrix<f64>) -> usize {
+    m.w().max(2)
+}
+
+/// The class of every row
+pub fn classes(m: &Matrix<f64>) -> Vec<usize> {
+    (0..m.h())
+        .map(|i| {
+            if m.w() == 1 {
+                (m[(i, 0)] > 0.5) as usize
+            } else {
+                (0..m.w()).fold(0, |best, j| if m[(i, j)] > m[(i, best)] { j } else { best })
+            }
+        })
+        .collect()
+}
+
+/// Fraction of rows whose predicted class is the target one
+pub fn accuracy(pred: &Matrix<f64>, y: &Matrix<f64>) -> f64 {
+    check_shapes(pred, y);
+    let correct = classes(pred)
+        .into_iter()
+        .zip(classes(y))
+        .filter(|(p, y)| p == y)
+        .count();
+    correct as f64 / pred.h().max(1) as f64
+}
+
+/// Fraction of rows where the target class is among the `k` highest predictions. Ties with the
+/// target's prediction count against it.
+pub fn top_k_accuracy(pred: &Matrix<f64>, y: &Matrix<f64>, k: usize) -> f64 {
+    check_shapes(pred, y);
+    assert!(pred.w() > 1, "top-k accuracy needs a column per class");
+    let hits = classes(y)
+        .into_iter()
+        .enumerate()
+        .filter(|&(i, target)| {
+            let above = (0..pred.w())
+                .filter(|&j| j != target && pred[(i, j)] >= pred[(i, target)])
+                .count();
+            above < k
+        })
+        .count();
+    hits as f64 / pred.h().max(1) as f64
+}
+
+/// How to combine per-class precision, recall or F1 into one number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Average {
+    /// From the counts summed over every class, which for single-label data is the accuracy
+    Micro,
+    /// Unweighted mean over the classes
+    Macro,
+    /// Mean over the classes weighted by how many rows have each as their target
+    Weighted,
+}
+
+/// Counts of rows by target class (row) and predicted class (column).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfusionMatrix {
+    counts: Vec<Vec<usize>>,
+}
+
+impl ConfusionMatrix {
+    pub fn new(pred: &Matrix<f64>, y: &Matrix<f64>) -> Self {
+        check_shapes(pred, y);
+        let n = class_count(y);
+        let mut counts = vec![vec![0; n]; n];
+        for (p, y) in classes(pred).into_iter().zip(classes(y)) {
+            counts[y][p] += 1;
+        }
+        Self { counts }
+    }
+
+    pub fn classes(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// Rows of class `actual` predicted as `predicted`
+    pub fn count(&self, actual: usize, predicted: usize) -> usize {
+        self.counts[actual][predicted]
+    }
+
+    fn true_positives(&self, class: usize) -> usize {
+        self.counts[class][class]
+    }
+
+    fn predicted(&self, class: usize) -> usize {
+        self.counts.iter().map(|row| row[class]).sum()
+    }
+
+    /// Rows whose target is `class`
+    pub fn support(&self, class: usize) -> usize {
+        self.counts[class].iter().sum()
+    }
+
+    /// Of the rows predicted as `class`, the fraction that are. 0 if none are predicted.
+    pub fn precision(&self, class: usize) -> f64 {
+        ratio(self.true_positives(class), self.predicted(class))
+    }
+
+    /// Of the rows that are `class`, the fraction predicted as it. 0 if there are none.
+    pub fn recall(&self, class: usize) -> f64 {
+        ratio(self.true_positives(class), self.support(class))
+    }
+
+    pub fn f1(&self, class: usize) -> f64 {
+        harmonic_mean(self.precision(class), self.recall(class))
+    }
+
+    /// `counts` as a matrix, target classes down and predicted classes across
+    pub fn to_matrix(&self) -> Matrix<f64> {
+        let n = self.classes();
+        let data = self.counts.iter().flatten().map(|&c| c as f64).collect();
+        Matrix::new(data, n, n)
+    }
+
+    // combines score over every class
+    fn average(&self, average: Average, score: impl Fn(usize) -> f64) -> f64 {
+        let n = self.classes();
+        match average {
+            Average::Micro => self.micro(),
+            Average::Macro => (0..n).map(score).sum::<f64>() / n as f64,
+            Average::Weighted => {
+                let total: usize = (0..n).map(|c| self.support(c)).sum();
+                let weighted: f64 = (0..n).map(|c| score(c) * self.support(c) as f64).sum();
+                weighted / total.max(1) as f64
+            }
+        }
+    }
+
+    // with one label per row, every false positive of one class is a false negative of another,
+    // so micro precision, recall and F1 all come out as the accuracy
+    fn micro(&self) -> f64 {
+        let correct: usize = (0..self.classes()).map(|c| self.true_positives(c)).sum();
+        let total: usize = (0..self.classes()).map(|c| self.support(c)).sum();
+        ratio(correct, total)
+    }
+}
+
+fn ratio(numerator: usize, denominator: usize) -> f64 {
+    if denominator == 0 {
+        0.0
+    } else {
+        numerator as f64 / denominator as f64
+    }
+}
+
+fn harmonic_mean(a: f64, b: f64) -> f64 {
+    if a + b == 0.0 {
+        0.0
+    } else {
+        2.0 * a * b / (a + b)
+    }
+}
+
+pub fn precision(pred: &Matrix<f64>, y: &Matrix<f64>, average: Average) -> f64 {
+    let confusion = ConfusionMatrix::new(pred, y);
+    confusion.average(average, |c| confusion.precision(c))
+}
+
+pub fn recall(pred: &Matrix<f64>, y: &Matrix<f64>, average: Average) -> f64 {
+    let confusion = ConfusionMatrix::new(pred, y);
+    confusion.average(average, |c| confusion.recall(c))
+}
+
+pub fn f1_score(pred: &Matrix<f64>, y: &Matrix<f64>, average: Average) -> f64 {
+    let confusion = ConfusionMatrix::new(pred, y);
+    confusion.average(average, |c| confusion.f1(c))
+}
+
+/// Mean cross entropy of the predicted probabilities, binary for a single column. Probabilities
+/// are clamped away from 0 and 1 so a confident mistake costs a lot rather than infinity.
+pub fn log_loss(pred: &Matrix<f64>, y: &Matrix<f64>) -> f64 {
+    check_shapes(pred, y);
+    let clamp = |p: f64| p.clamp(MIN_PROB, 1.0 - MIN_PROB);
+    let total: f64 = (0..pred.h())
+        .map(|i| {
+            if pred.w() == 1 {
+                let (p, y) = (clamp(pred[(i, 0)]), y[(i, 0)]);
+                -(y * p.ln() + (1.0 - y) * (1.0 - p).ln())
+            } else {
+                -(0..pred.w())
+                    .map(|j| y[(i, j)] * clamp(pred[(i, j)]).ln())
+                    .sum::<f64>()
+            }
+        })
+        .sum();
+    total / pred.h().max(1) as f64
+}
+
+/// Points of a curve traced by lowering a decision threshold, from the highest score down.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve {
+    pub x: Vec<f64>,
+    pub y: Vec<f64>,
+    /// Scores at or above this count as positive at each point
+    pub thresholds: Vec<f64>,
+}
+
+impl Curve {
+    /// Area under the curve, by the trapezoidal rule
+    pub fn area(&self) -> f64 {
+        self.x
+            .windows(2)
+            .zip(self.y.windows(2))
+            .map(|(x, y)| (x[1] - x[0]) * (y[0] + y[1]) / 2.0)
+            .sum()
+    }
+}
+
+// (threshold, true positives, false positives) at every distinct score of column `class`,
+// highest first
+fn threshold_counts(pred: &Matrix<f64>, y: &Matrix<f64>, class: usize) -> Vec<(f64, usize, usize)> {
+    check_shapes(pred, y);
+    assert!(class < pred.w(), "no column {} to score", class);
+    let mut order: Vec<usize> = (0..pred.h()).collect();
+    order.sort_by(|&a, &b| pred[(b, class)].total_cmp(&pred[(a, class)]));
+    let mut counts: Vec<(f64, usize, usize)> = Vec::new();
+    let (mut tp, mut fp) = (0, 0);
+    for (n, &i) in order.iter().enumerate() {
+        if y[(i, class)] > 0.5 {
+            tp += 1;
+        } else {
+            fp += 1;
+        }
+        // only once every row with this score is in
+        let score = pred[(i, class)];
+        if order
+            .get(n + 1)
+            .is_none_or(|&next| pred[(next, class)] != score)
+        {
+            counts.push((score, tp, fp));
+        }
+    }
+    counts
+}
+
+/// Receiver operating characteristic of column `class` against the rest: false positive rate (x)
+/// against true positive rate (y), starting from (0, 0). For a single-column binary prediction,
+/// `class` is 0. Rates are NaN if `y` has no positives or no negatives.
+pub fn roc_curve(pred: &Matrix<f64>, y: &Matrix<f64>, class: usize) -> Curve {
+    let counts = threshold_counts(pred, y, class);
+    let (positives, negatives) = counts.last().map_or((0, 0), |&(_, tp, fp)| (tp, fp));
+    let mut curve = Curve {
+        x: vec![0.0],
+        y: vec![0.0],
+        thresholds: vec![f64::INFINITY],
+    };
+    for (threshold, tp, fp) in counts {
+        curve.x.push(fp as f64 / negatives as f64);
+        curve.y.push(tp as f64 / positives as f64);
+        curve.thresholds.push(threshold);
+    }
+    curve
+}
+
+/// Area under `roc_curve`, averaged over the columns for several classes
+pub fn roc_auc(pred: &Matrix<f64>, y: &Matrix<f64>) -> f64 {
+    (0..pred.w())
+        .map(|class| roc_curve(pred, y, class).area())
+        .sum::<f64>()
+        / pred.w() as f64
+}
+
+/// Recall (x) against precision (y) of column `class` against the rest, starting from recall 0 at
+/// precision 1. For a single-column binary prediction, `class` is 0.
+pub fn pr_curve(pred: &Matrix<f64>, y: &Matrix<f64>, class: usize) -> Curve {
+    let counts = threshold_counts(pred, y, class);
+    let positives = counts.last().map_or(0, |&(_, tp, _)| tp);
+    let mut curve = Curve {
+        x: vec![0.0],
+        y: vec![1.0],
+        thresholds: vec![f64::INFINITY],
+    };
+    for (threshold, tp, fp) in counts {
+        curve.x.push(ratio(tp, positives));
+        curve.y.push(ratio(tp, tp + fp));
+        curve.thresholds.push(threshold);
+    }
+    curve
+}
+
+/// Sum over `pr_curve` of each rise in recall times the precision reached, which unlike `area`
+/// doesn't interpolate between points. Averaged over the columns for several classes.
+pub fn average_precision(pred: &Matrix<f64>, y: &Matrix<f64>) -> f64 {
+    let per_class = |class| {
+        let curve = pr_curve(pred, y, class);
+        curve
+            .x
+            .windows(2)
+            .zip(curve.y.iter().skip(1))
+            .map(|(recall, precision)| (recall[1] - recall[0]) * precision)
+            .sum::<f64>()
+    };
+    (0..pred.w()).map(per_class).sum::<f64>() / pred.w() as f64
+}
+
+/// Mean of the squared error of every element
+pub fn mse(pred: &Matrix<f64>, y: &Matrix<f64>) -> f64 {
+    check_shapes(pred, y);
+    let total: f64 = pred
+        .iter()
+        .zip(y.iter())
+        .map(|(p, y)| (p - y).powi(2))
+        .sum();
+    total / pred.len().max(1) as f64
+}
+
+pub fn rmse(pred: &Matrix<f64>, y: &Matrix<f64>) -> f64 {
+    mse(pred, y).sqrt()
+}
+
+/// Mean of the absolute error of every element
+pub fn mae(pred: &Matrix<f64>, y: &Matrix<f64>) -> f64 {
+    check_shapes(pred, y);
+    let total: f64 = pred.iter().zip(y.iter()).map(|(p, y)| (p - y).abs()).sum();
+    total / pred.len().max(1) as f64
+}
+
+fn mean(values: impl Iterator<Item = f64> + Clone) -> f64 {
+    values.clone().sum::<f64>() / values.count().max(1) as f64
+}
+
+fn variance(values: impl Iterator<Item = f64> + Clone) -> f64 {
+    let mean = mean(values.clone());
+    self::mean(values.map(|x| (x - mean).powi(2)))
+}
+
+// score of every column, averaged
+fn per_column(pred: &Matrix<f64>, y: &Matrix<f64>, score: impl Fn(&[f64], &[f64]) -> f64) -> f64 {
+    check_shapes(pred, y);
+    let column = |m: &Matrix<f64>, j| (0..m.h()).map(|i| m[(i, j)]).collect::<Vec<f64>>();
+    (0..pred.w())
+        .map(|j| score(&column(pred, j), &column(y, j)))
+        .sum::<f64>()
+        / pred.w().max(1) as f64
+}
+
+/// Coefficient of determination, 1 - SSE / total sum of squares, per column and averaged. 1 is a
+/// perfect fit and 0 is no better than predicting the mean. NaN for a constant target.
+pub fn r2(pred: &Matrix<f64>, y: &Matrix<f64>) -> f64 {
+    per_column(pred, y, |pred, y| {
+        let sse: f64 = pred.iter().zip(y).map(|(p, y)| (y - p).powi(2)).sum();
+        let y_mean = mean(y.iter().copied());
+        let sst: f64 = y.iter().map(|y| (y - y_mean).powi(2)).sum();
+        1.0 - sse / sst
+    })
+}
+
+/// 1 - Var(y - pred) / Var(y), per column and averaged. Unlike `r2`, a constant offset in the
+/// predictions isn't penalised.
+pub fn explained_variance(pred: &Matrix<f64>, y: &Matrix<f64>) -> f64 {
+    per_column(pred, y, |pred, y| {
+        let residuals = pred.iter().zip(y).map(|(p, y)| y - p);
+        1.0 - variance(residuals) / variance(y.iter().copied())
+    })
+}
@@ -1,33 +1,78 @@
+use super::dual::{Dual, HyperDual, Real};
+use super::ParseKindError;
+use std::f64::consts::{PI, SQRT_2};
+use std::fmt::{Debug, Display};
+use std::str::FromStr;
+use std::sync::Arc;
+
 pub trait Activation {
     fn prime(x: f64) -> f64;
     fn calc(x: f64) -> f64;
+    /// Identifies the activation at runtime, eg. in `ActivationFn` and saved models
+    fn name() -> &'static str {
+        std::any::type_name::<Self>()
+    }
+}
+
+/// An activation written once, generically over `Real`. `Activation::prime` is then found by
+/// evaluating it on a `Dual`, so it can never disagree with `calc`.
+pub trait AutoActivation {
+    fn eval<R: Real>(x: R) -> R;
+
+    fn second_derivative(x: f64) -> f64 {
+        Self::eval(HyperDual::variable(x)).e12
+    }
+
+    fn name() -> &'static str {
+        std::any::type_name::<Self>()
+    }
+}
+
+impl<T: AutoActivation> Activation for T {
+    fn calc(x: f64) -> f64 {
+        T::eval(x)
+    }
+    fn prime(x: f64) -> f64 {
+        T::eval(Dual::variable(x)).du
+    }
+    fn name() -> &'static str {
+        <T as AutoActivation>::name()
+    }
 }
 
+/// ln(1 + e^x)
 pub struct Softplus;
 impl Activation for Softplus {
     fn calc(x: f64) -> f64 {
-        if x > 420.0 {
-            x
-        } else {
-            (x.exp() + 1.0).ln()
-        }
+        // e^x can't overflow when the exponent is never positive
+        x.max(0.0) + (-x.abs()).exp().ln_1p()
     }
     fn prime(x: f64) -> f64 {
         Sigmoid::calc(x)
     }
+    fn name() -> &'static str {
+        "softplus"
+    }
 }
 
+/// 1 / (1 + e^-x)
 pub struct Sigmoid;
 impl Activation for Sigmoid {
     fn calc(x: f64) -> f64 {
-        if x < -420.0 {
-            0.0
-        } else {
+        // e^x can't overflow when the exponent is never positive
+        if x >= 0.0 {
             1.0 / (1.0 + (-x).exp())
+        } else {
+            let exp = x.exp();
+            exp / (1.0 + exp)
         }
     }
     fn prime(x: f64) -> f64 {
-        Self::calc(x) * (1.0 - Self::calc(x))
+        let s = Self::calc(x);
+        s * (1.0 - s)
+    }
+    fn name() -> &'static str {
+        "sigmoid"
     }
 }
 
@@ -43,4 +88,393 @@ impl Activation for ReLU {
             0.0
         }
     }
+    fn name() -> &'static str {
+        "relu"
+    }
+}
+
+pub struct Tanh;
+impl Activation for Tanh {
+    fn calc(x: f64) -> f64 {
+        x.tanh()
+    }
+    fn prime(x: f64) -> f64 {
+        let t = x.tanh();
+        1.0 - t * t
+    }
+    fn name() -> &'static str {
+        "tanh"
+    }
+}
+
+/// `ReLU`, with a slope of `LeakyReLU::SLOPE` below 0
+pub struct LeakyReLU;
+impl LeakyReLU {
+    pub const SLOPE: f64 = 0.01;
+}
+impl Activation for LeakyReLU {
+    fn calc(x: f64) -> f64 {
+        if x > 0.0 {
+            x
+        } else {
+            Self::SLOPE * x
+        }
+    }
+    fn prime(x: f64) -> f64 {
+        if x > 0.0 {
+            1.0
+        } else {
+            Self::SLOPE
+        }
+    }
+    fn name() -> &'static str {
+        "leaky_relu"
+    }
+}
+
+/// x above 0, `ELU::ALPHA` * (e^x - 1) below
+pub struct ELU;
+impl ELU {
+    pub const ALPHA: f64 = 1.0;
+}
+impl Activation for ELU {
+    fn calc(x: f64) -> f64 {
+        if x > 0.0 {
+            x
+        } else {
+            Self::ALPHA * x.exp_m1()
+        }
+    }
+    fn prime(x: f64) -> f64 {
+        if x > 0.0 {
+            1.0
+        } else {
+            Self::ALPHA * x.exp()
+        }
+    }
+    fn name() -> &'static str {
+        "elu"
+    }
+}
+
+/// `ELU` scaled so activations keep zero mean and unit variance (Klambauer et al., 2017)
+pub struct SELU;
+impl SELU {
+    pub const ALPHA: f64 = 1.673_263_242_354_377_3;
+    pub const SCALE: f64 = 1.050_700_987_355_480_5;
+}
+impl Activation for SELU {
+    fn calc(x: f64) -> f64 {
+        if x > 0.0 {
+            Self::SCALE * x
+        } else {
+            Self::SCALE * Self::ALPHA * x.exp_m1()
+        }
+    }
+    fn prime(x: f64) -> f64 {
+        if x > 0.0 {
+            Self::SCALE
+        } else {
+            Self::SCALE * Self::ALPHA * x.exp()
+        }
+    }
+    fn name() -> &'static str {
+        "selu"
+    }
+}
+
+// 2 / sqrt(pi) * e^-x^2 * sum of 2^n x^(2n + 1) / (1 * 3 * ... * (2n + 1)), every term positive
+fn erf_series(x: f64) -> f64 {
+    let mut term = x;
+    let mut sum = x;
+    let mut n = 0.0;
+    while term.abs() > sum.abs() * f64::EPSILON {
+        n += 1.0;
+        term *= 2.0 * x * x / (2.0 * n + 1.0);
+        sum += term;
+    }
+    2.0 / PI.sqrt() * (-x * x).exp() * sum
+}
+
+// continued fraction, accurate for x >= 3 where erf_series would need too many terms
+fn erfc_fraction(x: f64) -> f64 {
+    let mut fraction = x;
+    for n in (1..=60).rev() {
+        fraction = x + n as f64 / 2.0 / fraction;
+    }
+    (-x * x).exp() / PI.sqrt() / fraction
+}
+
+/// Standard normal cumulative distribution function
+pub fn normal_cdf(x: f64) -> f64 {
+    let z = x / SQRT_2;
+    if z.abs() < 3.0 {
+        0.5 * (1.0 + erf_series(z))
+    } else if z > 0.0 {
+        1.0 - 0.5 * erfc_fraction(z)
+    } else {
+        0.5 * erfc_fraction(-z)
+    }
+}
+
+/// Standard normal probability density function
+pub fn normal_pdf(x: f64) -> f64 {
+    (-0.5 * x * x).exp() / (2.0 * PI).sqrt()
+}
+
+/// x * P(X <= x) for standard normal X
+pub struct GELU;
+impl Activation for GELU {
+    fn calc(x: f64) -> f64 {
+        x * normal_cdf(x)
+    }
+    fn prime(x: f64) -> f64 {
+        normal_cdf(x) + x * normal_pdf(x)
+    }
+    fn name() -> &'static str {
+        "gelu"
+    }
+}
+
+/// `GELU`, approximated with tanh as in the original paper
+pub struct GELUTanh;
+impl GELUTanh {
+    const COEFFICIENT: f64 = 0.044715;
+
+    // sqrt(2 / pi) * (x + 0.044715 x^3)
+    fn inner(x: f64) -> f64 {
+        (2.0 / PI).sqrt() * (x + Self::COEFFICIENT * x * x * x)
+    }
+}
+impl Activation for GELUTanh {
+    fn calc(x: f64) -> f64 {
+        0.5 * x * (1.0 + Self::inner(x).tanh())
+    }
+    fn prime(x: f64) -> f64 {
+        let t = Self::inner(x).tanh();
+        let inner_prime = (2.0 / PI).sqrt() * (1.0 + 3.0 * Self::COEFFICIENT * x * x);
+        0.5 * (1.0 + t) + 0.5 * x * (1.0 - t * t) * inner_prime
+    }
+    fn name() -> &'static str {
+        "gelu_tanh"
+    }
+}
+
+/// x * sigmoid(x), also known as `Swish`
+pub struct SiLU;
+impl Activation for SiLU {
+    fn calc(x: f64) -> f64 {
+        x * Sigmoid::calc(x)
+    }
+    fn prime(x: f64) -> f64 {
+        let s = Sigmoid::calc(x);
+        s + x * s * (1.0 - s)
+    }
+    fn name() -> &'static str {
+        "silu"
+    }
+}
+
+pub type Swish = SiLU;
+
+/// x * tanh(softplus(x))
+pub struct Mish;
+impl Activation for Mish {
+    fn calc(x: f64) -> f64 {
+        x * Softplus::calc(x).tanh()
+    }
+    fn prime(x: f64) -> f64 {
+        let t = Softplus::calc(x).tanh();
+        t + x * (1.0 - t * t) * Sigmoid::calc(x)
+    }
+    fn name() -> &'static str {
+        "mish"
+    }
+}
+
+/// x / 6 + 1 / 2, clamped to [0, 1]
+pub struct HardSigmoid;
+impl Activation for HardSigmoid {
+    fn calc(x: f64) -> f64 {
+        (x / 6.0 + 0.5).clamp(0.0, 1.0)
+    }
+    fn prime(x: f64) -> f64 {
+        if x > -3.0 && x < 3.0 {
+            1.0 / 6.0
+        } else {
+            0.0
+        }
+    }
+    fn name() -> &'static str {
+        "hard_sigmoid"
+    }
+}
+
+/// x clamped to [-1, 1]
+pub struct HardTanh;
+impl Activation for HardTanh {
+    fn calc(x: f64) -> f64 {
+        x.clamp(-1.0, 1.0)
+    }
+    fn prime(x: f64) -> f64 {
+        if x > -1.0 && x < 1.0 {
+            1.0
+        } else {
+            0.0
+        }
+    }
+    fn name() -> &'static str {
+        "hard_tanh"
+    }
+}
+
+/// x / (1 + |x|)
+pub struct Softsign;
+impl Activation for Softsign {
+    fn calc(x: f64) -> f64 {
+        x / (1.0 + x.abs())
+    }
+    fn prime(x: f64) -> f64 {
+        let denominator = 1.0 + x.abs();
+        1.0 / (denominator * denominator)
+    }
+    fn name() -> &'static str {
+        "softsign"
+    }
+}
+
+/// No activation, eg. for the output of a regression model
+pub struct Identity;
+impl Activation for Identity {
+    fn calc(x: f64) -> f64 {
+        x
+    }
+    fn prime(_: f64) -> f64 {
+        1.0
+    }
+    fn name() -> &'static str {
+        "identity"
+    }
+}
+
+// generates ActivationKind, with a variant for every built-in activation type
+macro_rules! activation_kinds {
+    ($($kind: ident => $type: ty),* $(,)?) => {
+        /// Every built-in activation, for picking one at runtime. Parses from, and displays as,
+        /// `Activation::name`.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum ActivationKind {
+            $($kind),*
+        }
+
+        impl ActivationKind {
+            pub const ALL: &'static [ActivationKind] = &[$(ActivationKind::$kind),*];
+
+            pub fn calc(&self, x: f64) -> f64 {
+                match self {
+                    $(ActivationKind::$kind => <$type as Activation>::calc(x)),*
+                }
+            }
+
+            pub fn prime(&self, x: f64) -> f64 {
+                match self {
+                    $(ActivationKind::$kind => <$type as Activation>::prime(x)),*
+                }
+            }
+
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(ActivationKind::$kind => <$type as Activation>::name()),*
+                }
+            }
+        }
+    };
+}
+
+activation_kinds! {
+    Softplus => Softplus,
+    Sigmoid => Sigmoid,
+    ReLU => ReLU,
+    Tanh => Tanh,
+    LeakyReLU => LeakyReLU,
+    ELU => ELU,
+    SELU => SELU,
+    GELU => GELU,
+    GELUTanh => GELUTanh,
+    SiLU => SiLU,
+    Mish => Mish,
+    HardSigmoid => HardSigmoid,
+    HardTanh => HardTanh,
+    Softsign => Softsign,
+    Identity => Identity,
+}
+
+impl Display for ActivationKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for ActivationKind {
+    type Err = ParseKindError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ActivationKind::ALL
+            .iter()
+            .find(|kind| kind.name().eq_ignore_ascii_case(s.trim()))
+            .copied()
+            .ok_or_else(|| ParseKindError::new("activation", s))
+    }
+}
+
+type ScalarFn = Arc<dyn Fn(f64) -> f64 + Send + Sync>;
+
+/// An activation chosen at runtime: an `ActivationKind`, any `Activation` type, or a pair of
+/// closures.
+#[derive(Clone)]
+pub struct ActivationFn {
+    name: String,
+    calc: ScalarFn,
+    prime: ScalarFn,
+}
+
+impl ActivationFn {
+    pub fn new<F, P>(name: &str, calc: F, prime: P) -> Self
+    where
+        F: Fn(f64) -> f64 + Send + Sync + 'static,
+        P: Fn(f64) -> f64 + Send + Sync + 'static,
+    {
+        Self {
+            name: name.to_string(),
+            calc: Arc::new(calc),
+            prime: Arc::new(prime),
+        }
+    }
+
+    pub fn of<A: Activation + 'static>() -> Self {
+        Self::new(A::name(), A::calc, A::prime)
+    }
+
+    pub fn calc(&self, x: f64) -> f64 {
+        (self.calc)(x)
+    }
+
+    pub fn prime(&self, x: f64) -> f64 {
+        (self.prime)(x)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl From<ActivationKind> for ActivationFn {
+    fn from(kind: ActivationKind) -> Self {
+        Self::new(kind.name(), move |x| kind.calc(x), move |x| kind.prime(x))
+    }
+}
+
+impl Debug for ActivationFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ActivationFn({})", self.name)
+    }
 }
@@ -0,0 +1,253 @@
+use super::activations::Activation;
+use super::cost::Cost;
+use crate::algebra::{MatLike, Matrix};
+use std::cell::RefCell;
+use std::ops::{Add, Mul, Sub};
+
+// every op stores the tape indices of its operands, and whatever else it needs to push the
+// gradient back through itself
+enum Op {
+    Leaf,
+    MatMul(usize, usize),
+    Add(usize, usize),
+    Sub(usize, usize),
+    MulElementWise(usize, usize),
+    Scale(usize, f64),
+    Transpose(usize),
+    Apply(usize, fn(f64) -> f64), // derivative of the applied function
+    Sum(usize),
+    Mean(usize),
+    Cost(usize, Matrix<f64>), // cost_wrt_pred, computed on the forward pass
+}
+
+struct Node {
+    value: Matrix<f64>,
+    op: Op,
+}
+
+/// Records every operation performed on its `Var`s so that `Var::backward` can replay them in
+/// reverse.
+#[derive(Default)]
+pub struct Tape {
+    nodes: RefCell<Vec<Node>>,
+}
+
+#[derive(Clone, Copy)]
+pub struct Var<'t> {
+    tape: &'t Tape,
+    index: usize,
+}
+
+/// Gradients of the value `backward` was called on, indexed by `Var`.
+pub struct Gradients {
+    grads: Vec<Option<Matrix<f64>>>,
+}
+
+impl Tape {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn var(&self, value: Matrix<f64>) -> Var<'_> {
+        self.push(value, Op::Leaf)
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn push(&self, value: Matrix<f64>, op: Op) -> Var<'_> {
+        let mut nodes = self.nodes.borrow_mut();
+        nodes.push(Node { value, op });
+        Var {
+            tape: self,
+            index: nodes.len() - 1,
+        }
+    }
+
+    fn value(&self, index: usize) -> Matrix<f64> {
+        self.nodes.borrow()[index].value.clone()
+    }
+}
+
+impl<'t> Var<'t> {
+    pub fn value(&self) -> Matrix<f64> {
+        self.tape.value(self.index)
+    }
+
+    pub fn matmul(self, rhs: Var<'t>) -> Var<'t> {
+        self.same_tape(&rhs);
+        let value = self.value() * rhs.value();
+        self.tape.push(value, Op::MatMul(self.index, rhs.index))
+    }
+
+    pub fn mul_element_wise(self, rhs: Var<'t>) -> Var<'t> {
+        self.same_tape(&rhs);
+        let value = self.value().mul_element_wise(rhs.value());
+        self.tape
+            .push(value, Op::MulElementWise(self.index, rhs.index))
+    }
+
+    pub fn scale(self, factor: f64) -> Var<'t> {
+        let value = self.value() * factor;
+        self.tape.push(value, Op::Scale(self.index, factor))
+    }
+
+    pub fn transpose(self) -> Var<'t> {
+        let value = self.value().new_transposed();
+        self.tape.push(value, Op::Transpose(self.index))
+    }
+
+    /// Applies `function` element-wise. `derivative` is used on the backward pass.
+    pub fn apply(self, function: fn(f64) -> f64, derivative: fn(f64) -> f64) -> Var<'t> {
+        let value = self.value().apply(function);
+        self.tape.push(value, Op::Apply(self.index, derivative))
+    }
+
+    pub fn activate<A: Activation>(self) -> Var<'t> {
+        self.apply(A::calc, A::prime)
+    }
+
+    pub fn sum(self) -> Var<'t> {
+        let value = Matrix::new(vec![self.value().iter().sum()], 1, 1);
+        self.tape.push(value, Op::Sum(self.index))
+    }
+
+    pub fn mean(self) -> Var<'t> {
+        let input = self.value();
+        let value = Matrix::new(vec![input.iter().sum::<f64>() / input.len() as f64], 1, 1);
+        self.tape.push(value, Op::Mean(self.index))
+    }
+
+    /// Scalar cost of `self` as a prediction of `actual`.
+    pub fn cost<C: Cost>(self, actual: &Matrix<f64>) -> Var<'t> {
+        let pred = self.value();
+        let value = Matrix::new(vec![C::calc(&pred, actual)], 1, 1);
+        let cost_wrt_pred = C::prime(&pred, actual);
+        self.tape.push(value, Op::Cost(self.index, cost_wrt_pred))
+    }
+
+    /// Gradient of `self` with respect to every `Var` recorded before it. If `self` is not a
+    /// scalar, the gradient of the sum of its elements is calculated.
+    pub fn backward(self) -> Gradients {
+        let nodes = self.tape.nodes.borrow();
+        let mut grads: Vec<Option<Matrix<f64>>> = vec![None; nodes.len()];
+        let output = &nodes[self.index].value;
+        grads[self.index] = Some(Matrix::new_uniform(1.0, output.w(), output.h()));
+
+        for i in (0..=self.index).rev() {
+            let Some(grad) = grads[i].take() else {
+                continue;
+            };
+            let node = &nodes[i];
+            match node.op {
+                Op::Leaf => {}
+                Op::MatMul(a, b) => {
+                    accumulate(&mut grads, a, &grad * nodes[b].value.new_transposed());
+                    accumulate(&mut grads, b, nodes[a].value.new_transposed() * &grad);
+                }
+                Op::Add(a, b) => {
+                    accumulate(&mut grads, a, grad.clone());
+                    accumulate(&mut grads, b, grad.clone());
+                }
+                Op::Sub(a, b) => {
+                    accumulate(&mut grads, a, grad.clone());
+                    accumulate(&mut grads, b, &grad * -1.0);
+                }
+                Op::MulElementWise(a, b) => {
+                    accumulate(&mut grads, a, grad.mul_element_wise(nodes[b].value.clone()));
+                    accumulate(&mut grads, b, grad.mul_element_wise(nodes[a].value.clone()));
+                }
+                Op::Scale(a, factor) => accumulate(&mut grads, a, &grad * factor),
+                Op::Transpose(a) => accumulate(&mut grads, a, grad.new_transposed()),
+                Op::Apply(a, derivative) => {
+                    let local = nodes[a].value.apply(derivative);
+                    accumulate(&mut grads, a, grad.mul_element_wise(local));
+                }
+                Op::Sum(a) => {
+                    let input = &nodes[a].value;
+                    accumulate(
+                        &mut grads,
+                        a,
+                        Matrix::new_uniform(grad[(0, 0)], input.w(), input.h()),
+                    );
+                }
+                Op::Mean(a) => {
+                    let input = &nodes[a].value;
+                    let share = grad[(0, 0)] / input.len() as f64;
+                    accumulate(
+                        &mut grads,
+                        a,
+                        Matrix::new_uniform(share, input.w(), input.h()),
+                    );
+                }
+                Op::Cost(a, ref cost_wrt_pred) => {
+                    accumulate(&mut grads, a, cost_wrt_pred * grad[(0, 0)]);
+                }
+            }
+            // leaves keep their gradient, everything else is only needed on the way down
+            if let Op::Leaf = node.op {
+                grads[i] = Some(grad);
+            }
+        }
+        Gradients { grads }
+    }
+
+    fn same_tape(&self, other: &Var<'t>) {
+        assert!(
+            std::ptr::eq(self.tape, other.tape),
+            "vars belong to different tapes"
+        );
+    }
+}
+
+fn accumulate(grads: &mut [Option<Matrix<f64>>], index: usize, grad: Matrix<f64>) {
+    grads[index] = Some(match grads[index].take() {
+        Some(existing) => existing + grad,
+        None => grad,
+    });
+}
+
+impl Gradients {
+    /// `None` if `var` is not a leaf, or the output does not depend on it.
+    pub fn wrt(&self, var: Var) -> Option<&Matrix<f64>> {
+        self.grads.get(var.index)?.as_ref()
+    }
+}
+
+impl<'t> Add for Var<'t> {
+    type Output = Var<'t>;
+    fn add(self, rhs: Var<'t>) -> Var<'t> {
+        self.same_tape(&rhs);
+        let value = self.value() + rhs.value();
+        self.tape.push(value, Op::Add(self.index, rhs.index))
+    }
+}
+
+impl<'t> Sub for Var<'t> {
+    type Output = Var<'t>;
+    fn sub(self, rhs: Var<'t>) -> Var<'t> {
+        self.same_tape(&rhs);
+        let value = self.value() - rhs.value();
+        self.tape.push(value, Op::Sub(self.index, rhs.index))
+    }
+}
+
+// `*` is matrix multiplication, same as for `Matrix`
+impl<'t> Mul for Var<'t> {
+    type Output = Var<'t>;
+    fn mul(self, rhs: Var<'t>) -> Var<'t> {
+        self.matmul(rhs)
+    }
+}
+
+impl<'t> Mul<f64> for Var<'t> {
+    type Output = Var<'t>;
+    fn mul(self, rhs: f64) -> Var<'t> {
+        self.scale(rhs)
+    }
+}
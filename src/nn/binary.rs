@@ -0,0 +1,76 @@
+// Little-endian readers and writers shared by the binary model and checkpoint formats
+use super::ModelError;
+use crate::algebra::{MatLike, Matrix};
+use std::io::{Read, Write};
+
+pub(crate) fn read_bytes<R: Read, const N: usize>(reader: &mut R) -> Result<[u8; N], ModelError> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+pub(crate) fn read_u32<R: Read>(reader: &mut R) -> Result<u32, ModelError> {
+    read_bytes(reader).map(u32::from_le_bytes)
+}
+
+pub(crate) fn read_u64<R: Read>(reader: &mut R) -> Result<u64, ModelError> {
+    read_bytes(reader).map(u64::from_le_bytes)
+}
+
+pub(crate) fn read_f64<R: Read>(reader: &mut R) -> Result<f64, ModelError> {
+    read_bytes(reader).map(f64::from_le_bytes)
+}
+
+/// Reads `len` bytes as they arrive, so a corrupt length runs into the end of the file rather
+/// than allocating all of it up front.
+pub(crate) fn read_vec<R: Read>(
+    reader: &mut R,
+    len: u64,
+    what: &str,
+) -> Result<Vec<u8>, ModelError> {
+    let mut bytes = Vec::new();
+    reader.by_ref().take(len).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(ModelError::format(format!("truncated {}", what)));
+    }
+    Ok(bytes)
+}
+
+pub(crate) fn read_string<R: Read>(reader: &mut R) -> Result<String, ModelError> {
+    let len = read_u32(reader)?;
+    let bytes = read_vec(reader, len.into(), "name")?;
+    String::from_utf8(bytes).map_err(|_| ModelError::format("name is not utf-8"))
+}
+
+pub(crate) fn write_string<W: Write>(writer: &mut W, s: &str) -> Result<(), ModelError> {
+    writer.write_all(&(s.len() as u32).to_le_bytes())?;
+    writer.write_all(s.as_bytes())?;
+    Ok(())
+}
+
+pub(crate) fn read_matrix<R: Read>(
+    reader: &mut R,
+    w: usize,
+    h: usize,
+) -> Result<Matrix<f64>, ModelError> {
+    let len = w
+        .checked_mul(h)
+        .and_then(|len| len.checked_mul(8))
+        .ok_or_else(|| ModelError::format("layer too large"))?;
+    let bytes = read_vec(reader, len as u64, "matrix")?;
+    let data = bytes
+        .chunks_exact(8)
+        .map(|chunk| f64::from_le_bytes(chunk.try_into().unwrap()))
+        .collect();
+    Ok(Matrix::new(data, w, h))
+}
+
+pub(crate) fn write_matrix<W: Write>(
+    writer: &mut W,
+    matrix: &Matrix<f64>,
+) -> Result<(), ModelError> {
+    for x in matrix.iter() {
+        writer.write_all(&x.to_le_bytes())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,287 @@
+use super::json::Json;
+use crate::algebra::{MatLike, Matrix};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::Instant;
+
+/// What `Callback::on_batch_grad` is told about a batch before its step is taken.
+#[derive(Debug, Clone, Copy)]
+pub struct BatchGrad<'a> {
+    pub epoch: usize,
+    /// Index of the batch within the epoch
+    pub batch: usize,
+    /// Steps taken so far, not including this batch's
+    pub step: usize,
+    /// Mean cost of the batch's cases
+    pub loss: f64,
+    /// Gradient of `loss` for every param, in `Trainable::params_mut` order
+    pub grads: &'a [Matrix<f64>],
+}
+
+/// What `Callback::on_batch_end` is told about the step just taken.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BatchEnd {
+    /// Epochs and steps are counted across calls to `fit`, like `LrSchedule`'s
+    pub epoch: usize,
+    /// Index of the batch within the epoch
+    pub batch: usize,
+    pub batches: usize,
+    /// Steps taken so far, including this one
+    pub step: usize,
+    pub cases: usize,
+    /// Mean cost of the batch's cases
+    pub loss: f64,
+    pub learning_rate: f64,
+}
+
+/// What `Callback::on_epoch_end` is told about the epoch just finished.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EpochEnd<'a> {
+    pub epoch: usize,
+    /// The epoch `fit` stops before, unless stopped early
+    pub epochs: usize,
+    pub step: usize,
+    /// Cases trained on during the epoch
+    pub cases: usize,
+    /// Mean cost of every case seen during the epoch
+    pub loss: f64,
+    /// Learning rate of the last step
+    pub learning_rate: f64,
+    /// Name of the validation `Metric`, and its score, if there's validation data
+    pub validation: Option<(&'a str, f64)>,
+    /// Time spent on the epoch
+    pub seconds: f64,
+}
+
+/// Hooks `Trainer` calls as it goes. Returning `Err` from any of them aborts the run with that
+/// reason, as `TrainError::Aborted`.
+pub trait Callback {
+    fn on_epoch_begin(&mut self, _epoch: usize) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn on_batch_begin(&mut self, _epoch: usize, _batch: usize) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Called between working out a batch's gradients and stepping the params with them, so
+    /// aborting here leaves the params as they were.
+    fn on_batch_grad(&mut self, _batch: &BatchGrad) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn on_batch_end(&mut self, _batch: &BatchEnd) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn on_epoch_end(&mut self, _epoch: &EpochEnd) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+fn write_error(error: io::Error) -> String {
+    format!("couldn't write training log: {}", error)
+}
+
+/// Writes a line per epoch, eg.
+/// `epoch 3/10  loss 0.012345  val_accuracy 0.9800  lr 0.01  5120 cases/s`,
+/// and optionally one every `every_batches` batches.
+pub struct ProgressLine<W: Write> {
+    writer: W,
+    every_batches: Option<usize>,
+    epoch_start: Option<Instant>,
+}
+
+impl ProgressLine<io::Stderr> {
+    pub fn stderr() -> Self {
+        Self::new(io::stderr())
+    }
+}
+
+impl<W: Write> ProgressLine<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            every_batches: None,
+            epoch_start: None,
+        }
+    }
+
+    /// Also writes a line every `batches` batches.
+    pub fn every_batches(mut self, batches: usize) -> Self {
+        assert!(batches > 0, "batches must be positive");
+        self.every_batches = Some(batches);
+        self
+    }
+}
+
+impl<W: Write> Callback for ProgressLine<W> {
+    fn on_epoch_begin(&mut self, _epoch: usize) -> Result<(), String> {
+        self.epoch_start = Some(Instant::now());
+        Ok(())
+    }
+
+    fn on_batch_end(&mut self, batch: &BatchEnd) -> Result<(), String> {
+        let Some(every) = self.every_batches else {
+            return Ok(());
+        };
+        if !(batch.batch + 1).is_multiple_of(every) {
+            return Ok(());
+        }
+        let elapsed = self
+            .epoch_start
+            .map_or(0.0, |start| start.elapsed().as_secs_f64());
+        writeln!(
+            self.writer,
+            "epoch {}  batch {}/{}  loss {:.6}  lr {}  {:.1}s",
+            batch.epoch + 1,
+            batch.batch + 1,
+            batch.batches,
+            batch.loss,
+            batch.learning_rate,
+            elapsed
+        )
+        .map_err(write_error)
+    }
+
+    fn on_epoch_end(&mut self, epoch: &EpochEnd) -> Result<(), String> {
+        let mut line = format!(
+            "epoch {}/{}  loss {:.6}",
+            epoch.epoch + 1,
+            epoch.epochs,
+            epoch.loss
+        );
+        if let Some((metric, score)) = epoch.validation {
+            line += &format!("  val_{} {:.6}", metric, score);
+        }
+        line += &format!("  lr {}", epoch.learning_rate);
+        if epoch.seconds > 0.0 {
+            line += &format!("  {:.0} cases/s", epoch.cases as f64 / epoch.seconds);
+        }
+        writeln!(self.writer, "{}", line).map_err(write_error)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// A header, then `epoch,step,loss,learning_rate,validation,seconds` per epoch, with the
+    /// validation column empty if there's no validation data
+    Csv,
+    /// A JSON object per epoch with the same fields, and the validation metric's name
+    JsonLines,
+}
+
+/// Writes the numbers of every epoch as a CSV or JSON Lines row, for plotting or comparing runs.
+pub struct MetricsLogger<W: Write> {
+    writer: W,
+    format: LogFormat,
+    wrote_header: bool,
+}
+
+impl MetricsLogger<BufWriter<File>> {
+    /// Creates (or truncates) `path`.
+    pub fn create<P: AsRef<Path>>(path: P, format: LogFormat) -> io::Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?), format))
+    }
+}
+
+impl<W: Write> MetricsLogger<W> {
+    pub fn new(writer: W, format: LogFormat) -> Self {
+        Self {
+            writer,
+            format,
+            wrote_header: false,
+        }
+    }
+
+    fn write_row(&mut self, epoch: &EpochEnd) -> io::Result<()> {
+        match self.format {
+            LogFormat::Csv => {
+                if !self.wrote_header {
+                    writeln!(
+                        self.writer,
+                        "epoch,step,loss,learning_rate,validation,seconds"
+                    )?;
+                    self.wrote_header = true;
+                }
+                let validation = epoch
+                    .validation
+                    .map_or(String::new(), |(_, score)| format!("{:?}", score));
+                writeln!(
+                    self.writer,
+                    "{},{},{:?},{:?},{},{:?}",
+                    epoch.epoch,
+                    epoch.step,
+                    epoch.loss,
+                    epoch.learning_rate,
+                    validation,
+                    epoch.seconds
+                )?;
+            }
+            LogFormat::JsonLines => {
+                let (metric, score) = match epoch.validation {
+                    Some((metric, score)) => {
+                        (Json::String(metric.to_string()), Json::Number(score))
+                    }
+                    None => (Json::Null, Json::Null),
+                };
+                let row = Json::object([
+                    ("epoch", Json::Number(epoch.epoch as f64)),
+                    ("step", Json::Number(epoch.step as f64)),
+                    ("loss", Json::Number(epoch.loss)),
+                    ("learning_rate", Json::Number(epoch.learning_rate)),
+                    ("metric", metric),
+                    ("validation", score),
+                    ("seconds", Json::Number(epoch.seconds)),
+                ]);
+                writeln!(self.writer, "{}", row)?;
+            }
+        }
+        // flushed every epoch, so the log is readable while training runs
+        self.writer.flush()
+    }
+}
+
+impl<W: Write> Callback for MetricsLogger<W> {
+    fn on_epoch_end(&mut self, epoch: &EpochEnd) -> Result<(), String> {
+        self.write_row(epoch).map_err(write_error)
+    }
+}
+
+/// Aborts the run as soon as a batch loss, gradient or validation score is NaN or infinite,
+/// before the step that would put it into the params.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NanGuard;
+
+impl Callback for NanGuard {
+    fn on_batch_grad(&mut self, batch: &BatchGrad) -> Result<(), String> {
+        if !batch.loss.is_finite() {
+            Err(format!(
+                "loss is {} at epoch {} batch {}",
+                batch.loss, batch.epoch, batch.batch
+            ))
+        } else if let Some(param) = batch
+            .grads
+            .iter()
+            .position(|grad| grad.iter().any(|g| !g.is_finite()))
+        {
+            Err(format!(
+                "gradient of param {} isn't finite at epoch {} batch {}",
+                param, batch.epoch, batch.batch
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn on_epoch_end(&mut self, epoch: &EpochEnd) -> Result<(), String> {
+        match epoch.validation {
+            Some((metric, score)) if !score.is_finite() => Err(format!(
+                "validation {} is {} at epoch {}",
+                metric, score, epoch.epoch
+            )),
+            _ => Ok(()),
+        }
+    }
+}
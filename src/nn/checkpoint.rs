@@ -0,0 +1,398 @@
+use super::binary::{read_bytes, read_f64, read_matrix, read_string, read_u32, read_u64, read_vec};
+use super::binary::{write_matrix, write_string};
+use super::optim::OptimizerState;
+use super::train::{BestEpoch, History};
+use super::ModelError;
+use crate::algebra::{MatLike, Matrix};
+use rand_chacha::ChaCha12Rng;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+// Binary layout, every number little-endian, counts and sizes as u64 unless noted:
+//   MAGIC, VERSION as u32, step, epoch, run_start,
+//   1 then the rng's 32 byte seed, stream and word position (u128), or 0 without an rng,
+//   number of cases then the order they're in,
+//   1 then the epoch's next batch, loss so far (f64) and learning rate (f64), or 0 between epochs,
+//   the history's schedule (u32 byte count then utf-8), its losses, learning rates and validation
+//   scores,
+//   1 then the best epoch, its score (f64), stale epochs, and its params and model state as
+//   below, or 0,
+//   number of params as u32, then every param as w, h and its elements,
+//   the model's state as a byte count then those bytes,
+//   optimizer steps, number of buffers as u32, then every buffer as a u32 count of params,
+//   number of schedule state values as u32, then every value
+const MAGIC: &[u8; 4] = b"MLCK";
+const VERSION: u32 = 1;
+const PREFIX: &str = "checkpoint-";
+const EXTENSION: &str = "ckpt";
+
+/// How often `Trainer` writes a checkpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckpointEvery {
+    Steps(usize),
+    Epochs(usize),
+}
+
+/// Where and how often `Trainer` writes checkpoints, and how many to keep.
+#[derive(Debug, Clone)]
+pub struct CheckpointConfig {
+    pub dir: PathBuf,
+    pub every: CheckpointEvery,
+    /// Older checkpoints in `dir` are deleted once there are more than this
+    pub keep: usize,
+}
+
+impl CheckpointConfig {
+    /// Keeps the last 3 checkpoints.
+    pub fn new<P: Into<PathBuf>>(dir: P, every: CheckpointEvery) -> Self {
+        let interval = match every {
+            CheckpointEvery::Steps(n) | CheckpointEvery::Epochs(n) => n,
+        };
+        assert!(interval > 0, "checkpoint interval must be positive");
+        Self {
+            dir: dir.into(),
+            every,
+            keep: 3,
+        }
+    }
+
+    pub fn keep(mut self, keep: usize) -> Self {
+        assert!(keep > 0, "must keep at least 1 checkpoint");
+        self.keep = keep;
+        self
+    }
+}
+
+/// Where a `ChaCha12Rng` is in its stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RngState {
+    pub seed: [u8; 32],
+    pub stream: u64,
+    pub word_pos: u128,
+}
+
+impl RngState {
+    pub fn of(rng: &ChaCha12Rng) -> Self {
+        Self {
+            seed: rng.get_seed(),
+            stream: rng.get_stream(),
+            word_pos: rng.get_word_pos(),
+        }
+    }
+
+    pub fn to_rng(&self) -> ChaCha12Rng {
+        use rand::SeedableRng;
+        let mut rng = ChaCha12Rng::from_seed(self.seed);
+        rng.set_stream(self.stream);
+        rng.set_word_pos(self.word_pos);
+        rng
+    }
+
+    /// The seed, then the stream and word position little-endian
+    pub fn to_bytes(&self) -> [u8; 56] {
+        let mut bytes = [0; 56];
+        bytes[..32].copy_from_slice(&self.seed);
+        bytes[32..40].copy_from_slice(&self.stream.to_le_bytes());
+        bytes[40..].copy_from_slice(&self.word_pos.to_le_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: [u8; 56]) -> Self {
+        Self {
+            seed: bytes[..32].try_into().unwrap(),
+            stream: u64::from_le_bytes(bytes[32..40].try_into().unwrap()),
+            word_pos: u128::from_le_bytes(bytes[40..].try_into().unwrap()),
+        }
+    }
+}
+
+/// How far `Trainer` had got through an epoch when the checkpoint was written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpochProgress {
+    /// Index of the next batch in `Checkpoint::order`
+    pub next_batch: usize,
+    /// Sum of the loss of every case seen so far this epoch
+    pub total_loss: f64,
+    /// Learning rate of the last step
+    pub learning_rate: f64,
+}
+
+/// Everything `Trainer::resume` needs to carry on a run exactly where it was left.
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    pub step: usize,
+    pub epoch: usize,
+    /// Epoch the interrupted `fit` started at, so the resumed one knows how many are left
+    pub run_start: usize,
+    /// The shuffling RNG, if the cases are shuffled
+    pub rng: Option<RngState>,
+    /// Order of the cases in the current epoch, or the last one between epochs. Each epoch
+    /// shuffles the previous one's order.
+    pub order: Vec<usize>,
+    /// `None` if the checkpoint was written between epochs
+    pub progress: Option<EpochProgress>,
+    /// History of the interrupted `fit` so far
+    pub history: History,
+    /// Best validation score so far, if there is validation data
+    pub best: Option<BestEpoch>,
+    /// Every param of the model, in `Trainable::params_mut` order
+    pub params: Vec<Matrix<f64>>,
+    /// `Trainable::state` of the model, eg. the RNGs of its dropout layers
+    pub model: Vec<u8>,
+    pub optimizer: OptimizerState,
+    pub schedule: Vec<f64>,
+}
+
+fn write_u64<W: Write>(writer: &mut W, x: usize) -> Result<(), ModelError> {
+    writer.write_all(&(x as u64).to_le_bytes())?;
+    Ok(())
+}
+
+fn write_floats<W: Write>(writer: &mut W, floats: &[f64]) -> Result<(), ModelError> {
+    write_u64(writer, floats.len())?;
+    for x in floats {
+        writer.write_all(&x.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+fn read_usize<R: Read>(reader: &mut R) -> Result<usize, ModelError> {
+    read_u64(reader).map(|x| x as usize)
+}
+
+fn read_floats<R: Read>(reader: &mut R) -> Result<Vec<f64>, ModelError> {
+    (0..read_u64(reader)?).map(|_| read_f64(reader)).collect()
+}
+
+fn read_flag<R: Read>(reader: &mut R) -> Result<bool, ModelError> {
+    match read_bytes::<R, 1>(reader)? {
+        [0] => Ok(false),
+        [1] => Ok(true),
+        _ => Err(ModelError::format("corrupt checkpoint")),
+    }
+}
+
+fn write_matrices<W: Write>(writer: &mut W, matrices: &[Matrix<f64>]) -> Result<(), ModelError> {
+    writer.write_all(&(matrices.len() as u32).to_le_bytes())?;
+    for matrix in matrices {
+        write_u64(writer, matrix.w())?;
+        write_u64(writer, matrix.h())?;
+        write_matrix(writer, matrix)?;
+    }
+    Ok(())
+}
+
+fn read_matrices<R: Read>(reader: &mut R) -> Result<Vec<Matrix<f64>>, ModelError> {
+    (0..read_u32(reader)?)
+        .map(|_| {
+            let w = read_usize(reader)?;
+            let h = read_usize(reader)?;
+            read_matrix(reader, w, h)
+        })
+        .collect()
+}
+
+impl Checkpoint {
+    /// Writes to a temporary file next to `path` first, so an interrupted save never leaves a
+    /// truncated checkpoint behind.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ModelError> {
+        let path = path.as_ref();
+        let temporary = path.with_extension("tmp");
+        let mut writer = BufWriter::new(File::create(&temporary)?);
+        self.write_to(&mut writer)?;
+        writer.flush()?;
+        drop(writer);
+        fs::rename(temporary, path)?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ModelError> {
+        Self::read_from(&mut BufReader::new(File::open(path)?))
+    }
+
+    /// `save` to any writer
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), ModelError> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        write_u64(writer, self.step)?;
+        write_u64(writer, self.epoch)?;
+        write_u64(writer, self.run_start)?;
+        match &self.rng {
+            Some(rng) => {
+                writer.write_all(&[1])?;
+                writer.write_all(&rng.to_bytes())?;
+            }
+            None => writer.write_all(&[0])?,
+        }
+        write_u64(writer, self.order.len())?;
+        for &i in self.order.iter() {
+            write_u64(writer, i)?;
+        }
+        match &self.progress {
+            Some(progress) => {
+                writer.write_all(&[1])?;
+                write_u64(writer, progress.next_batch)?;
+                writer.write_all(&progress.total_loss.to_le_bytes())?;
+                writer.write_all(&progress.learning_rate.to_le_bytes())?;
+            }
+            None => writer.write_all(&[0])?,
+        }
+        write_string(writer, &self.history.schedule)?;
+        write_floats(writer, &self.history.loss)?;
+        write_floats(writer, &self.history.learning_rate)?;
+        write_floats(writer, &self.history.validation)?;
+        match &self.best {
+            Some(best) => {
+                writer.write_all(&[1])?;
+                write_u64(writer, best.epoch)?;
+                writer.write_all(&best.score.to_le_bytes())?;
+                write_u64(writer, best.stale_epochs)?;
+                write_matrices(writer, &best.params)?;
+                write_u64(writer, best.state.len())?;
+                writer.write_all(&best.state)?;
+            }
+            None => writer.write_all(&[0])?,
+        }
+        write_matrices(writer, &self.params)?;
+        write_u64(writer, self.model.len())?;
+        writer.write_all(&self.model)?;
+        write_u64(writer, self.optimizer.steps as usize)?;
+        writer.write_all(&(self.optimizer.buffers.len() as u32).to_le_bytes())?;
+        for buffer in self.optimizer.buffers.iter() {
+            write_matrices(writer, buffer)?;
+        }
+        writer.write_all(&(self.schedule.len() as u32).to_le_bytes())?;
+        for x in self.schedule.iter() {
+            writer.write_all(&x.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// `load` from any reader
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Self, ModelError> {
+        if &read_bytes::<R, 4>(reader)? != MAGIC {
+            return Err(ModelError::format("not a checkpoint file"));
+        }
+        let version = read_u32(reader)?;
+        if version != VERSION {
+            return Err(ModelError::format(format!(
+                "unsupported version {} (expected {})",
+                version, VERSION
+            )));
+        }
+        let step = read_usize(reader)?;
+        let epoch = read_usize(reader)?;
+        let run_start = read_usize(reader)?;
+        let rng = if read_flag(reader)? {
+            Some(RngState::from_bytes(read_bytes(reader)?))
+        } else {
+            None
+        };
+        let order = (0..read_u64(reader)?)
+            .map(|_| read_usize(reader))
+            .collect::<Result<_, _>>()?;
+        let progress = if read_flag(reader)? {
+            Some(EpochProgress {
+                next_batch: read_usize(reader)?,
+                total_loss: read_f64(reader)?,
+                learning_rate: read_f64(reader)?,
+            })
+        } else {
+            None
+        };
+        let history = History {
+            schedule: read_string(reader)?,
+            loss: read_floats(reader)?,
+            learning_rate: read_floats(reader)?,
+            validation: read_floats(reader)?,
+            ..History::default()
+        };
+        let best = if read_flag(reader)? {
+            Some(BestEpoch {
+                epoch: read_usize(reader)?,
+                score: read_f64(reader)?,
+                stale_epochs: read_usize(reader)?,
+                params: read_matrices(reader)?,
+                state: {
+                    let len = read_u64(reader)?;
+                    read_vec(reader, len, "best model state")?
+                },
+            })
+        } else {
+            None
+        };
+        let params = read_matrices(reader)?;
+        let len = read_u64(reader)?;
+        let model = read_vec(reader, len, "model state")?;
+        let optimizer = OptimizerState {
+            steps: read_u64(reader)?,
+            buffers: (0..read_u32(reader)?)
+                .map(|_| read_matrices(reader))
+                .collect::<Result<_, _>>()?,
+        };
+        let schedule = (0..read_u32(reader)?)
+            .map(|_| read_f64(reader))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            step,
+            epoch,
+            run_start,
+            rng,
+            order,
+            progress,
+            history,
+            best,
+            params,
+            model,
+            optimizer,
+            schedule,
+        })
+    }
+
+    /// Name of the checkpoint written after `step` steps. Zero-padded, so they sort by step.
+    pub fn file_name(step: usize) -> String {
+        format!("{}{:010}.{}", PREFIX, step, EXTENSION)
+    }
+
+    /// Every checkpoint `Trainer` has written to `dir`, oldest first. Empty if `dir` doesn't
+    /// exist.
+    pub fn list<P: AsRef<Path>>(dir: P) -> Result<Vec<PathBuf>, ModelError> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error.into()),
+        };
+        let mut paths = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            let is_checkpoint = path.extension().is_some_and(|e| e == EXTENSION)
+                && path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with(PREFIX));
+            if is_checkpoint {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        Ok(paths)
+    }
+
+    /// The most recent checkpoint in `dir`, if any
+    pub fn latest<P: AsRef<Path>>(dir: P) -> Result<Option<PathBuf>, ModelError> {
+        Ok(Self::list(dir)?.pop())
+    }
+
+    /// Saves into `config.dir`, then deletes all but the newest `config.keep` checkpoints there.
+    pub(crate) fn save_rotating(&self, config: &CheckpointConfig) -> Result<(), ModelError> {
+        fs::create_dir_all(&config.dir)?;
+        self.save(config.dir.join(Self::file_name(self.step)))?;
+        let paths = Self::list(&config.dir)?;
+        let stale = paths.len().saturating_sub(config.keep);
+        for path in paths.into_iter().take(stale) {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
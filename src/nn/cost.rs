@@ -1,27 +1,595 @@
+use super::activations::{Activation, Sigmoid};
+use super::ParseKindError;
 use crate::algebra::{MatLike, Matrix};
+use std::fmt::{Debug, Display};
 use std::iter::zip;
+use std::str::FromStr;
+use std::sync::Arc;
 
+/// How the loss of every case in a batch is combined, when scoring a model with `Cost::reduce`
+/// or `CostFn::reduce`. Training always uses `Mean`: `Trainable::batch_grad`, and so `Trainer`,
+/// average the cost and its gradient over the batch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Reduction {
+    Sum,
+    #[default]
+    Mean,
+    /// Keep one loss per case
+    None,
+}
+
+impl Reduction {
+    /// @param per_case loss of every case
+    /// @return the total for `Sum`, the average for `Mean`, `per_case` as is for `None`
+    pub fn apply(&self, per_case: Vec<f64>) -> Vec<f64> {
+        match self {
+            Reduction::Sum => vec![per_case.iter().sum()],
+            Reduction::Mean => vec![per_case.iter().sum::<f64>() / per_case.len().max(1) as f64],
+            Reduction::None => per_case,
+        }
+    }
+
+    /// Turns `Cost::prime` into cost_wrt_pred of the reduced loss. Row i of the result for `None`
+    /// is the gradient of case i's loss.
+    pub fn apply_prime(&self, prime: Matrix<f64>) -> Matrix<f64> {
+        match self {
+            Reduction::Mean => {
+                let cases = prime.h().max(1) as f64;
+                prime * (1.0 / cases)
+            }
+            Reduction::Sum | Reduction::None => prime,
+        }
+    }
+}
+
+/// A loss over a batch with one case per row. `calc` is the sum over rows of the loss of each
+/// case, so `reduce` can combine the cases with any `Reduction`, and training divides by the
+/// number of cases.
 pub trait Cost {
     fn calc(pred: &Matrix<f64>, actual: &Matrix<f64>) -> f64;
+    /// cost_wrt_pred, shaped like `pred`
     fn prime(pred: &Matrix<f64>, actual: &Matrix<f64>) -> Matrix<f64>;
+    /// Identifies the cost at runtime, eg. in `CostFn` and saved models
+    fn name() -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    /// Loss of every case (row) on its own
+    fn per_case(pred: &Matrix<f64>, actual: &Matrix<f64>) -> Vec<f64> {
+        (0..pred.h())
+            .map(|i| Self::calc(&pred.clone_row(i), &actual.clone_row(i)))
+            .collect()
+    }
+
+    fn reduce(pred: &Matrix<f64>, actual: &Matrix<f64>, reduction: Reduction) -> Vec<f64> {
+        reduction.apply(Self::per_case(pred, actual))
+    }
+}
+
+// sum of loss(pred, actual) over every element
+fn sum_elements(pred: &Matrix<f64>, actual: &Matrix<f64>, loss: impl Fn(f64, f64) -> f64) -> f64 {
+    assert_eq!(
+        (pred.w(), pred.h()),
+        (actual.w(), actual.h()),
+        "pred and actual have different shapes"
+    );
+    zip(pred.iter(), actual.iter())
+        .map(|(&p, &y)| loss(p, y))
+        .sum()
+}
+
+// sum over rows of the mean of loss(pred, actual) over the row
+fn mean_elements(pred: &Matrix<f64>, actual: &Matrix<f64>, loss: impl Fn(f64, f64) -> f64) -> f64 {
+    sum_elements(pred, actual, loss) / pred.w() as f64
+}
+
+// prime(pred, actual) for every element
+fn map_elements(
+    pred: &Matrix<f64>,
+    actual: &Matrix<f64>,
+    prime: impl Fn(f64, f64) -> f64,
+) -> Matrix<f64> {
+    Matrix::new(
+        zip(pred.iter(), actual.iter())
+            .map(|(&p, &y)| prime(p, y))
+            .collect(),
+        pred.w(),
+        pred.h(),
+    )
+}
+
+// map_elements of a loss that mean_elements averages over the row
+fn map_mean_elements(
+    pred: &Matrix<f64>,
+    actual: &Matrix<f64>,
+    prime: impl Fn(f64, f64) -> f64,
+) -> Matrix<f64> {
+    let w = pred.w() as f64;
+    map_elements(pred, actual, |p, y| prime(p, y) / w)
+}
+
+// keeps ln and 1/x finite when a probability underflows to 0 (or rounds up to 1)
+const MIN_PROB: f64 = 1e-15;
+
+fn sign(x: f64) -> f64 {
+    if x > 0.0 {
+        1.0
+    } else if x < 0.0 {
+        -1.0
+    } else {
+        0.0
+    }
+}
+
+fn huber(d: f64, delta: f64) -> f64 {
+    if d.abs() <= delta {
+        0.5 * d * d
+    } else {
+        delta * (d.abs() - 0.5 * delta)
+    }
+}
+
+fn huber_prime(d: f64, delta: f64) -> f64 {
+    d.clamp(-delta, delta)
 }
 
+fn quantile(d: f64, tau: f64) -> f64 {
+    // d = pred - actual, so under-predicting costs tau per unit
+    if d < 0.0 {
+        -tau * d
+    } else {
+        (1.0 - tau) * d
+    }
+}
+
+fn quantile_prime(d: f64, tau: f64) -> f64 {
+    if d < 0.0 {
+        -tau
+    } else if d > 0.0 {
+        1.0 - tau
+    } else {
+        0.0
+    }
+}
+
+/// Sum of (pred - actual)^2 over every output
 pub struct SumSquared {}
 impl Cost for SumSquared {
     fn calc(pred: &Matrix<f64>, actual: &Matrix<f64>) -> f64 {
-        pred.iter()
-            .zip(actual.iter())
-            .map(|(x, y)| (x - y) * (x - y))
-            .sum()
+        sum_elements(pred, actual, |p, y| (p - y) * (p - y))
+    }
+
+    fn prime(pred: &Matrix<f64>, actual: &Matrix<f64>) -> Matrix<f64> {
+        map_elements(pred, actual, |p, y| 2.0 * (p - y))
+    }
+
+    fn name() -> &'static str {
+        "sum_squared"
+    }
+}
+
+/// Mean of (pred - actual)^2 over the outputs of a case
+pub struct MeanSquared;
+impl Cost for MeanSquared {
+    fn calc(pred: &Matrix<f64>, actual: &Matrix<f64>) -> f64 {
+        mean_elements(pred, actual, |p, y| (p - y) * (p - y))
     }
 
     fn prime(pred: &Matrix<f64>, actual: &Matrix<f64>) -> Matrix<f64> {
-        Matrix::new(
-            zip(pred.iter(), actual.iter())
-                .map(|(x, y)| 2.0 * (x - y))
-                .collect(),
-            1,
-            pred.w(),
+        map_mean_elements(pred, actual, |p, y| 2.0 * (p - y))
+    }
+
+    fn name() -> &'static str {
+        "mse"
+    }
+}
+
+/// Mean of |pred - actual| over the outputs of a case
+pub struct MeanAbsolute;
+impl Cost for MeanAbsolute {
+    fn calc(pred: &Matrix<f64>, actual: &Matrix<f64>) -> f64 {
+        mean_elements(pred, actual, |p, y| (p - y).abs())
+    }
+
+    fn prime(pred: &Matrix<f64>, actual: &Matrix<f64>) -> Matrix<f64> {
+        map_mean_elements(pred, actual, |p, y| sign(p - y))
+    }
+
+    fn name() -> &'static str {
+        "mae"
+    }
+}
+
+/// Mean over the outputs of a case of (pred - actual)^2 / 2 within `Huber::DELTA` of actual, and
+/// linear beyond it. `CostFn::huber` takes any delta.
+pub struct Huber;
+impl Huber {
+    pub const DELTA: f64 = 1.0;
+}
+impl Cost for Huber {
+    fn calc(pred: &Matrix<f64>, actual: &Matrix<f64>) -> f64 {
+        mean_elements(pred, actual, |p, y| huber(p - y, Self::DELTA))
+    }
+
+    fn prime(pred: &Matrix<f64>, actual: &Matrix<f64>) -> Matrix<f64> {
+        map_mean_elements(pred, actual, |p, y| huber_prime(p - y, Self::DELTA))
+    }
+
+    fn name() -> &'static str {
+        "huber"
+    }
+}
+
+/// Mean of ln(cosh(pred - actual)) over the outputs of a case
+pub struct LogCosh;
+impl Cost for LogCosh {
+    fn calc(pred: &Matrix<f64>, actual: &Matrix<f64>) -> f64 {
+        // cosh overflows long before ln(cosh) does
+        mean_elements(pred, actual, |p, y| {
+            let d = (p - y).abs();
+            d + (-2.0 * d).exp().ln_1p() - std::f64::consts::LN_2
+        })
+    }
+
+    fn prime(pred: &Matrix<f64>, actual: &Matrix<f64>) -> Matrix<f64> {
+        map_mean_elements(pred, actual, |p, y| (p - y).tanh())
+    }
+
+    fn name() -> &'static str {
+        "log_cosh"
+    }
+}
+
+/// Mean over the outputs of a case of -(actual * ln(pred) + (1 - actual) * ln(1 - pred)), for
+/// pred from a sigmoid
+pub struct BinaryCrossEntropy;
+impl Cost for BinaryCrossEntropy {
+    fn calc(pred: &Matrix<f64>, actual: &Matrix<f64>) -> f64 {
+        mean_elements(pred, actual, |p, y| {
+            let p = p.clamp(MIN_PROB, 1.0 - MIN_PROB);
+            -(y * p.ln() + (1.0 - y) * (1.0 - p).ln())
+        })
+    }
+
+    fn prime(pred: &Matrix<f64>, actual: &Matrix<f64>) -> Matrix<f64> {
+        map_mean_elements(pred, actual, |p, y| {
+            let p = p.clamp(MIN_PROB, 1.0 - MIN_PROB);
+            (p - y) / (p * (1.0 - p))
+        })
+    }
+
+    fn name() -> &'static str {
+        "binary_cross_entropy"
+    }
+}
+
+/// `BinaryCrossEntropy` of sigmoid(pred), computed from the logits so it can't saturate
+pub struct BinaryCrossEntropyWithLogits;
+impl Cost for BinaryCrossEntropyWithLogits {
+    fn calc(pred: &Matrix<f64>, actual: &Matrix<f64>) -> f64 {
+        mean_elements(pred, actual, |z, y| {
+            z.max(0.0) - z * y + (-z.abs()).exp().ln_1p()
+        })
+    }
+
+    fn prime(pred: &Matrix<f64>, actual: &Matrix<f64>) -> Matrix<f64> {
+        map_mean_elements(pred, actual, |z, y| Sigmoid::calc(z) - y)
+    }
+
+    fn name() -> &'static str {
+        "binary_cross_entropy_with_logits"
+    }
+}
+
+/// -sum of actual * ln(pred), for pred from a softmax and one-hot (or probability) actual
+pub struct CategoricalCrossEntropy;
+impl Cost for CategoricalCrossEntropy {
+    fn calc(pred: &Matrix<f64>, actual: &Matrix<f64>) -> f64 {
+        sum_elements(pred, actual, |p, y| -y * p.max(MIN_PROB).ln())
+    }
+
+    fn prime(pred: &Matrix<f64>, actual: &Matrix<f64>) -> Matrix<f64> {
+        map_elements(pred, actual, |p, y| -y / p.max(MIN_PROB))
+    }
+
+    fn name() -> &'static str {
+        "categorical_cross_entropy"
+    }
+}
+
+/// -sum of actual * pred, for pred from a log softmax
+pub struct NegativeLogLikelihood;
+impl Cost for NegativeLogLikelihood {
+    fn calc(pred: &Matrix<f64>, actual: &Matrix<f64>) -> f64 {
+        sum_elements(pred, actual, |p, y| -y * p)
+    }
+
+    fn prime(_pred: &Matrix<f64>, actual: &Matrix<f64>) -> Matrix<f64> {
+        actual.apply(|y| -y)
+    }
+
+    fn name() -> &'static str {
+        "nll"
+    }
+}
+
+/// Mean of max(0, 1 - actual * pred) over the outputs of a case, for actual of -1 or 1
+pub struct Hinge;
+impl Cost for Hinge {
+    fn calc(pred: &Matrix<f64>, actual: &Matrix<f64>) -> f64 {
+        mean_elements(pred, actual, |p, y| (1.0 - y * p).max(0.0))
+    }
+
+    fn prime(pred: &Matrix<f64>, actual: &Matrix<f64>) -> Matrix<f64> {
+        map_mean_elements(pred, actual, |p, y| if y * p < 1.0 { -y } else { 0.0 })
+    }
+
+    fn name() -> &'static str {
+        "hinge"
+    }
+}
+
+/// Mean of max(0, 1 - actual * pred)^2 over the outputs of a case, for actual of -1 or 1
+pub struct SquaredHinge;
+impl Cost for SquaredHinge {
+    fn calc(pred: &Matrix<f64>, actual: &Matrix<f64>) -> f64 {
+        mean_elements(pred, actual, |p, y| (1.0 - y * p).max(0.0).powi(2))
+    }
+
+    fn prime(pred: &Matrix<f64>, actual: &Matrix<f64>) -> Matrix<f64> {
+        map_mean_elements(pred, actual, |p, y| -2.0 * y * (1.0 - y * p).max(0.0))
+    }
+
+    fn name() -> &'static str {
+        "squared_hinge"
+    }
+}
+
+/// Sum of actual * ln(actual / pred), how far the distribution pred is from actual
+pub struct KLDivergence;
+impl Cost for KLDivergence {
+    fn calc(pred: &Matrix<f64>, actual: &Matrix<f64>) -> f64 {
+        sum_elements(pred, actual, |p, y| {
+            // 0 ln 0 = 0
+            if y > 0.0 {
+                y * (y / p.max(MIN_PROB)).ln()
+            } else {
+                0.0
+            }
+        })
+    }
+
+    fn prime(pred: &Matrix<f64>, actual: &Matrix<f64>) -> Matrix<f64> {
+        map_elements(pred, actual, |p, y| -y / p.max(MIN_PROB))
+    }
+
+    fn name() -> &'static str {
+        "kl_divergence"
+    }
+}
+
+/// Mean over the outputs of a case of the negative log likelihood of actual counts under a
+/// Poisson distribution, without the constant ln(actual!). pred is the log of the rate, so it
+/// needs no activation: e^pred - actual * pred.
+pub struct PoissonNLL;
+impl Cost for PoissonNLL {
+    fn calc(pred: &Matrix<f64>, actual: &Matrix<f64>) -> f64 {
+        mean_elements(pred, actual, |p, y| p.exp() - y * p)
+    }
+
+    fn prime(pred: &Matrix<f64>, actual: &Matrix<f64>) -> Matrix<f64> {
+        map_mean_elements(pred, actual, |p, y| p.exp() - y)
+    }
+
+    fn name() -> &'static str {
+        "poisson_nll"
+    }
+}
+
+/// Pinball loss for predicting the `Quantile::TAU` quantile (the median), averaged over the
+/// outputs of a case. Under-predicting costs tau per unit, over-predicting 1 - tau.
+/// `CostFn::quantile` takes any tau.
+pub struct Quantile;
+impl Quantile {
+    pub const TAU: f64 = 0.5;
+}
+impl Cost for Quantile {
+    fn calc(pred: &Matrix<f64>, actual: &Matrix<f64>) -> f64 {
+        mean_elements(pred, actual, |p, y| quantile(p - y, Self::TAU))
+    }
+
+    fn prime(pred: &Matrix<f64>, actual: &Matrix<f64>) -> Matrix<f64> {
+        map_mean_elements(pred, actual, |p, y| quantile_prime(p - y, Self::TAU))
+    }
+
+    fn name() -> &'static str {
+        "quantile"
+    }
+}
+
+// generates CostKind, with a variant for every built-in cost type
+macro_rules! cost_kinds {
+    ($($kind: ident => $type: ty),* $(,)?) => {
+        /// Every built-in cost, for picking one at runtime. Parses from, and displays as,
+        /// `Cost::name`.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum CostKind {
+            $($kind),*
+        }
+
+        impl CostKind {
+            pub const ALL: &'static [CostKind] = &[$(CostKind::$kind),*];
+
+            pub fn calc(&self, pred: &Matrix<f64>, actual: &Matrix<f64>) -> f64 {
+                match self {
+                    $(CostKind::$kind => <$type as Cost>::calc(pred, actual)),*
+                }
+            }
+
+            pub fn prime(&self, pred: &Matrix<f64>, actual: &Matrix<f64>) -> Matrix<f64> {
+                match self {
+                    $(CostKind::$kind => <$type as Cost>::prime(pred, actual)),*
+                }
+            }
+
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(CostKind::$kind => <$type as Cost>::name()),*
+                }
+            }
+        }
+    };
+}
+
+cost_kinds! {
+    SumSquared => SumSquared,
+    MeanSquared => MeanSquared,
+    MeanAbsolute => MeanAbsolute,
+    Huber => Huber,
+    LogCosh => LogCosh,
+    BinaryCrossEntropy => BinaryCrossEntropy,
+    BinaryCrossEntropyWithLogits => BinaryCrossEntropyWithLogits,
+    CategoricalCrossEntropy => CategoricalCrossEntropy,
+    NegativeLogLikelihood => NegativeLogLikelihood,
+    Hinge => Hinge,
+    SquaredHinge => SquaredHinge,
+    KLDivergence => KLDivergence,
+    PoissonNLL => PoissonNLL,
+    Quantile => Quantile,
+}
+
+impl Display for CostKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for CostKind {
+    type Err = ParseKindError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CostKind::ALL
+            .iter()
+            .find(|kind| kind.name().eq_ignore_ascii_case(s.trim()))
+            .copied()
+            .ok_or_else(|| ParseKindError::new("cost", s))
+    }
+}
+
+type CalcFn = Arc<dyn Fn(&Matrix<f64>, &Matrix<f64>) -> f64 + Send + Sync>;
+type PrimeFn = Arc<dyn Fn(&Matrix<f64>, &Matrix<f64>) -> Matrix<f64> + Send + Sync>;
+
+/// A cost chosen at runtime: a `CostKind`, any `Cost` type, or a pair of closures.
+#[derive(Clone)]
+pub struct CostFn {
+    name: String,
+    calc: CalcFn,
+    prime: PrimeFn,
+}
+
+impl CostFn {
+    pub fn new<F, P>(name: &str, calc: F, prime: P) -> Self
+    where
+        F: Fn(&Matrix<f64>, &Matrix<f64>) -> f64 + Send + Sync + 'static,
+        P: Fn(&Matrix<f64>, &Matrix<f64>) -> Matrix<f64> + Send + Sync + 'static,
+    {
+        Self {
+            name: name.to_string(),
+            calc: Arc::new(calc),
+            prime: Arc::new(prime),
+        }
+    }
+
+    pub fn of<C: Cost + 'static>() -> Self {
+        Self::new(C::name(), C::calc, C::prime)
+    }
+
+    pub fn calc(&self, pred: &Matrix<f64>, actual: &Matrix<f64>) -> f64 {
+        (self.calc)(pred, actual)
+    }
+
+    pub fn prime(&self, pred: &Matrix<f64>, actual: &Matrix<f64>) -> Matrix<f64> {
+        (self.prime)(pred, actual)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Loss of every case (row) on its own
+    pub fn per_case(&self, pred: &Matrix<f64>, actual: &Matrix<f64>) -> Vec<f64> {
+        (0..pred.h())
+            .map(|i| self.calc(&pred.clone_row(i), &actual.clone_row(i)))
+            .collect()
+    }
+
+    pub fn reduce(
+        &self,
+        pred: &Matrix<f64>,
+        actual: &Matrix<f64>,
+        reduction: Reduction,
+    ) -> Vec<f64> {
+        reduction.apply(self.per_case(pred, actual))
+    }
+
+    /// `Huber` with any delta, named eg. "huber(0.5)"
+    pub fn huber(delta: f64) -> Self {
+        assert!(delta > 0.0, "delta must be positive");
+        Self::new(
+            &format!("{}({})", Huber::name(), delta),
+            move |pred, actual| mean_elements(pred, actual, |p, y| huber(p - y, delta)),
+            move |pred, actual| map_mean_elements(pred, actual, |p, y| huber_prime(p - y, delta)),
+        )
+    }
+
+    /// `Quantile` for any tau in (0, 1), named eg. "quantile(0.9)"
+    pub fn quantile(tau: f64) -> Self {
+        assert!(tau > 0.0 && tau < 1.0, "tau must be between 0 and 1");
+        Self::new(
+            &format!("{}({})", Quantile::name(), tau),
+            move |pred, actual| mean_elements(pred, actual, |p, y| quantile(p - y, tau)),
+            move |pred, actual| map_mean_elements(pred, actual, |p, y| quantile_prime(p - y, tau)),
         )
     }
 }
+
+impl From<CostKind> for CostFn {
+    fn from(kind: CostKind) -> Self {
+        Self::new(
+            kind.name(),
+            move |pred, actual| kind.calc(pred, actual),
+            move |pred, actual| kind.prime(pred, actual),
+        )
+    }
+}
+
+impl Display for CostFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// Parses a `CostKind`, or the name of a `CostFn::huber` or `CostFn::quantile`.
+impl FromStr for CostFn {
+    type Err = ParseKindError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((name, param)) = s.trim().strip_suffix(')').and_then(|s| s.split_once('(')) else {
+            return s.parse::<CostKind>().map(CostFn::from);
+        };
+        let error = || ParseKindError::new("cost", s);
+        let param: f64 = param.trim().parse().map_err(|_| error())?;
+        let name = name.trim();
+        if name.eq_ignore_ascii_case(Huber::name()) && param > 0.0 {
+            Ok(CostFn::huber(param))
+        } else if name.eq_ignore_ascii_case(Quantile::name()) && param > 0.0 && param < 1.0 {
+            Ok(CostFn::quantile(param))
+        } else {
+            Err(error())
+        }
+    }
+}
+
+impl Debug for CostFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CostFn({})", self.name)
+    }
+}
@@ -0,0 +1,421 @@
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+/// Anything a function can be written generically over, so the same code computes plain values
+/// (`f64`), first derivatives (`Dual`) or second derivatives (`HyperDual`).
+pub trait Real:
+    Copy
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + Add<f64, Output = Self>
+    + Sub<f64, Output = Self>
+    + Mul<f64, Output = Self>
+    + Div<f64, Output = Self>
+{
+    fn constant(x: f64) -> Self;
+    /// Real part, for branching (eg. `if x.value() > 0.0`)
+    fn value(self) -> f64;
+    fn exp(self) -> Self;
+    fn ln(self) -> Self;
+    fn sqrt(self) -> Self;
+    fn powi(self, n: i32) -> Self;
+    fn powf(self, n: f64) -> Self;
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    fn tanh(self) -> Self;
+    fn sinh(self) -> Self;
+    fn cosh(self) -> Self;
+    fn abs(self) -> Self;
+    fn recip(self) -> Self;
+    fn ln_1p(self) -> Self;
+    fn exp_m1(self) -> Self;
+}
+
+impl Real for f64 {
+    fn constant(x: f64) -> Self {
+        x
+    }
+    fn value(self) -> f64 {
+        self
+    }
+    fn exp(self) -> Self {
+        f64::exp(self)
+    }
+    fn ln(self) -> Self {
+        f64::ln(self)
+    }
+    fn sqrt(self) -> Self {
+        f64::sqrt(self)
+    }
+    fn powi(self, n: i32) -> Self {
+        f64::powi(self, n)
+    }
+    fn powf(self, n: f64) -> Self {
+        f64::powf(self, n)
+    }
+    fn sin(self) -> Self {
+        f64::sin(self)
+    }
+    fn cos(self) -> Self {
+        f64::cos(self)
+    }
+    fn tanh(self) -> Self {
+        f64::tanh(self)
+    }
+    fn sinh(self) -> Self {
+        f64::sinh(self)
+    }
+    fn cosh(self) -> Self {
+        f64::cosh(self)
+    }
+    fn abs(self) -> Self {
+        f64::abs(self)
+    }
+    fn recip(self) -> Self {
+        f64::recip(self)
+    }
+    fn ln_1p(self) -> Self {
+        f64::ln_1p(self)
+    }
+    fn exp_m1(self) -> Self {
+        f64::exp_m1(self)
+    }
+}
+
+// (f, f', f'') at a point, everything a unary function needs to push through either number type
+type Derivs = (f64, f64, f64);
+
+fn derivs_exp(x: f64) -> Derivs {
+    let e = x.exp();
+    (e, e, e)
+}
+fn derivs_ln(x: f64) -> Derivs {
+    (x.ln(), 1.0 / x, -1.0 / (x * x))
+}
+fn derivs_sqrt(x: f64) -> Derivs {
+    let s = x.sqrt();
+    (s, 0.5 / s, -0.25 / (s * x))
+}
+fn derivs_powf(x: f64, n: f64) -> Derivs {
+    (
+        x.powf(n),
+        n * x.powf(n - 1.0),
+        n * (n - 1.0) * x.powf(n - 2.0),
+    )
+}
+fn derivs_powi(x: f64, n: i32) -> Derivs {
+    let n_f = n as f64;
+    (
+        x.powi(n),
+        n_f * x.powi(n - 1),
+        n_f * (n_f - 1.0) * x.powi(n - 2),
+    )
+}
+fn derivs_sin(x: f64) -> Derivs {
+    (x.sin(), x.cos(), -x.sin())
+}
+fn derivs_cos(x: f64) -> Derivs {
+    (x.cos(), -x.sin(), -x.cos())
+}
+fn derivs_tanh(x: f64) -> Derivs {
+    let t = x.tanh();
+    (t, 1.0 - t * t, -2.0 * t * (1.0 - t * t))
+}
+fn derivs_sinh(x: f64) -> Derivs {
+    (x.sinh(), x.cosh(), x.sinh())
+}
+fn derivs_cosh(x: f64) -> Derivs {
+    (x.cosh(), x.sinh(), x.cosh())
+}
+fn derivs_abs(x: f64) -> Derivs {
+    (x.abs(), x.signum(), 0.0)
+}
+fn derivs_recip(x: f64) -> Derivs {
+    (1.0 / x, -1.0 / (x * x), 2.0 / (x * x * x))
+}
+fn derivs_ln_1p(x: f64) -> Derivs {
+    (x.ln_1p(), 1.0 / (1.0 + x), -1.0 / ((1.0 + x) * (1.0 + x)))
+}
+fn derivs_exp_m1(x: f64) -> Derivs {
+    (x.exp_m1(), x.exp(), x.exp())
+}
+
+// implements every transcendental function of `Real` through `$type::chain`
+macro_rules! real_functions {
+    ($type: ty) => {
+        impl Real for $type {
+            fn constant(x: f64) -> Self {
+                Self::new(x)
+            }
+            fn value(self) -> f64 {
+                self.re
+            }
+            fn exp(self) -> Self {
+                self.chain(derivs_exp(self.re))
+            }
+            fn ln(self) -> Self {
+                self.chain(derivs_ln(self.re))
+            }
+            fn sqrt(self) -> Self {
+                self.chain(derivs_sqrt(self.re))
+            }
+            fn powi(self, n: i32) -> Self {
+                self.chain(derivs_powi(self.re, n))
+            }
+            fn powf(self, n: f64) -> Self {
+                self.chain(derivs_powf(self.re, n))
+            }
+            fn sin(self) -> Self {
+                self.chain(derivs_sin(self.re))
+            }
+            fn cos(self) -> Self {
+                self.chain(derivs_cos(self.re))
+            }
+            fn tanh(self) -> Self {
+                self.chain(derivs_tanh(self.re))
+            }
+            fn sinh(self) -> Self {
+                self.chain(derivs_sinh(self.re))
+            }
+            fn cosh(self) -> Self {
+                self.chain(derivs_cosh(self.re))
+            }
+            fn abs(self) -> Self {
+                self.chain(derivs_abs(self.re))
+            }
+            fn recip(self) -> Self {
+                self.chain(derivs_recip(self.re))
+            }
+            fn ln_1p(self) -> Self {
+                self.chain(derivs_ln_1p(self.re))
+            }
+            fn exp_m1(self) -> Self {
+                self.chain(derivs_exp_m1(self.re))
+            }
+        }
+
+        impl Add<f64> for $type {
+            type Output = $type;
+            fn add(self, rhs: f64) -> $type {
+                self + <$type>::new(rhs)
+            }
+        }
+
+        impl Sub<f64> for $type {
+            type Output = $type;
+            fn sub(self, rhs: f64) -> $type {
+                self - <$type>::new(rhs)
+            }
+        }
+
+        impl Mul<f64> for $type {
+            type Output = $type;
+            fn mul(self, rhs: f64) -> $type {
+                self * <$type>::new(rhs)
+            }
+        }
+
+        impl Div<f64> for $type {
+            type Output = $type;
+            fn div(self, rhs: f64) -> $type {
+                self * (1.0 / rhs)
+            }
+        }
+
+        impl Div for $type {
+            type Output = $type;
+            // x / y = x * (1 / y), and recip already knows its derivatives
+            #[allow(clippy::suspicious_arithmetic_impl)]
+            fn div(self, rhs: $type) -> $type {
+                self * rhs.recip()
+            }
+        }
+
+        impl Neg for $type {
+            type Output = $type;
+            fn neg(self) -> $type {
+                self * -1.0
+            }
+        }
+    };
+}
+
+/// re + du ε, with ε² = 0. Evaluating f(x + ε) gives f(x) + f'(x) ε.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dual {
+    pub re: f64,
+    pub du: f64,
+}
+
+impl Dual {
+    pub fn new(re: f64) -> Self {
+        Self { re, du: 0.0 }
+    }
+
+    /// The point a derivative is taken at.
+    pub fn variable(re: f64) -> Self {
+        Self { re, du: 1.0 }
+    }
+
+    fn chain(self, (f, df, _): Derivs) -> Self {
+        Self {
+            re: f,
+            du: df * self.du,
+        }
+    }
+}
+
+impl Add for Dual {
+    type Output = Dual;
+    fn add(self, rhs: Dual) -> Dual {
+        Dual {
+            re: self.re + rhs.re,
+            du: self.du + rhs.du,
+        }
+    }
+}
+
+impl Sub for Dual {
+    type Output = Dual;
+    fn sub(self, rhs: Dual) -> Dual {
+        Dual {
+            re: self.re - rhs.re,
+            du: self.du - rhs.du,
+        }
+    }
+}
+
+impl Mul for Dual {
+    type Output = Dual;
+    fn mul(self, rhs: Dual) -> Dual {
+        Dual {
+            re: self.re * rhs.re,
+            du: self.re * rhs.du + self.du * rhs.re,
+        }
+    }
+}
+
+real_functions!(Dual);
+
+/// re + e1 ε1 + e2 ε2 + e12 ε1ε2, with ε1² = ε2² = 0. Evaluating f(x + ε1 + ε2) gives f'(x) in
+/// both e1 and e2, and f''(x) in e12, exactly rather than by finite differences.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HyperDual {
+    pub re: f64,
+    pub e1: f64,
+    pub e2: f64,
+    pub e12: f64,
+}
+
+impl HyperDual {
+    pub fn new(re: f64) -> Self {
+        Self {
+            re,
+            e1: 0.0,
+            e2: 0.0,
+            e12: 0.0,
+        }
+    }
+
+    /// The point a second derivative is taken at.
+    pub fn variable(re: f64) -> Self {
+        Self {
+            re,
+            e1: 1.0,
+            e2: 1.0,
+            e12: 0.0,
+        }
+    }
+
+    fn chain(self, (f, df, d2f): Derivs) -> Self {
+        Self {
+            re: f,
+            e1: df * self.e1,
+            e2: df * self.e2,
+            e12: df * self.e12 + d2f * self.e1 * self.e2,
+        }
+    }
+}
+
+impl Add for HyperDual {
+    type Output = HyperDual;
+    fn add(self, rhs: HyperDual) -> HyperDual {
+        HyperDual {
+            re: self.re + rhs.re,
+            e1: self.e1 + rhs.e1,
+            e2: self.e2 + rhs.e2,
+            e12: self.e12 + rhs.e12,
+        }
+    }
+}
+
+impl Sub for HyperDual {
+    type Output = HyperDual;
+    fn sub(self, rhs: HyperDual) -> HyperDual {
+        HyperDual {
+            re: self.re - rhs.re,
+            e1: self.e1 - rhs.e1,
+            e2: self.e2 - rhs.e2,
+            e12: self.e12 - rhs.e12,
+        }
+    }
+}
+
+impl Mul for HyperDual {
+    type Output = HyperDual;
+    fn mul(self, rhs: HyperDual) -> HyperDual {
+        HyperDual {
+            re: self.re * rhs.re,
+            e1: self.re * rhs.e1 + self.e1 * rhs.re,
+            e2: self.re * rhs.e2 + self.e2 * rhs.re,
+            e12: self.re * rhs.e12 + self.e1 * rhs.e2 + self.e2 * rhs.e1 + self.e12 * rhs.re,
+        }
+    }
+}
+
+real_functions!(HyperDual);
+
+/// Gradient of `function` at `x`, one forward pass per input.
+pub fn gradient<F: Fn(&[Dual]) -> Dual>(function: F, x: &[f64]) -> Vec<f64> {
+    (0..x.len())
+        .map(|i| {
+            let input: Vec<Dual> = x
+                .iter()
+                .enumerate()
+                .map(|(j, &x_j)| Dual {
+                    re: x_j,
+                    du: if i == j { 1.0 } else { 0.0 },
+                })
+                .collect();
+            function(&input).du
+        })
+        .collect()
+}
+
+/// H v, where H is the Hessian of `function` at `x`, without forming H. Seeding ε1 with `v` and ε2
+/// with the i-th basis vector leaves (H v)_i in e12.
+pub fn hessian_vector_product<F: Fn(&[HyperDual]) -> HyperDual>(
+    function: F,
+    x: &[f64],
+    v: &[f64],
+) -> Vec<f64> {
+    assert_eq!(x.len(), v.len(), "x and v lengths do not match");
+    (0..x.len())
+        .map(|i| {
+            let input: Vec<HyperDual> = x
+                .iter()
+                .zip(v.iter())
+                .enumerate()
+                .map(|(j, (&x_j, &v_j))| HyperDual {
+                    re: x_j,
+                    e1: v_j,
+                    e2: if i == j { 1.0 } else { 0.0 },
+                    e12: 0.0,
+                })
+                .collect();
+            function(&input).e12
+        })
+        .collect()
+}
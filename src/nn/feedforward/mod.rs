@@ -1,14 +1,21 @@
 use super::activations::Activation;
 use super::cost::Cost;
-use crate::algebra::Matrix;
+use super::init::Initializer;
+use super::layers::Dropout;
+use super::regularization::Regularization;
+use crate::algebra::{MatLike, Matrix};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use std::marker::PhantomData;
 
+mod save;
 mod train;
 
 pub struct FFNet<A: Activation, C: Cost> {
     layers: Vec<Layer<A, C>>,
     activated: Vec<Matrix<f64>>,   // row vec
     unactivated: Vec<Matrix<f64>>, // col vec
+    autograd: bool,
+    training: bool,
     spine_chilling: PhantomData<A>,
     gut_wrneching: PhantomData<C>,
 }
@@ -18,17 +25,21 @@ struct Layer<A: Activation, C: Cost> {
     biases: Matrix<f64>,
     in_shape: usize,
     out_shape: usize,
+    regularization: Regularization,
+    dropout: Option<Dropout>, // applied to the activated output while training
     spine_chilling: PhantomData<A>,
     gut_wrneching: PhantomData<C>,
 }
 
 impl<A: Activation, C: Cost> Layer<A, C> {
-    pub fn new(in_shape: usize, out_shape: usize) -> Self {
+    pub fn new(in_shape: usize, out_shape: usize, weights: Matrix<f64>) -> Self {
         Self {
-            weights: Matrix::new_uniform(1_f64, in_shape, out_shape), // initialise to 1 for testing purposes
+            weights,
             in_shape,
             out_shape,
             biases: Matrix::new_uniform(0.0, 1, out_shape),
+            regularization: Regularization::none(),
+            dropout: None,
             spine_chilling: PhantomData,
             gut_wrneching: PhantomData,
         }
@@ -39,33 +50,156 @@ impl<A: Activation, C: Cost> Layer<A, C> {
         let activated = unactivated.apply(|x| A::calc(x));
         (unactivated, activated)
     }
+
+    /// The regularization penalty on this layer's params
+    pub fn penalty(&self) -> f64 {
+        let weights = self.regularization.penalty(&self.weights);
+        if self.regularization.biases {
+            weights + self.regularization.penalty(&self.biases)
+        } else {
+            weights
+        }
+    }
+
+    /// @param input one case per row
+    /// @return (unactivated, activated), one case per row
+    pub fn pred_batch(&self, input: &Matrix<f64>) -> (Matrix<f64>, Matrix<f64>) {
+        let unactivated = (input * self.weights.new_transposed()).add_to_rows(&self.biases);
+        let activated = unactivated.apply(|x| A::calc(x));
+        (unactivated, activated)
+    }
 }
 
 impl<A: Activation, C: Cost> FFNet<A, C> {
+    /// Weights drawn with `Initializer::for_activation(A::name())`, and biases of 0.
     pub fn new(shape: Vec<usize>) -> Self {
+        Self::with_rng(shape, &mut rand::thread_rng())
+    }
+
+    /// `new`, reproducibly
+    pub fn with_seed(shape: Vec<usize>, seed: u64) -> Self {
+        Self::with_rng(shape, &mut StdRng::seed_from_u64(seed))
+    }
+
+    fn with_rng<R: Rng>(shape: Vec<usize>, rng: &mut R) -> Self {
+        let initializer = Initializer::for_activation(A::name());
         let layers = (1..shape.len())
-            .map(|i| Layer::<A, C>::new(shape[i - 1], shape[i]))
+            .map(|i| {
+                let weights = initializer.weights(shape[i - 1], shape[i], rng);
+                Layer::<A, C>::new(shape[i - 1], shape[i], weights)
+            })
             .collect();
-        let activated = (0..shape.len())
-            .map(|i| Matrix::<f64>::new_uniform(0.0, 1, shape[i]))
+        Self::from_layers(shape.first().copied().unwrap_or(0), layers)
+    }
+
+    // the buffers are sized from the layers, which hold at least as many params
+    fn from_layers(in_shape: usize, layers: Vec<Layer<A, C>>) -> Self {
+        let activated = std::iter::once(in_shape)
+            .chain(layers.iter().map(|layer| layer.out_shape))
+            .map(|size| Matrix::<f64>::new_uniform(0.0, 1, size))
             .collect();
-        let unactivated = (1..shape.len())
-            .map(|i| Matrix::<f64>::new_uniform(0.0, 1, shape[i]))
+        let unactivated = layers
+            .iter()
+            .map(|layer| Matrix::<f64>::new_uniform(0.0, 1, layer.out_shape))
             .collect();
         Self {
             layers,
             activated,
             unactivated,
+            autograd: false,
+            training: true,
             spine_chilling: PhantomData,
             gut_wrneching: PhantomData,
         }
     }
 
+    /// Use `nn::autograd` instead of the hand-written backprop when training.
+    pub fn set_autograd(&mut self, autograd: bool) {
+        self.autograd = autograd;
+    }
+
+    /// Redraws the weights of layer `layer` (0 being the one fed the input) with `initializer`,
+    /// and zeroes its biases.
+    pub fn initialize(&mut self, layer: usize, initializer: &Initializer, seed: u64) {
+        self.layers[layer].initialize(initializer, &mut StdRng::seed_from_u64(seed));
+    }
+
+    /// `initialize` for every layer, drawing from one RNG
+    pub fn initialize_all(&mut self, initializer: &Initializer, seed: u64) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        for layer in self.layers.iter_mut() {
+            layer.initialize(initializer, &mut rng);
+        }
+    }
+
+    /// Drops out the activated outputs of layer `layer` (0 being the one fed the input) while
+    /// training.
+    pub fn set_dropout(&mut self, layer: usize, dropout: Dropout) {
+        self.layers[layer].dropout = Some(dropout);
+    }
+
+    /// Training mode, the default: dropout is applied by training and `pred_single`.
+    pub fn train(&mut self) {
+        self.training = true;
+    }
+
+    /// Inference mode: no dropout anywhere. `predict` always acts like this.
+    pub fn eval(&mut self) {
+        self.training = false;
+    }
+
+    pub fn is_training(&self) -> bool {
+        self.training
+    }
+
+    /// Penalises the params of layer `layer` (0 being the one fed the input) during training.
+    pub fn set_regularization(&mut self, layer: usize, regularization: Regularization) {
+        self.layers[layer].regularization = regularization;
+    }
+
+    /// `set_regularization` for every layer
+    pub fn set_regularization_all(&mut self, regularization: Regularization) {
+        for layer in self.layers.iter_mut() {
+            layer.regularization = regularization;
+        }
+    }
+
+    pub fn regularization(&self, layer: usize) -> Regularization {
+        self.layers[layer].regularization
+    }
+
+    /// Sum of every layer's regularization penalty, as added to the training loss
+    pub fn penalty(&self) -> f64 {
+        self.layers.iter().map(|layer| layer.penalty()).sum()
+    }
+
+    /// Every layer's weights followed by its biases, in layer order.
+    pub fn params_mut(&mut self) -> Vec<&mut Matrix<f64>> {
+        self.layers
+            .iter_mut()
+            .flat_map(|layer| [&mut layer.weights, &mut layer.biases])
+            .collect()
+    }
+
+    /// Runs every row of `x` through the net at once, without dropout.
+    /// @param x one case per row
+    /// @return one prediction per row
+    pub fn predict(&self, x: &Matrix<f64>) -> Matrix<f64> {
+        self.layers
+            .iter()
+            .fold(x.clone(), |input, layer| layer.pred_batch(&input).1)
+    }
+
     pub fn pred_single(&mut self, input: Matrix<f64>) -> &Matrix<f64> {
         self.activated[0] = input;
-        let layers = self.layers.iter().enumerate();
+        let layers = self.layers.iter_mut().enumerate();
         for (i, layer) in layers {
             (self.unactivated[i], self.activated[i + 1]) = layer.pred(&self.activated[i]);
+            if let (true, Some(dropout)) = (self.training, layer.dropout.as_mut()) {
+                let activated = &self.activated[i + 1];
+                let mask = dropout.sample_mask(activated.w(), activated.h());
+                self.activated[i + 1] = mask.apply(activated);
+            }
             self.activated[i].transpose();
         }
         self.activated.last_mut().unwrap().transpose();
@@ -0,0 +1,275 @@
+use super::{Activation, Cost, FFNet, Layer};
+use crate::algebra::{MatLike, Matrix};
+use crate::nn::binary::{read_matrix, read_string, read_u32, read_u64, write_matrix, write_string};
+use crate::nn::json::Json;
+use crate::nn::ModelError;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+// Binary layout, every number little-endian:
+//   MAGIC, VERSION as u32,
+//   activation name, cost name, each as a u32 byte count then utf-8,
+//   number of entries in the shape as u32, then every entry as u64,
+//   then for every layer, its weights (row-major, out_shape x in_shape) then its biases, as f64
+const MAGIC: &[u8; 4] = b"MLFF";
+const VERSION: u32 = 1;
+const JSON_FORMAT: &str = "ml-ffnet";
+
+fn json_matrix(matrix: &Matrix<f64>) -> Json {
+    Json::Array(
+        (0..matrix.h())
+            .map(|i| Json::numbers(matrix.clone_row(i).iter().copied()))
+            .collect(),
+    )
+}
+
+// a w x h matrix from an array of h rows of w numbers
+fn matrix_from_json(
+    json: &Json,
+    w: usize,
+    h: usize,
+    what: &str,
+) -> Result<Matrix<f64>, ModelError> {
+    let bad_shape = || ModelError::format(format!("{} should be {} rows of {}", what, h, w));
+    let rows = json
+        .as_array()
+        .filter(|rows| rows.len() == h)
+        .ok_or_else(bad_shape)?;
+    // the rows' lengths are checked as they're read, so the shape isn't trusted up front
+    let mut data = Vec::new();
+    for row in rows {
+        let row = row
+            .as_array()
+            .filter(|row| row.len() == w)
+            .ok_or_else(bad_shape)?;
+        for x in row {
+            data.push(
+                x.as_f64()
+                    .ok_or_else(|| ModelError::format(format!("{} holds a non-number", what)))?,
+            );
+        }
+    }
+    Ok(Matrix::new(data, w, h))
+}
+
+// a shape read from a file, before anything is read or built for it
+fn check_shape(shape: &[usize]) -> Result<(), ModelError> {
+    if shape.len() < 2 {
+        return Err(ModelError::format(
+            "a net needs at least 2 sizes in its shape",
+        ));
+    }
+    if shape.contains(&0) {
+        return Err(ModelError::format(
+            "every size in the shape must be positive",
+        ));
+    }
+    // every layer's weights must be addressable as f64s
+    let too_large = |sizes: &[usize]| {
+        sizes[0]
+            .checked_mul(sizes[1])
+            .and_then(|len| len.checked_mul(8))
+            .is_none()
+    };
+    if shape.windows(2).any(too_large) {
+        return Err(ModelError::format("layer too large"));
+    }
+    Ok(())
+}
+
+impl<A: Activation, C: Cost> FFNet<A, C> {
+    /// Number of inputs, then the number of outputs of every layer, as passed to `new`
+    pub fn shape(&self) -> Vec<usize> {
+        let mut shape = vec![self.layers.first().map_or(0, |layer| layer.in_shape)];
+        shape.extend(self.layers.iter().map(|layer| layer.out_shape));
+        shape
+    }
+
+    /// Writes the shape, activation and cost names, and every weight and bias, in the binary
+    /// format `load` reads. Training settings (regularization, dropout, autograd) aren't saved.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ModelError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Reads a net written by `save`, failing with `ModelError::Mismatch` if it was saved with a
+    /// different activation or cost than `A` and `C`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ModelError> {
+        Self::read_from(&mut BufReader::new(File::open(path)?))
+    }
+
+    /// `save` to any writer
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), ModelError> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        write_string(writer, A::name())?;
+        write_string(writer, C::name())?;
+        let shape = self.shape();
+        writer.write_all(&(shape.len() as u32).to_le_bytes())?;
+        for size in shape {
+            writer.write_all(&(size as u64).to_le_bytes())?;
+        }
+        for layer in self.layers.iter() {
+            write_matrix(writer, &layer.weights)?;
+            write_matrix(writer, &layer.biases)?;
+        }
+        Ok(())
+    }
+
+    /// `load` from any reader
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Self, ModelError> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(ModelError::format("not an FFNet file"));
+        }
+        let version = read_u32(reader)?;
+        if version != VERSION {
+            return Err(ModelError::format(format!(
+                "unsupported version {} (expected {})",
+                version, VERSION
+            )));
+        }
+        Self::check_names(&read_string(reader)?, &read_string(reader)?)?;
+        let shape = (0..read_u32(reader)?)
+            .map(|_| read_u64(reader).map(|size| size as usize))
+            .collect::<Result<Vec<usize>, ModelError>>()?;
+        check_shape(&shape)?;
+        let mut params = Vec::with_capacity(shape.len().saturating_sub(1));
+        for sizes in shape.windows(2) {
+            let weights = read_matrix(reader, sizes[0], sizes[1])?;
+            let biases = read_matrix(reader, 1, sizes[1])?;
+            params.push((weights, biases));
+        }
+        Ok(Self::from_params(params))
+    }
+
+    /// The same as `save`, as human-readable JSON
+    pub fn save_json<P: AsRef<Path>>(&self, path: P) -> Result<(), ModelError> {
+        std::fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    /// `load` for files written by `save_json`
+    pub fn load_json<P: AsRef<Path>>(path: P) -> Result<Self, ModelError> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    /// Weights are written as arrays of rows. JSON has no NaN or infinity, so a net with a
+    /// non-finite param fails with `ModelError::NonFinite` rather than writing something
+    /// `from_json` can't read back; `save` keeps them.
+    pub fn to_json(&self) -> Result<String, ModelError> {
+        for (i, layer) in self.layers.iter().enumerate() {
+            for (what, param) in [("weights", &layer.weights), ("biases", &layer.biases)] {
+                if !param.iter().all(|x| x.is_finite()) {
+                    return Err(ModelError::NonFinite(format!("layer {} {}", i, what)));
+                }
+            }
+        }
+        let layers = self.layers.iter().map(|layer| {
+            Json::object([
+                ("weights", json_matrix(&layer.weights)),
+                ("biases", Json::numbers(layer.biases.iter().copied())),
+            ])
+        });
+        Ok(Json::object([
+            ("format", Json::String(JSON_FORMAT.to_string())),
+            ("version", Json::Number(VERSION as f64)),
+            ("activation", Json::String(A::name().to_string())),
+            ("cost", Json::String(C::name().to_string())),
+            (
+                "shape",
+                Json::numbers(self.shape().into_iter().map(|size| size as f64)),
+            ),
+            ("layers", Json::Array(layers.collect())),
+        ])
+        .to_string())
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, ModelError> {
+        let json = Json::parse(text).map_err(ModelError::format)?;
+        let field = |key: &'static str| {
+            json.get(key)
+                .ok_or_else(|| ModelError::format(format!("missing \"{}\"", key)))
+        };
+        if field("format")?.as_str() != Some(JSON_FORMAT) {
+            return Err(ModelError::format("not an FFNet file"));
+        }
+        let version = field("version")?.as_usize();
+        if version != Some(VERSION as usize) {
+            return Err(ModelError::format(format!(
+                "unsupported version {} (expected {})",
+                field("version")?,
+                VERSION
+            )));
+        }
+        let name = |key| {
+            field(key)?
+                .as_str()
+                .ok_or_else(|| ModelError::format(format!("\"{}\" should be a string", key)))
+        };
+        Self::check_names(name("activation")?, name("cost")?)?;
+        let bad_shape = || ModelError::format("\"shape\" should be an array of sizes");
+        let shape = field("shape")?
+            .as_array()
+            .ok_or_else(bad_shape)?
+            .iter()
+            .map(|size| size.as_usize().ok_or_else(bad_shape))
+            .collect::<Result<Vec<usize>, ModelError>>()?;
+        check_shape(&shape)?;
+        let layers = field("layers")?
+            .as_array()
+            .filter(|layers| layers.len() == shape.len().saturating_sub(1))
+            .ok_or_else(|| ModelError::format("\"layers\" doesn't match \"shape\""))?;
+        let mut params = Vec::with_capacity(layers.len());
+        for (i, (layer, sizes)) in layers.iter().zip(shape.windows(2)).enumerate() {
+            let missing = |key| ModelError::format(format!("layer {} is missing \"{}\"", i, key));
+            let weights = layer.get("weights").ok_or_else(|| missing("weights"))?;
+            let biases = layer.get("biases").ok_or_else(|| missing("biases"))?;
+            let what = format!("layer {} weights", i);
+            let weights = matrix_from_json(weights, sizes[0], sizes[1], &what)?;
+            // biases are a flat array, ie. 1 row of out_shape
+            let what = format!("layer {} biases", i);
+            let mut biases =
+                matrix_from_json(&Json::Array(vec![biases.clone()]), sizes[1], 1, &what)?;
+            biases.transpose();
+            params.push((weights, biases));
+        }
+        Ok(Self::from_params(params))
+    }
+
+    fn check_names(activation: &str, cost: &str) -> Result<(), ModelError> {
+        if activation != A::name() {
+            return Err(ModelError::Mismatch {
+                field: "activation",
+                expected: A::name().to_string(),
+                found: activation.to_string(),
+            });
+        }
+        if cost != C::name() {
+            return Err(ModelError::Mismatch {
+                field: "cost",
+                expected: C::name().to_string(),
+                found: cost.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    // params holds (weights, biases) for every layer, as read for a shape `check_shape` passed
+    fn from_params(params: Vec<(Matrix<f64>, Matrix<f64>)>) -> Self {
+        let in_shape = params[0].0.w();
+        let layers = params
+            .into_iter()
+            .map(|(weights, biases)| {
+                let mut layer = Layer::new(weights.w(), weights.h(), weights);
+                layer.biases = biases;
+                layer
+            })
+            .collect();
+        Self::from_layers(in_shape, layers)
+    }
+}
@@ -1,88 +1,240 @@
 use super::{Activation, Cost, FFNet, Layer};
 use crate::algebra::{MatLike, Matrix};
+use crate::nn::autograd::{Tape, Var};
+use crate::nn::binary::read_bytes;
+use crate::nn::checkpoint::RngState;
+use crate::nn::init::Initializer;
+use crate::nn::layers::Mask;
+use crate::nn::train::Trainable;
+use crate::nn::ModelError;
+use rand::Rng;
 
 impl<A: Activation, C: Cost> FFNet<A, C> {
-    pub fn sgd(&mut self, x: &Matrix<f64>, y: &Matrix<f64>, batch_size: usize, learning_rate: f64) {
-        self.randomize_params();
-        let (mut weight_grad, mut bias_grad) = self.init_params();
-        for i in 0..x.h() {
-            let (case_weight, case_bias) =
-                self.single_case_grad(x.clone_row(i).transpose(), &y.clone_row(i));
-            for j in (0..self.layers.len()).rev() {
-                weight_grad[j] =
-                    &weight_grad[j] - &case_weight[j] * (learning_rate / batch_size as f64);
-                bias_grad[j] = &bias_grad[j] - &case_bias[j] * (learning_rate / batch_size as f64);
-            }
-            if i % batch_size == 0 {
-                self.apply_grad(&weight_grad, &bias_grad);
-                (weight_grad, bias_grad) = self.init_params();
-                println!("grad applied");
-            }
+    /// Gradients from either `autograd_case_grad` or `single_case_grad`, without dropout.
+    pub fn case_grad(
+        &mut self,
+        input: &Matrix<f64>,
+        output: &Matrix<f64>,
+    ) -> (Vec<Matrix<f64>>, Vec<Matrix<f64>>) {
+        if self.autograd {
+            self.autograd_case_grad(input, output)
+        } else {
+            self.single_case_grad(input, output)
         }
     }
 
-    fn single_case_grad(
-        &mut self,
+    /// @param input col vec, output row vec
+    /// @return cost plus the regularization penalty, without dropout, as used by `case_grad`
+    pub fn case_cost(&mut self, input: &Matrix<f64>, output: &Matrix<f64>) -> f64 {
+        C::calc(&self.predict(&input.new_transposed()), output) + self.penalty()
+    }
+
+    /// @param input col vec, output row vec
+    /// @return (weight gradients, bias gradients), one per layer
+    pub fn single_case_grad(
+        &self,
         input: &Matrix<f64>,
         output: &Matrix<f64>,
     ) -> (Vec<Matrix<f64>>, Vec<Matrix<f64>>) {
-        let result = self.pred_single(input.clone());
+        let (_, weight_grads, bias_grads) = self.backprop(&input.new_transposed(), output, &[]);
+        (weight_grads, bias_grads)
+    }
+
+    /// Same as `single_case_grad`, but the gradients are found by recording the forward pass on
+    /// a `Tape` rather than by the hand-written backprop.
+    pub fn autograd_case_grad(
+        &self,
+        input: &Matrix<f64>,
+        output: &Matrix<f64>,
+    ) -> (Vec<Matrix<f64>>, Vec<Matrix<f64>>) {
+        let (_, weight_grads, bias_grads) =
+            self.autograd_backprop(&input.new_transposed(), output, &[]);
+        (weight_grads, bias_grads)
+    }
+
+    /// A dropout mask for every layer with dropout, or none at all outside training.
+    fn sample_masks(&mut self, cases: usize) -> Vec<Option<Mask>> {
+        let training = self.training;
+        self.layers
+            .iter_mut()
+            .map(|layer| {
+                let out_shape = layer.out_shape;
+                layer
+                    .dropout
+                    .as_mut()
+                    .filter(|_| training)
+                    .map(|dropout| dropout.sample_mask(out_shape, cases))
+            })
+            .collect()
+    }
+
+    /// @param x, y one case per row
+    /// @param masks applied to the activated output of the layer with the same index, if any
+    /// @return (mean cost, weight gradients, bias gradients), averaged over the rows, with the
+    /// regularization penalty added to both
+    fn backprop(
+        &self,
+        x: &Matrix<f64>,
+        y: &Matrix<f64>,
+        masks: &[Option<Mask>],
+    ) -> (f64, Vec<Matrix<f64>>, Vec<Matrix<f64>>) {
+        let mask = |i: usize| masks.get(i).and_then(Option::as_ref);
+        let mut activated = vec![x.clone()];
+        let mut unactivated = Vec::with_capacity(self.layers.len());
+        for (i, layer) in self.layers.iter().enumerate() {
+            let (layer_unactivated, mut layer_activated) =
+                layer.pred_batch(activated.last().unwrap());
+            if let Some(mask) = mask(i) {
+                layer_activated = mask.apply(&layer_activated);
+            }
+            unactivated.push(layer_unactivated);
+            activated.push(layer_activated);
+        }
+        let pred = activated.pop().unwrap();
+        let cases = x.h() as f64;
+        let cost = C::calc(&pred, y) / cases + self.penalty();
+
         let (mut weight_grad, mut bias_grad);
-        let mut cost_wrt_output: Matrix<f64> = C::prime(result, output);
-        let mut weight_grads = vec![Matrix::<f64>::default(); self.unactivated.len()];
-        let mut bias_grads = vec![Matrix::<f64>::default(); self.unactivated.len()];
-
-        for (i, layer) in self.layers.iter_mut().enumerate().rev() {
-            (weight_grad, bias_grad, cost_wrt_output) = layer.calculate_grad(
-                &self.activated[i],
-                &self.unactivated[i],
-                &cost_wrt_output,
-                i == 0,
-            );
+        let mut cost_wrt_output = C::prime(&pred, y) * (1.0 / cases);
+        let mut weight_grads = vec![Matrix::<f64>::default(); self.layers.len()];
+        let mut bias_grads = vec![Matrix::<f64>::default(); self.layers.len()];
+        for (i, layer) in self.layers.iter().enumerate().rev() {
+            if let Some(mask) = mask(i) {
+                cost_wrt_output = mask.backward(&cost_wrt_output);
+            }
+            (weight_grad, bias_grad, cost_wrt_output) =
+                layer.calculate_grad(&activated[i], &unactivated[i], &cost_wrt_output, i == 0);
             weight_grads[i] = weight_grad;
             bias_grads[i] = bias_grad;
         }
-        (weight_grads, bias_grads)
+        (cost, weight_grads, bias_grads)
     }
 
-    fn init_params(&self) -> (Vec<Matrix<f64>>, Vec<Matrix<f64>>) {
+    /// `backprop` on a `Tape`
+    fn autograd_backprop(
+        &self,
+        x: &Matrix<f64>,
+        y: &Matrix<f64>,
+        masks: &[Option<Mask>],
+    ) -> (f64, Vec<Matrix<f64>>, Vec<Matrix<f64>>) {
+        let tape = Tape::new();
+        let params: Vec<(Var, Var)> = self
+            .layers
+            .iter()
+            .map(|layer| {
+                (
+                    tape.var(layer.weights.clone()),
+                    tape.var(layer.biases.clone()),
+                )
+            })
+            .collect();
+        // ones * biases^T repeats the biases on every row
+        let ones = tape.var(Matrix::new_uniform(1.0, 1, x.h()));
+        let mut activated = tape.var(x.clone());
+        for (i, &(weights, biases)) in params.iter().enumerate() {
+            activated =
+                (activated * weights.transpose() + ones * biases.transpose()).activate::<A>();
+            if let Some(Some(mask)) = masks.get(i) {
+                activated = activated.mul_element_wise(tape.var(mask.scale.clone()))
+                    + tape.var(mask.shift.clone());
+            }
+        }
+        let cost = activated.cost::<C>(y) * (1.0 / x.h() as f64);
+        let grads = cost.backward();
+        // the penalty doesn't depend on the data, so it's added outside the tape
+        let (weight_grads, bias_grads) = params
+            .iter()
+            .zip(self.layers.iter())
+            .map(|(&(weights, biases), layer)| {
+                let weight_grad = grads.wrt(weights).expect("every weight feeds the cost");
+                let bias_grad = grads.wrt(biases).expect("every bias feeds the cost");
+                layer.regularize(weight_grad.clone(), bias_grad.clone())
+            })
+            .unzip();
         (
-            self.layers
-                .iter()
-                .map(|layer| Matrix::<f64>::new_uniform(0.0, layer.in_shape, layer.out_shape))
-                .collect(),
-            self.layers
-                .iter()
-                .map(|layer| Matrix::<f64>::new_uniform(0.0, 1, layer.out_shape))
-                .collect(),
+            cost.value()[(0, 0)] + self.penalty(),
+            weight_grads,
+            bias_grads,
         )
     }
 
-    fn apply_grad(&mut self, weight: &[Matrix<f64>], bias: &[Matrix<f64>]) {
-        for i in 0..self.layers.len() {
-            self.layers[i].apply_grad(&weight[i], &bias[i]);
+    /// Redraws every weight with `Initializer::for_activation(A::name())`, unseeded, and zeroes
+    /// the biases.
+    pub fn randomize_params(&mut self) {
+        let initializer = Initializer::for_activation(A::name());
+        let mut rng = rand::thread_rng();
+        for layer in self.layers.iter_mut() {
+            layer.initialize(&initializer, &mut rng);
         }
     }
+}
 
-    fn randomize_params(&mut self) {
-        for layer in self.layers.iter_mut() {
-            layer.randomize_params();
+impl<A: Activation, C: Cost> Trainable for FFNet<A, C> {
+    fn batch_grad(&mut self, x: &Matrix<f64>, y: &Matrix<f64>) -> (f64, Vec<Matrix<f64>>) {
+        let masks = self.sample_masks(x.h());
+        let (cost, weight_grads, bias_grads) = if self.autograd {
+            self.autograd_backprop(x, y, &masks)
+        } else {
+            self.backprop(x, y, &masks)
+        };
+        let grads = weight_grads
+            .into_iter()
+            .zip(bias_grads)
+            .flat_map(|(weight, bias)| [weight, bias])
+            .collect();
+        (cost, grads)
+    }
+
+    fn batch_cost(&mut self, x: &Matrix<f64>, y: &Matrix<f64>) -> f64 {
+        C::calc(&self.predict(x), y) / x.h() as f64 + self.penalty()
+    }
+
+    fn params_mut(&mut self) -> Vec<&mut Matrix<f64>> {
+        FFNet::params_mut(self)
+    }
+
+    fn predict(&self, x: &Matrix<f64>) -> Matrix<f64> {
+        FFNet::predict(self, x)
+    }
+
+    /// The mask RNG of every layer with dropout, in order
+    fn state(&self) -> Vec<u8> {
+        self.layers
+            .iter()
+            .filter_map(|layer| layer.dropout.as_ref())
+            .flat_map(|dropout| dropout.rng_state().to_bytes())
+            .collect()
+    }
+
+    fn load_state(&mut self, mut state: &[u8]) -> Result<(), ModelError> {
+        for dropout in self
+            .layers
+            .iter_mut()
+            .filter_map(|layer| layer.dropout.as_mut())
+        {
+            dropout.set_rng_state(RngState::from_bytes(read_bytes(&mut state)?));
+        }
+        if !state.is_empty() {
+            return Err(ModelError::format("model state is for more dropout layers"));
         }
+        Ok(())
     }
 }
 
 impl<A: Activation, C: Cost> Layer<A, C> {
     // a_wrt_b = partial derivative of a with respect to b
-    /// @return (weight gradient, bias gradient, cost_wrt_input = cost_wrt_output for next layer)
+    /// All of input, unactivated_output and cost_wrt_output hold one case per row.
+    /// @return (weight gradient, bias gradient, cost_wrt_input = cost_wrt_output for next layer),
+    /// with the gradients summed over the cases, plus the gradient of the regularization penalty
     pub fn calculate_grad(
         &self,
-        input: &Matrix<f64>,              // row
-        unactivated_output: &Matrix<f64>, // col
-        cost_wrt_output: &Matrix<f64>,    // col
+        input: &Matrix<f64>,
+        unactivated_output: &Matrix<f64>,
+        cost_wrt_output: &Matrix<f64>,
         is_first_layer: bool,
     ) -> (Matrix<f64>, Matrix<f64>, Matrix<f64>) {
         assert_eq!(
-            cost_wrt_output.h(),
+            cost_wrt_output.w(),
             self.out_shape,
             "cost_wrt_output does not match out_shape"
         );
@@ -93,28 +245,44 @@ impl<A: Activation, C: Cost> Layer<A, C> {
         );
 
         let output_wrt_unactivated = unactivated_output.apply(|x| A::prime(x));
-        let mut cost_wrt_unactivated = cost_wrt_output.mul_element_wise(output_wrt_unactivated);
-        let weight_grad = &cost_wrt_unactivated * input;
+        let cost_wrt_unactivated = cost_wrt_output.mul_element_wise(output_wrt_unactivated);
+        let weight_grad = cost_wrt_unactivated.new_transposed() * input;
+        let mut bias_grad = cost_wrt_unactivated.sum_rows();
+        bias_grad.transpose();
+        let (weight_grad, bias_grad) = self.regularize(weight_grad, bias_grad);
 
         // cost_wrt_input will be passed too next layer as cost_wrt_output, so its unneeded if this
         // is the first layer
         if is_first_layer {
-            (weight_grad, cost_wrt_unactivated, Matrix::<f64>::default())
+            (weight_grad, bias_grad, Matrix::<f64>::default())
         } else {
-            let mut cost_wrt_input = cost_wrt_unactivated.transpose() * &self.weights;
-            cost_wrt_unactivated.transpose();
-            cost_wrt_input.transpose();
-            (weight_grad, cost_wrt_unactivated, cost_wrt_input)
+            let cost_wrt_input = cost_wrt_unactivated * &self.weights;
+            (weight_grad, bias_grad, cost_wrt_input)
         }
     }
 
-    pub fn apply_grad(&mut self, weight: &Matrix<f64>, bias: &Matrix<f64>) {
-        self.weights = &self.weights + weight;
-        self.biases = &self.biases + bias;
+    /// Adds the gradient of the regularization penalty to the gradients of the cost.
+    fn regularize(
+        &self,
+        weight_grad: Matrix<f64>,
+        bias_grad: Matrix<f64>,
+    ) -> (Matrix<f64>, Matrix<f64>) {
+        if self.regularization.is_none() {
+            return (weight_grad, bias_grad);
+        }
+        let weight_grad = weight_grad + self.regularization.grad(&self.weights);
+        if self.regularization.biases {
+            (
+                weight_grad,
+                bias_grad + self.regularization.grad(&self.biases),
+            )
+        } else {
+            (weight_grad, bias_grad)
+        }
     }
 
-    pub fn randomize_params(&mut self) {
-        self.weights = Matrix::random(-0.3_f64, 0.3_f64, self.weights.w(), self.weights.h());
-        self.biases = Matrix::random(-0.3_f64, 0.3_f64, self.biases.w(), self.biases.h());
+    pub fn initialize<R: Rng>(&mut self, initializer: &Initializer, rng: &mut R) {
+        self.weights = initializer.weights(self.in_shape, self.out_shape, rng);
+        self.biases = Matrix::new_uniform(0.0, 1, self.out_shape);
     }
 }
@@ -0,0 +1,144 @@
+use super::activations::Activation;
+use super::cost::Cost;
+use super::feedforward::FFNet;
+use super::train::Trainable;
+use crate::algebra::{MatLike, Matrix};
+
+/// Worst relative error between the analytic and central difference gradients of one layer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayerError {
+    pub weights: f64,
+    pub biases: f64,
+}
+
+impl LayerError {
+    pub fn worst(&self) -> f64 {
+        self.weights.max(self.biases)
+    }
+}
+
+/// |analytic - numeric| / (|analytic| + |numeric|), with gradients that both vanish counting as
+/// a match
+pub fn relative_error(analytic: f64, numeric: f64) -> f64 {
+    let scale = analytic.abs() + numeric.abs();
+    if scale < 1e-12 {
+        0.0
+    } else {
+        (analytic - numeric).abs() / scale
+    }
+}
+
+/// Nudges every weight and bias of `net` by ±`epsilon`, and compares the resulting change in cost
+/// against `FFNet::case_grad`.
+/// @param input col vec, output row vec
+/// @return one entry per layer
+pub fn check_net<A: Activation, C: Cost>(
+    net: &mut FFNet<A, C>,
+    input: &Matrix<f64>,
+    output: &Matrix<f64>,
+    epsilon: f64,
+) -> Vec<LayerError> {
+    let (weight_grads, bias_grads) = net.case_grad(input, output);
+    let analytic: Vec<Matrix<f64>> = weight_grads
+        .into_iter()
+        .zip(bias_grads)
+        .flat_map(|(weight, bias)| [weight, bias])
+        .collect();
+
+    // params_mut lists each layer's weights then biases, same order as analytic
+    let worst: Vec<f64> = analytic
+        .iter()
+        .enumerate()
+        .map(|(param, grad)| {
+            let mut worst = 0_f64;
+            for i in 0..grad.h() {
+                for j in 0..grad.w() {
+                    let original = net.params_mut()[param][(i, j)];
+                    net.params_mut()[param][(i, j)] = original + epsilon;
+                    let plus = net.case_cost(input, output);
+                    net.params_mut()[param][(i, j)] = original - epsilon;
+                    let minus = net.case_cost(input, output);
+                    net.params_mut()[param][(i, j)] = original;
+
+                    let numeric = (plus - minus) / (2.0 * epsilon);
+                    worst = worst.max(relative_error(grad[(i, j)], numeric));
+                }
+            }
+            worst
+        })
+        .collect();
+
+    worst
+        .chunks(2)
+        .map(|pair| LayerError {
+            weights: pair[0],
+            biases: pair[1],
+        })
+        .collect()
+}
+
+/// Nudges every element of every param of `model` by ±`epsilon`, and compares the resulting change
+/// in the cost reported by `Trainable::batch_grad` against its gradients.
+/// @param x, y one case per row
+/// @return worst relative error of each param, in `params_mut` order
+pub fn check_model<M: Trainable>(
+    model: &mut M,
+    x: &Matrix<f64>,
+    y: &Matrix<f64>,
+    epsilon: f64,
+) -> Vec<f64> {
+    let (_, analytic) = model.batch_grad(x, y);
+    analytic
+        .iter()
+        .enumerate()
+        .map(|(param, grad)| {
+            let mut worst = 0_f64;
+            for i in 0..grad.h() {
+                for j in 0..grad.w() {
+                    let original = model.params_mut()[param][(i, j)];
+                    model.params_mut()[param][(i, j)] = original + epsilon;
+                    let (plus, _) = model.batch_grad(x, y);
+                    model.params_mut()[param][(i, j)] = original - epsilon;
+                    let (minus, _) = model.batch_grad(x, y);
+                    model.params_mut()[param][(i, j)] = original;
+
+                    let numeric = (plus - minus) / (2.0 * epsilon);
+                    worst = worst.max(relative_error(grad[(i, j)], numeric));
+                }
+            }
+            worst
+        })
+        .collect()
+}
+
+/// @return worst relative error between `A::prime` and a central difference of `A::calc`
+pub fn check_activation<A: Activation>(points: &[f64], epsilon: f64) -> f64 {
+    points
+        .iter()
+        .map(|&x| {
+            let numeric = (A::calc(x + epsilon) - A::calc(x - epsilon)) / (2.0 * epsilon);
+            relative_error(A::prime(x), numeric)
+        })
+        .fold(0.0, f64::max)
+}
+
+/// @return worst relative error between `C::prime` and a central difference of `C::calc`, over
+/// every element of `pred`
+pub fn check_cost<C: Cost>(pred: &Matrix<f64>, actual: &Matrix<f64>, epsilon: f64) -> f64 {
+    let analytic = C::prime(pred, actual);
+    let mut nudged = pred.clone();
+    let mut worst = 0_f64;
+    for i in 0..pred.h() {
+        for j in 0..pred.w() {
+            nudged[(i, j)] = pred[(i, j)] + epsilon;
+            let plus = C::calc(&nudged, actual);
+            nudged[(i, j)] = pred[(i, j)] - epsilon;
+            let minus = C::calc(&nudged, actual);
+            nudged[(i, j)] = pred[(i, j)];
+
+            let numeric = (plus - minus) / (2.0 * epsilon);
+            worst = worst.max(relative_error(analytic[(i, j)], numeric));
+        }
+    }
+    worst
+}
@@ -0,0 +1,145 @@
+use crate::algebra::{MatLike, Matrix};
+use rand::Rng;
+
+/// How a layer's weights (out_shape x in_shape) are first set. fan_in is in_shape, fan_out is
+/// out_shape.
+#[derive(Debug, Clone)]
+pub enum Initializer {
+    /// U(-limit, limit) with limit = sqrt(6 / (fan_in + fan_out)) (Glorot & Bengio, 2010)
+    XavierUniform,
+    /// N(0, 2 / (fan_in + fan_out))
+    XavierNormal,
+    /// U(-limit, limit) with limit = sqrt(6 / fan_in) (He et al., 2015)
+    HeUniform,
+    /// N(0, 2 / fan_in)
+    HeNormal,
+    /// U(-limit, limit) with limit = sqrt(3 / fan_in)
+    LeCunUniform,
+    /// N(0, 1 / fan_in) (LeCun et al., 1998), what `SELU` nets need
+    LeCunNormal,
+    /// A random matrix with orthonormal rows (or columns, if it's taller than it is wide), times
+    /// `gain` (Saxe et al., 2014)
+    Orthogonal {
+        gain: f64,
+    },
+    Zeros,
+    Constant(f64),
+    /// U(low, high), whatever the layer's size
+    Uniform(f64, f64),
+    /// Exactly this matrix, which must be out_shape x in_shape (or filters x channels * kernel^2)
+    Given(Matrix<f64>),
+}
+
+impl Initializer {
+    /// The usual scheme for layers followed by the activation (or layer, eg. "prelu" for
+    /// `PReLU`) called `name`: He for the ReLU family, LeCun for SELU, Xavier for everything else.
+    pub fn for_activation(name: &str) -> Self {
+        match name {
+            "relu" | "leaky_relu" | "prelu" | "elu" | "gelu" | "gelu_tanh" | "silu" | "mish" => {
+                Initializer::HeNormal
+            }
+            "selu" => Initializer::LeCunNormal,
+            _ => Initializer::XavierUniform,
+        }
+    }
+
+    /// @return weights for a layer taking `in_shape` inputs to `out_shape` outputs
+    pub fn weights<R: Rng>(&self, in_shape: usize, out_shape: usize, rng: &mut R) -> Matrix<f64> {
+        self.check_given(in_shape, out_shape, "out_shape x in_shape");
+        self.draw(in_shape, out_shape, in_shape, out_shape, rng)
+    }
+
+    /// @return a convolution's `filters` kernels, one per row, each `channels` x `kernel` x
+    /// `kernel` flattened. Every kernel position counts towards the fans, so
+    /// fan_in = channels * kernel^2 and fan_out = filters * kernel^2.
+    pub fn kernels<R: Rng>(
+        &self,
+        channels: usize,
+        filters: usize,
+        kernel: usize,
+        rng: &mut R,
+    ) -> Matrix<f64> {
+        let area = kernel * kernel;
+        self.check_given(channels * area, filters, "filters x channels * kernel^2");
+        self.draw(
+            channels * area,
+            filters * area,
+            channels * area,
+            filters,
+            rng,
+        )
+    }
+
+    fn check_given(&self, w: usize, h: usize, shape: &str) {
+        if let Initializer::Given(weights) = self {
+            assert_eq!(
+                (weights.w(), weights.h()),
+                (w, h),
+                "given weights must be {}",
+                shape
+            );
+        }
+    }
+
+    // a w x h matrix, scaled for fan_in and fan_out
+    fn draw<R: Rng>(
+        &self,
+        fan_in: usize,
+        fan_out: usize,
+        w: usize,
+        h: usize,
+        rng: &mut R,
+    ) -> Matrix<f64> {
+        let (fan_in, fan_out) = (fan_in as f64, fan_out as f64);
+        let uniform = |limit: f64, rng: &mut R| Matrix::random_with(-limit, limit, w, h, rng);
+        let normal =
+            |std_dev: f64, rng: &mut R| Matrix::random_normal_with(0.0, std_dev, w, h, rng);
+        match self {
+            Initializer::XavierUniform => uniform((6.0 / (fan_in + fan_out)).sqrt(), rng),
+            Initializer::XavierNormal => normal((2.0 / (fan_in + fan_out)).sqrt(), rng),
+            Initializer::HeUniform => uniform((6.0 / fan_in).sqrt(), rng),
+            Initializer::HeNormal => normal((2.0 / fan_in).sqrt(), rng),
+            Initializer::LeCunUniform => uniform((3.0 / fan_in).sqrt(), rng),
+            Initializer::LeCunNormal => normal((1.0 / fan_in).sqrt(), rng),
+            Initializer::Orthogonal { gain } => orthogonal(w, h, rng) * *gain,
+            Initializer::Zeros => Matrix::new_uniform(0.0, w, h),
+            Initializer::Constant(value) => Matrix::new_uniform(*value, w, h),
+            Initializer::Uniform(low, high) => Matrix::random_with(*low, *high, w, h, rng),
+            Initializer::Given(weights) => weights.clone(),
+        }
+    }
+}
+
+// w x h, with orthonormal rows if w >= h and orthonormal columns otherwise
+fn orthogonal<R: Rng>(w: usize, h: usize, rng: &mut R) -> Matrix<f64> {
+    // orthonormalise the short side's vectors, each as long as the long side
+    let (count, len) = (w.min(h), w.max(h));
+    let mut vectors: Vec<Vec<f64>> = (0..count)
+        .map(|_| {
+            Matrix::random_normal_with(0.0, 1.0, len, 1, rng)
+                .iter()
+                .copied()
+                .collect()
+        })
+        .collect();
+    // modified Gram-Schmidt
+    for i in 0..count {
+        for j in 0..i {
+            let dot: f64 = vectors[i].iter().zip(&vectors[j]).map(|(a, b)| a * b).sum();
+            let previous = vectors[j].clone();
+            for (x, p) in vectors[i].iter_mut().zip(previous) {
+                *x -= dot * p;
+            }
+        }
+        let norm = vectors[i].iter().map(|x| x * x).sum::<f64>().sqrt();
+        for x in vectors[i].iter_mut() {
+            *x /= norm;
+        }
+    }
+    let rows = Matrix::new(vectors.into_iter().flatten().collect(), len, count);
+    if w >= h {
+        rows
+    } else {
+        rows.new_transposed()
+    }
+}
@@ -0,0 +1,45 @@
+use super::Layer;
+use crate::algebra::Matrix;
+use crate::nn::activations::{Activation, ActivationFn};
+
+/// Applies an activation element-wise.
+pub struct ActivationLayer {
+    activation: ActivationFn,
+    input: Matrix<f64>,
+}
+
+impl ActivationLayer {
+    pub fn new<F: Into<ActivationFn>>(activation: F) -> Self {
+        Self {
+            activation: activation.into(),
+            input: Matrix::default(),
+        }
+    }
+
+    pub fn of<A: Activation + 'static>() -> Self {
+        Self::new(ActivationFn::of::<A>())
+    }
+}
+
+impl Layer for ActivationLayer {
+    fn forward(&mut self, input: &Matrix<f64>) -> Matrix<f64> {
+        self.input = input.clone();
+        self.predict(input)
+    }
+
+    fn predict(&self, input: &Matrix<f64>) -> Matrix<f64> {
+        input.apply(|x| self.activation.calc(x))
+    }
+
+    fn backward(&mut self, cost_wrt_output: &Matrix<f64>) -> (Matrix<f64>, Vec<Matrix<f64>>) {
+        let output_wrt_input = self.input.apply(|x| self.activation.prime(x));
+        (
+            cost_wrt_output.mul_element_wise(output_wrt_input),
+            Vec::new(),
+        )
+    }
+
+    fn name(&self) -> String {
+        self.activation.name().to_string()
+    }
+}
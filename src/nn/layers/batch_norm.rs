@@ -0,0 +1,196 @@
+use super::Layer;
+use crate::algebra::{MatLike, Matrix};
+use crate::nn::ModelError;
+
+/// Batch normalization (Ioffe & Szegedy, 2015): normalises every feature over the cases of the
+/// batch, then scales by gamma and shifts by beta, both learned. Running estimates of each
+/// feature's mean and variance are kept while training and used instead of the batch statistics
+/// in eval mode and by `predict`.
+pub struct BatchNorm {
+    gamma: Matrix<f64>, // col vec, one per feature
+    beta: Matrix<f64>,  // col vec
+    running_mean: Matrix<f64>,
+    running_variance: Matrix<f64>,
+    /// Weight of each new batch in the running statistics
+    pub momentum: f64,
+    pub epsilon: f64,
+    training: bool,
+    // from the last forward pass
+    normalised: Matrix<f64>,
+    inv_std: Vec<f64>,
+    used_batch_statistics: bool,
+}
+
+impl BatchNorm {
+    pub fn new(features: usize) -> Self {
+        Self {
+            gamma: Matrix::new_uniform(1.0, 1, features),
+            beta: Matrix::new_uniform(0.0, 1, features),
+            running_mean: Matrix::new_uniform(0.0, 1, features),
+            running_variance: Matrix::new_uniform(1.0, 1, features),
+            momentum: 0.1,
+            epsilon: 1e-5,
+            training: true,
+            normalised: Matrix::default(),
+            inv_std: Vec::new(),
+            used_batch_statistics: false,
+        }
+    }
+
+    pub fn running_mean(&self) -> &Matrix<f64> {
+        &self.running_mean
+    }
+
+    pub fn running_variance(&self) -> &Matrix<f64> {
+        &self.running_variance
+    }
+
+    // (mean, biased variance) of every column
+    fn batch_statistics(input: &Matrix<f64>) -> (Vec<f64>, Vec<f64>) {
+        let cases = input.h() as f64;
+        let mean: Vec<f64> = input.sum_rows().iter().map(|sum| sum / cases).collect();
+        let mut variance = vec![0.0; input.w()];
+        for i in 0..input.h() {
+            for (j, v) in variance.iter_mut().enumerate() {
+                let d = input[(i, j)] - mean[j];
+                *v += d * d / cases;
+            }
+        }
+        (mean, variance)
+    }
+
+    // ((input - mean) * inv_std, gamma * that + beta)
+    fn normalise(
+        &self,
+        input: &Matrix<f64>,
+        mean: &[f64],
+        inv_std: &[f64],
+    ) -> (Matrix<f64>, Matrix<f64>) {
+        let mut normalised = input.clone();
+        let mut output = input.clone();
+        for i in 0..input.h() {
+            for j in 0..input.w() {
+                normalised[(i, j)] = (input[(i, j)] - mean[j]) * inv_std[j];
+                output[(i, j)] = self.gamma[(j, 0)] * normalised[(i, j)] + self.beta[(j, 0)];
+            }
+        }
+        (normalised, output)
+    }
+
+    fn running_inv_std(&self) -> Vec<f64> {
+        self.running_variance
+            .iter()
+            .map(|v| 1.0 / (v + self.epsilon).sqrt())
+            .collect()
+    }
+}
+
+impl Layer for BatchNorm {
+    fn forward(&mut self, input: &Matrix<f64>) -> Matrix<f64> {
+        // a single case has no variance to normalise by
+        self.used_batch_statistics = self.training && input.h() > 1;
+        let (mean, inv_std) = if self.used_batch_statistics {
+            let (mean, variance) = Self::batch_statistics(input);
+            // the running variance is unbiased
+            let correction = input.h() as f64 / (input.h() - 1) as f64;
+            for j in 0..input.w() {
+                let running = &mut self.running_mean[(j, 0)];
+                *running += self.momentum * (mean[j] - *running);
+                let running = &mut self.running_variance[(j, 0)];
+                *running += self.momentum * (variance[j] * correction - *running);
+            }
+            let inv_std = variance
+                .iter()
+                .map(|v| 1.0 / (v + self.epsilon).sqrt())
+                .collect();
+            (mean, inv_std)
+        } else {
+            (
+                self.running_mean.iter().copied().collect(),
+                self.running_inv_std(),
+            )
+        };
+        let (normalised, output) = self.normalise(input, &mean, &inv_std);
+        self.normalised = normalised;
+        self.inv_std = inv_std;
+        output
+    }
+
+    /// Always the inference pass, with the running statistics.
+    fn predict(&self, input: &Matrix<f64>) -> Matrix<f64> {
+        let mean: Vec<f64> = self.running_mean.iter().copied().collect();
+        self.normalise(input, &mean, &self.running_inv_std()).1
+    }
+
+    fn backward(&mut self, cost_wrt_output: &Matrix<f64>) -> (Matrix<f64>, Vec<Matrix<f64>>) {
+        let (w, h) = (cost_wrt_output.w(), cost_wrt_output.h());
+        let mut beta_grad = cost_wrt_output.sum_rows();
+        beta_grad.transpose();
+        let mut gamma_grad = cost_wrt_output
+            .clone()
+            .mul_element_wise(self.normalised.clone())
+            .sum_rows();
+        gamma_grad.transpose();
+
+        let mut cost_wrt_input = cost_wrt_output.clone();
+        let cases = h as f64;
+        for i in 0..h {
+            for j in 0..w {
+                let scale = self.gamma[(j, 0)] * self.inv_std[j];
+                cost_wrt_input[(i, j)] = if self.used_batch_statistics {
+                    // every case also moves the batch mean and variance
+                    scale
+                        * (cost_wrt_output[(i, j)]
+                            - beta_grad[(j, 0)] / cases
+                            - self.normalised[(i, j)] * gamma_grad[(j, 0)] / cases)
+                } else {
+                    scale * cost_wrt_output[(i, j)]
+                };
+            }
+        }
+        (cost_wrt_input, vec![gamma_grad, beta_grad])
+    }
+
+    fn params_mut(&mut self) -> Vec<&mut Matrix<f64>> {
+        vec![&mut self.gamma, &mut self.beta]
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+    }
+
+    /// The running mean then variance, little-endian
+    fn state(&self) -> Vec<u8> {
+        self.running_mean
+            .iter()
+            .chain(self.running_variance.iter())
+            .flat_map(|x| x.to_le_bytes())
+            .collect()
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> Result<(), ModelError> {
+        let features = self.running_mean.h();
+        if state.len() != 16 * features {
+            return Err(ModelError::Mismatch {
+                field: "batch norm state",
+                expected: format!("{} bytes", 16 * features),
+                found: format!("{} bytes", state.len()),
+            });
+        }
+        let mut floats = state
+            .chunks_exact(8)
+            .map(|chunk| f64::from_le_bytes(chunk.try_into().unwrap()));
+        for x in self
+            .running_mean
+            .iter_mut()
+            .chain(self.running_variance.iter_mut())
+        {
+            *x = floats.next().unwrap();
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> String {
+        String::from("batch_norm")
+    }
+}
@@ -0,0 +1,255 @@
+use super::Layer;
+use crate::algebra::{MatLike, Matrix};
+use crate::nn::init::Initializer;
+use rand::{rngs::StdRng, SeedableRng};
+
+/// (channels, height, width) of the images a layer takes or gives. Every case is still one row,
+/// holding its channels one after another, each row-major, so MNIST's 784 pixel rows are
+/// (1, 28, 28) images as they are.
+pub type ImageShape = (usize, usize, usize);
+
+/// A square window slid over every channel of an image, as convolutions and pools do. Padding is
+/// on all four sides.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Window {
+    pub input: ImageShape,
+    pub size: usize,
+    pub stride: usize,
+    pub padding: usize,
+}
+
+impl Window {
+    pub fn new(input: ImageShape, size: usize) -> Self {
+        assert!(size > 0, "size must be positive");
+        Self {
+            input,
+            size,
+            stride: 1,
+            padding: 0,
+        }
+    }
+
+    pub fn input_len(&self) -> usize {
+        let (channels, height, width) = self.input;
+        channels * height * width
+    }
+
+    /// (height, width) of every output channel
+    pub fn output(&self) -> (usize, usize) {
+        let (_, height, width) = self.input;
+        let side = |len: usize| {
+            let padded = len + 2 * self.padding;
+            assert!(
+                padded >= self.size,
+                "window of {} does not fit a padded input of {}",
+                self.size,
+                padded
+            );
+            (padded - self.size) / self.stride + 1
+        };
+        (side(height), side(width))
+    }
+
+    pub fn check_input(&self, input: &Matrix<f64>) {
+        assert_eq!(
+            input.w(),
+            self.input_len(),
+            "input rows must hold {:?} images",
+            self.input
+        );
+    }
+
+    /// Calls `visit(position, channel, tap, source)` for every cell of every window that isn't
+    /// padding, where `position` is the window's index in its output channel, `tap` the cell's
+    /// index in the window, and `source` the cell's column in an input row.
+    pub fn for_each<F: FnMut(usize, usize, usize, usize)>(&self, mut visit: F) {
+        let (channels, height, width) = self.input;
+        let (out_height, out_width) = self.output();
+        for channel in 0..channels {
+            for out_y in 0..out_height {
+                for out_x in 0..out_width {
+                    let position = out_y * out_width + out_x;
+                    for dy in 0..self.size {
+                        // the padded image starts at -padding
+                        let y = (out_y * self.stride + dy).wrapping_sub(self.padding);
+                        if y >= height {
+                            continue;
+                        }
+                        for dx in 0..self.size {
+                            let x = (out_x * self.stride + dx).wrapping_sub(self.padding);
+                            if x >= width {
+                                continue;
+                            }
+                            let source = (channel * height + y) * width + x;
+                            visit(position, channel, dy * self.size + dx, source);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 2D convolution (strictly, cross-correlation) with `filters` square kernels, each spanning
+/// every input channel, plus a bias per filter. Its output has one channel per filter.
+pub struct Conv2D {
+    kernels: Matrix<f64>, // filters x (channels * kernel * kernel)
+    biases: Matrix<f64>,  // col vec, one per filter
+    window: Window,
+    input: Matrix<f64>,
+}
+
+impl Conv2D {
+    /// `Initializer::XavierUniform` kernels, unseeded, and biases of 0, with a stride of 1 and no
+    /// padding
+    pub fn new(input: ImageShape, filters: usize, kernel: usize) -> Self {
+        let kernels =
+            Initializer::XavierUniform.kernels(input.0, filters, kernel, &mut rand::thread_rng());
+        Self::with_kernels(input, kernels, kernel)
+    }
+
+    /// Kernels drawn with `initializer` from an RNG seeded with `seed`, and biases of 0
+    pub fn with_initializer(
+        input: ImageShape,
+        filters: usize,
+        kernel: usize,
+        initializer: &Initializer,
+        seed: u64,
+    ) -> Self {
+        let kernels =
+            initializer.kernels(input.0, filters, kernel, &mut StdRng::seed_from_u64(seed));
+        Self::with_kernels(input, kernels, kernel)
+    }
+
+    fn with_kernels(input: ImageShape, kernels: Matrix<f64>, kernel: usize) -> Self {
+        Self {
+            biases: Matrix::new_uniform(0.0, 1, kernels.h()),
+            kernels,
+            window: Window::new(input, kernel),
+            input: Matrix::default(),
+        }
+    }
+
+    pub fn stride(mut self, stride: usize) -> Self {
+        assert!(stride > 0, "stride must be positive");
+        self.window.stride = stride;
+        self
+    }
+
+    /// Zeroes added around every side of each input channel
+    pub fn padding(mut self, padding: usize) -> Self {
+        self.window.padding = padding;
+        self
+    }
+
+    pub fn output_shape(&self) -> ImageShape {
+        let (height, width) = self.window.output();
+        (self.kernels.h(), height, width)
+    }
+}
+
+impl Layer for Conv2D {
+    fn forward(&mut self, input: &Matrix<f64>) -> Matrix<f64> {
+        self.input = input.clone();
+        self.predict(input)
+    }
+
+    fn predict(&self, input: &Matrix<f64>) -> Matrix<f64> {
+        self.window.check_input(input);
+        let (filters, height, width) = self.output_shape();
+        let positions = height * width;
+        let area = self.window.size * self.window.size;
+        let mut output = Matrix::new_uniform(0.0, filters * positions, input.h());
+        for i in 0..input.h() {
+            for filter in 0..filters {
+                for position in 0..positions {
+                    output[(i, filter * positions + position)] = self.biases[(filter, 0)];
+                }
+            }
+            self.window.for_each(|position, channel, tap, source| {
+                let value = input[(i, source)];
+                for filter in 0..filters {
+                    output[(i, filter * positions + position)] +=
+                        self.kernels[(filter, channel * area + tap)] * value;
+                }
+            });
+        }
+        output
+    }
+
+    fn backward(&mut self, cost_wrt_output: &Matrix<f64>) -> (Matrix<f64>, Vec<Matrix<f64>>) {
+        let (filters, height, width) = self.output_shape();
+        let positions = height * width;
+        let area = self.window.size * self.window.size;
+        let mut kernel_grad = Matrix::new_uniform(0.0, self.kernels.w(), filters);
+        let mut bias_grad = Matrix::new_uniform(0.0, 1, filters);
+        let mut cost_wrt_input = Matrix::new_uniform(0.0, self.input.w(), self.input.h());
+        for i in 0..self.input.h() {
+            for filter in 0..filters {
+                for position in 0..positions {
+                    bias_grad[(filter, 0)] += cost_wrt_output[(i, filter * positions + position)];
+                }
+            }
+            self.window.for_each(|position, channel, tap, source| {
+                let column = channel * area + tap;
+                for filter in 0..filters {
+                    let cost_wrt_out = cost_wrt_output[(i, filter * positions + position)];
+                    kernel_grad[(filter, column)] += cost_wrt_out * self.input[(i, source)];
+                    cost_wrt_input[(i, source)] += cost_wrt_out * self.kernels[(filter, column)];
+                }
+            });
+        }
+        (cost_wrt_input, vec![kernel_grad, bias_grad])
+    }
+
+    fn params_mut(&mut self) -> Vec<&mut Matrix<f64>> {
+        vec![&mut self.kernels, &mut self.biases]
+    }
+
+    fn name(&self) -> String {
+        String::from("conv2d")
+    }
+}
+
+/// Marks where images end and plain feature rows begin, eg. between the last `Conv2D` or pool and
+/// the first `Dense`. Images are already stored as flat rows, so it only checks their width and
+/// passes them on unchanged.
+pub struct Flatten {
+    input: ImageShape,
+}
+
+impl Flatten {
+    pub fn new(input: ImageShape) -> Self {
+        Self { input }
+    }
+
+    /// Features of every output row, channels * height * width
+    pub fn features(&self) -> usize {
+        let (channels, height, width) = self.input;
+        channels * height * width
+    }
+}
+
+impl Layer for Flatten {
+    fn forward(&mut self, input: &Matrix<f64>) -> Matrix<f64> {
+        self.predict(input)
+    }
+
+    fn predict(&self, input: &Matrix<f64>) -> Matrix<f64> {
+        assert_eq!(
+            input.w(),
+            self.features(),
+            "input rows must hold {:?} images",
+            self.input
+        );
+        input.clone()
+    }
+
+    fn backward(&mut self, cost_wrt_output: &Matrix<f64>) -> (Matrix<f64>, Vec<Matrix<f64>>) {
+        (cost_wrt_output.clone(), Vec::new())
+    }
+
+    fn name(&self) -> String {
+        String::from("flatten")
+    }
+}
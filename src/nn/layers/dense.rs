@@ -0,0 +1,68 @@
+use super::Layer;
+use crate::algebra::{MatLike, Matrix};
+use crate::nn::init::Initializer;
+use rand::{rngs::StdRng, SeedableRng};
+
+/// Fully connected layer with no activation: output = input * weights^T + biases
+pub struct Dense {
+    weights: Matrix<f64>, // out_shape x in_shape
+    biases: Matrix<f64>,  // col vec
+    input: Matrix<f64>,
+}
+
+impl Dense {
+    /// `Initializer::XavierUniform` weights, unseeded, and biases of 0
+    pub fn new(in_shape: usize, out_shape: usize) -> Self {
+        let weights =
+            Initializer::XavierUniform.weights(in_shape, out_shape, &mut rand::thread_rng());
+        Self::with_weights(weights)
+    }
+
+    /// Weights drawn with `initializer` from an RNG seeded with `seed`, and biases of 0
+    pub fn with_initializer(
+        in_shape: usize,
+        out_shape: usize,
+        initializer: &Initializer,
+        seed: u64,
+    ) -> Self {
+        let weights = initializer.weights(in_shape, out_shape, &mut StdRng::seed_from_u64(seed));
+        Self::with_weights(weights)
+    }
+
+    fn with_weights(weights: Matrix<f64>) -> Self {
+        Self {
+            biases: Matrix::new_uniform(0.0, 1, weights.h()),
+            weights,
+            input: Matrix::default(),
+        }
+    }
+}
+
+impl Layer for Dense {
+    fn forward(&mut self, input: &Matrix<f64>) -> Matrix<f64> {
+        self.input = input.clone();
+        self.predict(input)
+    }
+
+    fn predict(&self, input: &Matrix<f64>) -> Matrix<f64> {
+        (input * self.weights.new_transposed()).add_to_rows(&self.biases)
+    }
+
+    fn backward(&mut self, cost_wrt_output: &Matrix<f64>) -> (Matrix<f64>, Vec<Matrix<f64>>) {
+        let weight_grad = cost_wrt_output.new_transposed() * &self.input;
+        let mut bias_grad = cost_wrt_output.sum_rows();
+        bias_grad.transpose();
+        (
+            cost_wrt_output * &self.weights,
+            vec![weight_grad, bias_grad],
+        )
+    }
+
+    fn params_mut(&mut self) -> Vec<&mut Matrix<f64>> {
+        vec![&mut self.weights, &mut self.biases]
+    }
+
+    fn name(&self) -> String {
+        String::from("dense")
+    }
+}
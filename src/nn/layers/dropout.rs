@@ -0,0 +1,192 @@
+use super::Layer;
+use crate::algebra::{MatLike, Matrix};
+use crate::nn::activations::SELU;
+use crate::nn::checkpoint::RngState;
+use crate::nn::ModelError;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+
+/// How `Dropout` disturbs its input while training.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DropoutKind {
+    /// Inverted dropout: zeroes each input with probability `rate`, and scales the rest by
+    /// 1 / (1 - rate) so nothing needs rescaling at inference.
+    Standard,
+    /// Multiplies each input by noise drawn from N(1, rate / (1 - rate)).
+    Gaussian,
+    /// Sets dropped inputs to SELU's negative saturation value, then applies the affine
+    /// transform that keeps a SELU net's zero mean and unit variance (Klambauer et al., 2017).
+    Alpha,
+}
+
+/// A dropout mask drawn for one batch: output = input * scale + shift, element-wise
+#[derive(Debug, Clone)]
+pub struct Mask {
+    pub scale: Matrix<f64>,
+    pub shift: Matrix<f64>,
+}
+
+impl Mask {
+    pub fn apply(&self, input: &Matrix<f64>) -> Matrix<f64> {
+        input.clone().mul_element_wise(self.scale.clone()) + &self.shift
+    }
+
+    /// @return cost_wrt_input
+    pub fn backward(&self, cost_wrt_output: &Matrix<f64>) -> Matrix<f64> {
+        cost_wrt_output.clone().mul_element_wise(self.scale.clone())
+    }
+}
+
+/// Randomly drops inputs while training, and passes them through untouched otherwise. Masks come
+/// from an RNG seeded on creation, so runs can be repeated, and the mask drawn by `forward` is the
+/// one `backward` uses.
+pub struct Dropout {
+    kind: DropoutKind,
+    rate: f64,
+    rng: ChaCha12Rng,
+    training: bool,
+    mask: Option<Mask>,
+}
+
+impl Dropout {
+    fn with_kind(kind: DropoutKind, rate: f64, seed: u64) -> Self {
+        assert!(
+            (0.0..1.0).contains(&rate),
+            "rate must be at least 0 and less than 1"
+        );
+        Self {
+            kind,
+            rate,
+            rng: ChaCha12Rng::seed_from_u64(seed),
+            training: true,
+            mask: None,
+        }
+    }
+
+    /// Drops each input with probability `rate`.
+    pub fn new(rate: f64, seed: u64) -> Self {
+        Self::with_kind(DropoutKind::Standard, rate, seed)
+    }
+
+    pub fn gaussian(rate: f64, seed: u64) -> Self {
+        Self::with_kind(DropoutKind::Gaussian, rate, seed)
+    }
+
+    /// For nets using `SELU`
+    pub fn alpha(rate: f64, seed: u64) -> Self {
+        Self::with_kind(DropoutKind::Alpha, rate, seed)
+    }
+
+    pub fn kind(&self) -> DropoutKind {
+        self.kind
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    pub fn is_training(&self) -> bool {
+        self.training
+    }
+
+    /// Where the mask RNG is, so a checkpoint can pick up with the same masks
+    pub fn rng_state(&self) -> RngState {
+        RngState::of(&self.rng)
+    }
+
+    pub fn set_rng_state(&mut self, state: RngState) {
+        self.rng = state.to_rng();
+    }
+
+    /// Draws the next mask, for a batch of `h` cases with `w` features.
+    pub fn sample_mask(&mut self, w: usize, h: usize) -> Mask {
+        let keep = 1.0 - self.rate;
+        let mut kept = || {
+            let rate = self.rate;
+            Matrix::new(
+                (0..w * h)
+                    .map(|_| (self.rng.gen::<f64>() >= rate) as u8 as f64)
+                    .collect(),
+                w,
+                h,
+            )
+        };
+        match self.kind {
+            DropoutKind::Standard => Mask {
+                scale: kept() * (1.0 / keep),
+                shift: Matrix::new_uniform(0.0, w, h),
+            },
+            DropoutKind::Gaussian => Mask {
+                scale: Matrix::random_normal_with(
+                    1.0,
+                    (self.rate / keep).sqrt(),
+                    w,
+                    h,
+                    &mut self.rng,
+                ),
+                shift: Matrix::new_uniform(0.0, w, h),
+            },
+            DropoutKind::Alpha => {
+                let saturation = -SELU::SCALE * SELU::ALPHA;
+                let a = (keep * (1.0 + self.rate * saturation * saturation)).powf(-0.5);
+                let b = -a * saturation * self.rate;
+                let kept = kept();
+                Mask {
+                    scale: kept.apply(|k| a * k),
+                    shift: kept.apply(|k| a * saturation * (1.0 - k) + b),
+                }
+            }
+        }
+    }
+}
+
+impl Layer for Dropout {
+    fn forward(&mut self, input: &Matrix<f64>) -> Matrix<f64> {
+        if self.training && self.rate > 0.0 {
+            let mask = self.sample_mask(input.w(), input.h());
+            let output = mask.apply(input);
+            self.mask = Some(mask);
+            output
+        } else {
+            self.mask = None;
+            input.clone()
+        }
+    }
+
+    /// Always the inference pass, as drawing a mask would need `&mut self`.
+    fn predict(&self, input: &Matrix<f64>) -> Matrix<f64> {
+        input.clone()
+    }
+
+    fn backward(&mut self, cost_wrt_output: &Matrix<f64>) -> (Matrix<f64>, Vec<Matrix<f64>>) {
+        let cost_wrt_input = match &self.mask {
+            Some(mask) => mask.backward(cost_wrt_output),
+            None => cost_wrt_output.clone(),
+        };
+        (cost_wrt_input, Vec::new())
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+    }
+
+    fn state(&self) -> Vec<u8> {
+        self.rng_state().to_bytes().to_vec()
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> Result<(), ModelError> {
+        let bytes = state
+            .try_into()
+            .map_err(|_| ModelError::format("corrupt dropout state"))?;
+        self.set_rng_state(RngState::from_bytes(bytes));
+        Ok(())
+    }
+
+    fn name(&self) -> String {
+        String::from(match self.kind {
+            DropoutKind::Standard => "dropout",
+            DropoutKind::Gaussian => "gaussian_dropout",
+            DropoutKind::Alpha => "alpha_dropout",
+        })
+    }
+}
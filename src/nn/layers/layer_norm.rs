@@ -0,0 +1,196 @@
+use super::Layer;
+use crate::algebra::{MatLike, Matrix};
+
+// multiplies every column j of m by gamma_j
+fn scale_features(m: &Matrix<f64>, gamma: &Matrix<f64>) -> Matrix<f64> {
+    let mut result = m.clone();
+    for i in 0..result.h() {
+        for j in 0..result.w() {
+            result[(i, j)] *= gamma[(j, 0)];
+        }
+    }
+    result
+}
+
+// sum of a * b over the elements of row i
+fn row_dot(a: &Matrix<f64>, b: &Matrix<f64>, i: usize) -> f64 {
+    (0..a.w()).map(|j| a[(i, j)] * b[(i, j)]).sum()
+}
+
+// sum over rows of cost_wrt_output * normalised, as a col vec
+fn gamma_grad(cost_wrt_output: &Matrix<f64>, normalised: &Matrix<f64>) -> Matrix<f64> {
+    let mut grad = cost_wrt_output
+        .clone()
+        .mul_element_wise(normalised.clone())
+        .sum_rows();
+    grad.transpose();
+    grad
+}
+
+/// Layer normalization (Ba et al., 2016): normalises the features of every case to zero mean
+/// and unit variance, then scales by gamma and shifts by beta, both learned. Unlike `BatchNorm`
+/// nothing depends on the rest of the batch, so it acts the same in training and inference.
+pub struct LayerNorm {
+    gamma: Matrix<f64>, // col vec, one per feature
+    beta: Matrix<f64>,  // col vec
+    pub epsilon: f64,
+    // from the last forward pass
+    normalised: Matrix<f64>,
+    inv_std: Vec<f64>, // one per case
+}
+
+impl LayerNorm {
+    pub fn new(features: usize) -> Self {
+        Self {
+            gamma: Matrix::new_uniform(1.0, 1, features),
+            beta: Matrix::new_uniform(0.0, 1, features),
+            epsilon: 1e-5,
+            normalised: Matrix::default(),
+            inv_std: Vec::new(),
+        }
+    }
+
+    // (normalised input, 1 / std of every row)
+    fn normalise(&self, input: &Matrix<f64>) -> (Matrix<f64>, Vec<f64>) {
+        let features = input.w() as f64;
+        let mut normalised = input.clone();
+        let mut inv_stds = Vec::with_capacity(input.h());
+        for i in 0..input.h() {
+            let mean = (0..input.w()).map(|j| input[(i, j)]).sum::<f64>() / features;
+            let variance = (0..input.w())
+                .map(|j| (input[(i, j)] - mean) * (input[(i, j)] - mean))
+                .sum::<f64>()
+                / features;
+            let inv_std = 1.0 / (variance + self.epsilon).sqrt();
+            for j in 0..input.w() {
+                normalised[(i, j)] = (input[(i, j)] - mean) * inv_std;
+            }
+            inv_stds.push(inv_std);
+        }
+        (normalised, inv_stds)
+    }
+
+    fn affine(&self, normalised: &Matrix<f64>) -> Matrix<f64> {
+        let mut output = normalised.clone();
+        for i in 0..output.h() {
+            for j in 0..output.w() {
+                output[(i, j)] = self.gamma[(j, 0)] * normalised[(i, j)] + self.beta[(j, 0)];
+            }
+        }
+        output
+    }
+}
+
+impl Layer for LayerNorm {
+    fn forward(&mut self, input: &Matrix<f64>) -> Matrix<f64> {
+        (self.normalised, self.inv_std) = self.normalise(input);
+        self.affine(&self.normalised)
+    }
+
+    fn predict(&self, input: &Matrix<f64>) -> Matrix<f64> {
+        self.affine(&self.normalise(input).0)
+    }
+
+    fn backward(&mut self, cost_wrt_output: &Matrix<f64>) -> (Matrix<f64>, Vec<Matrix<f64>>) {
+        let mut beta_grad = cost_wrt_output.sum_rows();
+        beta_grad.transpose();
+        let gamma_grad = gamma_grad(cost_wrt_output, &self.normalised);
+
+        let d_normalised = scale_features(cost_wrt_output, &self.gamma);
+        let features = cost_wrt_output.w() as f64;
+        let mut cost_wrt_input = d_normalised.clone();
+        for i in 0..cost_wrt_input.h() {
+            // every feature also moves the case's mean and variance
+            let sum = (0..d_normalised.w())
+                .map(|j| d_normalised[(i, j)])
+                .sum::<f64>();
+            let dot = row_dot(&d_normalised, &self.normalised, i);
+            for j in 0..cost_wrt_input.w() {
+                cost_wrt_input[(i, j)] = self.inv_std[i]
+                    * (d_normalised[(i, j)]
+                        - sum / features
+                        - self.normalised[(i, j)] * dot / features);
+            }
+        }
+        (cost_wrt_input, vec![gamma_grad, beta_grad])
+    }
+
+    fn params_mut(&mut self) -> Vec<&mut Matrix<f64>> {
+        vec![&mut self.gamma, &mut self.beta]
+    }
+
+    fn name(&self) -> String {
+        String::from("layer_norm")
+    }
+}
+
+/// Root mean square normalization (Zhang & Sennrich, 2019): `LayerNorm` without centring or
+/// beta. Every case is divided by the root mean square of its features, then scaled by gamma.
+pub struct RMSNorm {
+    gamma: Matrix<f64>, // col vec, one per feature
+    pub epsilon: f64,
+    // from the last forward pass
+    normalised: Matrix<f64>,
+    inv_rms: Vec<f64>, // one per case
+}
+
+impl RMSNorm {
+    pub fn new(features: usize) -> Self {
+        Self {
+            gamma: Matrix::new_uniform(1.0, 1, features),
+            epsilon: 1e-8,
+            normalised: Matrix::default(),
+            inv_rms: Vec::new(),
+        }
+    }
+
+    // (normalised input, 1 / rms of every row)
+    fn normalise(&self, input: &Matrix<f64>) -> (Matrix<f64>, Vec<f64>) {
+        let features = input.w() as f64;
+        let mut normalised = input.clone();
+        let mut inv_rmss = Vec::with_capacity(input.h());
+        for i in 0..input.h() {
+            let mean_square = row_dot(input, input, i) / features;
+            let inv_rms = 1.0 / (mean_square + self.epsilon).sqrt();
+            for j in 0..input.w() {
+                normalised[(i, j)] = input[(i, j)] * inv_rms;
+            }
+            inv_rmss.push(inv_rms);
+        }
+        (normalised, inv_rmss)
+    }
+}
+
+impl Layer for RMSNorm {
+    fn forward(&mut self, input: &Matrix<f64>) -> Matrix<f64> {
+        (self.normalised, self.inv_rms) = self.normalise(input);
+        scale_features(&self.normalised, &self.gamma)
+    }
+
+    fn predict(&self, input: &Matrix<f64>) -> Matrix<f64> {
+        scale_features(&self.normalise(input).0, &self.gamma)
+    }
+
+    fn backward(&mut self, cost_wrt_output: &Matrix<f64>) -> (Matrix<f64>, Vec<Matrix<f64>>) {
+        let gamma_grad = gamma_grad(cost_wrt_output, &self.normalised);
+        let d_normalised = scale_features(cost_wrt_output, &self.gamma);
+        let features = cost_wrt_output.w() as f64;
+        let mut cost_wrt_input = d_normalised.clone();
+        for i in 0..cost_wrt_input.h() {
+            let dot = row_dot(&d_normalised, &self.normalised, i);
+            for j in 0..cost_wrt_input.w() {
+                cost_wrt_input[(i, j)] = self.inv_rms[i]
+                    * (d_normalised[(i, j)] - self.normalised[(i, j)] * dot / features);
+            }
+        }
+        (cost_wrt_input, vec![gamma_grad])
+    }
+
+    fn params_mut(&mut self) -> Vec<&mut Matrix<f64>> {
+        vec![&mut self.gamma]
+    }
+
+    fn name(&self) -> String {
+        String::from("rms_norm")
+    }
+}
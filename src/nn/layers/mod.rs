@@ -0,0 +1,79 @@
+mod activation;
+mod batch_norm;
+mod conv;
+mod dense;
+mod dropout;
+mod layer_norm;
+mod parametric;
+mod pool;
+mod recurrent;
+mod softmax;
+pub use crate::nn::layers::activation::ActivationLayer;
+pub use crate::nn::layers::batch_norm::BatchNorm;
+pub use crate::nn::layers::conv::{Conv2D, Flatten, ImageShape};
+pub use crate::nn::layers::dense::Dense;
+pub use crate::nn::layers::dropout::{Dropout, DropoutKind, Mask};
+pub use crate::nn::layers::layer_norm::{LayerNorm, RMSNorm};
+pub use crate::nn::layers::parametric::{LearnableSwish, Maxout, PReLU};
+pub use crate::nn::layers::pool::{AvgPool2D, MaxPool2D};
+pub use crate::nn::layers::recurrent::{
+    Cell, GRUCache, GRUCell, LSTMCache, LSTMCell, OutputMode, RNNCache, RNNCell, Recurrent,
+};
+pub use crate::nn::layers::softmax::{log_softmax, softmax, LogSoftmax, Softmax};
+
+use crate::algebra::Matrix;
+use crate::nn::ModelError;
+
+/// What the numbers a layer outputs are, so `Sequential` can tell a `Softmax` or `LogSoftmax`
+/// output layer apart from any other without going by its name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputKind {
+    Values,
+    Probabilities,
+    LogProbabilities,
+}
+
+/// One step of a `Sequential` model. Every matrix passed in or out holds one case per row.
+pub trait Layer {
+    /// Caches whatever `backward` will need.
+    fn forward(&mut self, input: &Matrix<f64>) -> Matrix<f64>;
+
+    /// Same output as `forward`, without touching any state.
+    fn predict(&self, input: &Matrix<f64>) -> Matrix<f64>;
+
+    // a_wrt_b = partial derivative of a with respect to b
+    /// @param cost_wrt_output for the output of the last call to `forward`
+    /// @return (cost_wrt_input, gradient of every param, in `params_mut` order)
+    fn backward(&mut self, cost_wrt_output: &Matrix<f64>) -> (Matrix<f64>, Vec<Matrix<f64>>);
+
+    fn params_mut(&mut self) -> Vec<&mut Matrix<f64>> {
+        Vec::new()
+    }
+
+    /// Switches between training and inference behaviour, for layers like `Dropout` that have
+    /// both. Layers start out training.
+    fn set_training(&mut self, _training: bool) {}
+
+    /// What training changes besides the params, see `Trainable::state`. Empty by default.
+    fn state(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> Result<(), ModelError> {
+        if state.is_empty() {
+            Ok(())
+        } else {
+            Err(ModelError::format(format!(
+                "{} has no state to load",
+                self.name()
+            )))
+        }
+    }
+
+    /// `OutputKind::Values` unless overridden
+    fn output_kind(&self) -> OutputKind {
+        OutputKind::Values
+    }
+
+    fn name(&self) -> String;
+}
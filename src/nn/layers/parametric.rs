@@ -0,0 +1,227 @@
+use super::{Dense, Layer};
+use crate::algebra::{MatLike, Matrix};
+use crate::nn::activations::{Activation, Sigmoid};
+use crate::nn::init::Initializer;
+
+/// `LeakyReLU` with a learned slope below 0 for every feature (He et al., 2015):
+/// output = input above 0, slope * input below
+pub struct PReLU {
+    slopes: Matrix<f64>, // col vec, one per feature
+    input: Matrix<f64>,
+}
+
+impl PReLU {
+    /// Every slope starts at 0.25, as in the paper.
+    pub fn new(features: usize) -> Self {
+        Self {
+            slopes: Matrix::new_uniform(0.25, 1, features),
+            input: Matrix::default(),
+        }
+    }
+
+    pub fn slopes(&self) -> &Matrix<f64> {
+        &self.slopes
+    }
+}
+
+impl Layer for PReLU {
+    fn forward(&mut self, input: &Matrix<f64>) -> Matrix<f64> {
+        self.input = input.clone();
+        self.predict(input)
+    }
+
+    fn predict(&self, input: &Matrix<f64>) -> Matrix<f64> {
+        let mut output = input.clone();
+        for i in 0..input.h() {
+            for j in 0..input.w() {
+                if input[(i, j)] <= 0.0 {
+                    output[(i, j)] *= self.slopes[(j, 0)];
+                }
+            }
+        }
+        output
+    }
+
+    fn backward(&mut self, cost_wrt_output: &Matrix<f64>) -> (Matrix<f64>, Vec<Matrix<f64>>) {
+        let mut cost_wrt_input = cost_wrt_output.clone();
+        let mut slope_grad = Matrix::new_uniform(0.0, 1, self.slopes.h());
+        for i in 0..self.input.h() {
+            for j in 0..self.input.w() {
+                let x = self.input[(i, j)];
+                if x <= 0.0 {
+                    cost_wrt_input[(i, j)] *= self.slopes[(j, 0)];
+                    slope_grad[(j, 0)] += cost_wrt_output[(i, j)] * x;
+                }
+            }
+        }
+        (cost_wrt_input, vec![slope_grad])
+    }
+
+    fn params_mut(&mut self) -> Vec<&mut Matrix<f64>> {
+        vec![&mut self.slopes]
+    }
+
+    fn name(&self) -> String {
+        String::from("prelu")
+    }
+}
+
+/// Swish with a learned beta for every feature (Ramachandran et al., 2017):
+/// output = input * sigmoid(beta * input)
+pub struct LearnableSwish {
+    betas: Matrix<f64>, // col vec, one per feature
+    input: Matrix<f64>,
+}
+
+impl LearnableSwish {
+    /// Every beta starts at 1, where this is `SiLU`.
+    pub fn new(features: usize) -> Self {
+        Self {
+            betas: Matrix::new_uniform(1.0, 1, features),
+            input: Matrix::default(),
+        }
+    }
+
+    pub fn betas(&self) -> &Matrix<f64> {
+        &self.betas
+    }
+}
+
+impl Layer for LearnableSwish {
+    fn forward(&mut self, input: &Matrix<f64>) -> Matrix<f64> {
+        self.input = input.clone();
+        self.predict(input)
+    }
+
+    fn predict(&self, input: &Matrix<f64>) -> Matrix<f64> {
+        let mut output = input.clone();
+        for i in 0..input.h() {
+            for j in 0..input.w() {
+                output[(i, j)] *= Sigmoid::calc(self.betas[(j, 0)] * input[(i, j)]);
+            }
+        }
+        output
+    }
+
+    fn backward(&mut self, cost_wrt_output: &Matrix<f64>) -> (Matrix<f64>, Vec<Matrix<f64>>) {
+        let mut cost_wrt_input = cost_wrt_output.clone();
+        let mut beta_grad = Matrix::new_uniform(0.0, 1, self.betas.h());
+        for i in 0..self.input.h() {
+            for j in 0..self.input.w() {
+                let (x, beta) = (self.input[(i, j)], self.betas[(j, 0)]);
+                let s = Sigmoid::calc(beta * x);
+                let s_prime = s * (1.0 - s);
+                cost_wrt_input[(i, j)] *= s + beta * x * s_prime;
+                beta_grad[(j, 0)] += cost_wrt_output[(i, j)] * x * x * s_prime;
+            }
+        }
+        (cost_wrt_input, vec![beta_grad])
+    }
+
+    fn params_mut(&mut self) -> Vec<&mut Matrix<f64>> {
+        vec![&mut self.betas]
+    }
+
+    fn name(&self) -> String {
+        String::from("learnable_swish")
+    }
+}
+
+/// Maxout (Goodfellow et al., 2013): every output is the max of `pieces` separate affine
+/// functions of the input, so the layer learns its own piecewise linear activation.
+pub struct Maxout {
+    // out_shape * pieces outputs, where output j's pieces are j * pieces..(j + 1) * pieces
+    dense: Dense,
+    pieces: usize,
+    // for every row and output, which dense output won
+    winners: Vec<usize>,
+    dense_shape: (usize, usize),
+}
+
+impl Maxout {
+    pub fn new(in_shape: usize, out_shape: usize, pieces: usize) -> Self {
+        assert!(pieces > 0, "pieces must be positive");
+        Self::with_dense(Dense::new(in_shape, out_shape * pieces), pieces)
+    }
+
+    /// Every piece's weights drawn with `initializer` from an RNG seeded with `seed`, and biases
+    /// of 0
+    pub fn with_initializer(
+        in_shape: usize,
+        out_shape: usize,
+        pieces: usize,
+        initializer: &Initializer,
+        seed: u64,
+    ) -> Self {
+        assert!(pieces > 0, "pieces must be positive");
+        let dense = Dense::with_initializer(in_shape, out_shape * pieces, initializer, seed);
+        Self::with_dense(dense, pieces)
+    }
+
+    fn with_dense(dense: Dense, pieces: usize) -> Self {
+        Self {
+            dense,
+            pieces,
+            winners: Vec::new(),
+            dense_shape: (0, 0),
+        }
+    }
+
+    // (output, index into dense output of the winning piece of each output)
+    fn max_pieces(&self, pieces: &Matrix<f64>) -> (Matrix<f64>, Vec<usize>) {
+        let out_shape = pieces.w() / self.pieces;
+        let mut output = Matrix::new_uniform(0.0, out_shape, pieces.h());
+        let mut winners = Vec::with_capacity(out_shape * pieces.h());
+        for i in 0..pieces.h() {
+            for j in 0..out_shape {
+                let winner = (j * self.pieces..(j + 1) * self.pieces)
+                    .reduce(|best, k| {
+                        if pieces[(i, k)] > pieces[(i, best)] {
+                            k
+                        } else {
+                            best
+                        }
+                    })
+                    .unwrap();
+                output[(i, j)] = pieces[(i, winner)];
+                winners.push(winner);
+            }
+        }
+        (output, winners)
+    }
+}
+
+impl Layer for Maxout {
+    fn forward(&mut self, input: &Matrix<f64>) -> Matrix<f64> {
+        let pieces = self.dense.forward(input);
+        let (output, winners) = self.max_pieces(&pieces);
+        self.winners = winners;
+        self.dense_shape = (pieces.w(), pieces.h());
+        output
+    }
+
+    fn predict(&self, input: &Matrix<f64>) -> Matrix<f64> {
+        self.max_pieces(&self.dense.predict(input)).0
+    }
+
+    fn backward(&mut self, cost_wrt_output: &Matrix<f64>) -> (Matrix<f64>, Vec<Matrix<f64>>) {
+        // only the winning piece of every output gets any gradient
+        let (w, h) = self.dense_shape;
+        let mut cost_wrt_pieces = Matrix::new_uniform(0.0, w, h);
+        for i in 0..cost_wrt_output.h() {
+            for j in 0..cost_wrt_output.w() {
+                let winner = self.winners[i * cost_wrt_output.w() + j];
+                cost_wrt_pieces[(i, winner)] = cost_wrt_output[(i, j)];
+            }
+        }
+        self.dense.backward(&cost_wrt_pieces)
+    }
+
+    fn params_mut(&mut self) -> Vec<&mut Matrix<f64>> {
+        self.dense.params_mut()
+    }
+
+    fn name(&self) -> String {
+        String::from("maxout")
+    }
+}
@@ -0,0 +1,184 @@
+use super::conv::{ImageShape, Window};
+use super::Layer;
+use crate::algebra::{MatLike, Matrix};
+
+fn pool_window(input: ImageShape, size: usize) -> Window {
+    let mut window = Window::new(input, size);
+    window.stride = size;
+    window
+}
+
+fn set_stride(window: &mut Window, stride: usize) {
+    assert!(stride > 0, "stride must be positive");
+    window.stride = stride;
+}
+
+fn set_padding(window: &mut Window, padding: usize) {
+    // so no window is all padding
+    assert!(
+        2 * padding <= window.size,
+        "padding must be at most half the pool size"
+    );
+    window.padding = padding;
+}
+
+fn output_shape(window: &Window) -> ImageShape {
+    let (height, width) = window.output();
+    (window.input.0, height, width)
+}
+
+/// Takes the largest value of every window, in every channel separately. Windows don't overlap
+/// unless `stride` says otherwise, and padding is never the largest value.
+pub struct MaxPool2D {
+    window: Window,
+    // input column each output came from, for every case of the last forward pass
+    sources: Vec<Vec<usize>>,
+}
+
+impl MaxPool2D {
+    /// `size` x `size` windows, with a stride of `size` and no padding
+    pub fn new(input: ImageShape, size: usize) -> Self {
+        Self {
+            window: pool_window(input, size),
+            sources: Vec::new(),
+        }
+    }
+
+    pub fn stride(mut self, stride: usize) -> Self {
+        set_stride(&mut self.window, stride);
+        self
+    }
+
+    /// Cells added around every side of each input channel, at most half the pool size
+    pub fn padding(mut self, padding: usize) -> Self {
+        set_padding(&mut self.window, padding);
+        self
+    }
+
+    pub fn output_shape(&self) -> ImageShape {
+        output_shape(&self.window)
+    }
+
+    // (output, input column of every output) of one case
+    fn pool(&self, input: &Matrix<f64>, i: usize) -> (Vec<f64>, Vec<usize>) {
+        let (channels, height, width) = self.output_shape();
+        let positions = height * width;
+        let mut output = vec![f64::NEG_INFINITY; channels * positions];
+        let mut sources = vec![0; channels * positions];
+        self.window.for_each(|position, channel, _, source| {
+            let out = channel * positions + position;
+            if input[(i, source)] > output[out] {
+                output[out] = input[(i, source)];
+                sources[out] = source;
+            }
+        });
+        (output, sources)
+    }
+}
+
+impl Layer for MaxPool2D {
+    fn forward(&mut self, input: &Matrix<f64>) -> Matrix<f64> {
+        self.window.check_input(input);
+        let (outputs, sources): (Vec<_>, Vec<_>) =
+            (0..input.h()).map(|i| self.pool(input, i)).unzip();
+        self.sources = sources;
+        let w = outputs.first().map_or(0, Vec::len);
+        Matrix::new(outputs.into_iter().flatten().collect(), w, input.h())
+    }
+
+    fn predict(&self, input: &Matrix<f64>) -> Matrix<f64> {
+        self.window.check_input(input);
+        let (channels, height, width) = self.output_shape();
+        let data = (0..input.h()).flat_map(|i| self.pool(input, i).0).collect();
+        Matrix::new(data, channels * height * width, input.h())
+    }
+
+    fn backward(&mut self, cost_wrt_output: &Matrix<f64>) -> (Matrix<f64>, Vec<Matrix<f64>>) {
+        let mut cost_wrt_input =
+            Matrix::new_uniform(0.0, self.window.input_len(), cost_wrt_output.h());
+        for (i, sources) in self.sources.iter().enumerate() {
+            // overlapping windows can share a winner
+            for (out, &source) in sources.iter().enumerate() {
+                cost_wrt_input[(i, source)] += cost_wrt_output[(i, out)];
+            }
+        }
+        (cost_wrt_input, Vec::new())
+    }
+
+    fn name(&self) -> String {
+        String::from("max_pool2d")
+    }
+}
+
+/// Takes the mean of every window, in every channel separately. Padding counts as zeroes, so
+/// every window is divided by its full size.
+pub struct AvgPool2D {
+    window: Window,
+}
+
+impl AvgPool2D {
+    /// `size` x `size` windows, with a stride of `size` and no padding
+    pub fn new(input: ImageShape, size: usize) -> Self {
+        Self {
+            window: pool_window(input, size),
+        }
+    }
+
+    pub fn stride(mut self, stride: usize) -> Self {
+        set_stride(&mut self.window, stride);
+        self
+    }
+
+    /// Zeroes added around every side of each input channel, at most half the pool size
+    pub fn padding(mut self, padding: usize) -> Self {
+        set_padding(&mut self.window, padding);
+        self
+    }
+
+    pub fn output_shape(&self) -> ImageShape {
+        output_shape(&self.window)
+    }
+
+    fn area(&self) -> f64 {
+        (self.window.size * self.window.size) as f64
+    }
+}
+
+impl Layer for AvgPool2D {
+    fn forward(&mut self, input: &Matrix<f64>) -> Matrix<f64> {
+        self.predict(input)
+    }
+
+    fn predict(&self, input: &Matrix<f64>) -> Matrix<f64> {
+        self.window.check_input(input);
+        let (channels, height, width) = self.output_shape();
+        let positions = height * width;
+        let area = self.area();
+        let mut output = Matrix::new_uniform(0.0, channels * positions, input.h());
+        for i in 0..input.h() {
+            self.window.for_each(|position, channel, _, source| {
+                output[(i, channel * positions + position)] += input[(i, source)] / area;
+            });
+        }
+        output
+    }
+
+    fn backward(&mut self, cost_wrt_output: &Matrix<f64>) -> (Matrix<f64>, Vec<Matrix<f64>>) {
+        let (_, height, width) = self.output_shape();
+        let positions = height * width;
+        let area = self.area();
+        let cases = cost_wrt_output.h();
+        let mut cost_wrt_input = Matrix::new_uniform(0.0, self.window.input_len(), cases);
+        for i in 0..cases {
+            self.window.for_each(|position, channel, _, source| {
+                cost_wrt_input[(i, source)] +=
+                    cost_wrt_output[(i, channel * positions + position)] / area;
+            });
+        }
+        (cost_wrt_input, Vec::new())
+    }
+
+    fn name(&self) -> String {
+        String::from("avg_pool2d")
+    }
+}
@@ -0,0 +1,667 @@
+use super::Layer;
+use crate::algebra::{MatLike, Matrix};
+use crate::nn::activations::{Activation, Sigmoid};
+use crate::nn::init::Initializer;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// One step of a recurrent layer: takes a batch of inputs and the state the last step left, one
+/// case per row, and gives the next state. The first `hidden` columns of a state are the cell's
+/// output.
+pub trait Cell {
+    /// What `step_back` needs from `step`
+    type Cache;
+
+    fn features(&self) -> usize;
+
+    fn hidden(&self) -> usize;
+
+    /// Columns of the state, `hidden` unless the cell carries more, like `LSTMCell`
+    fn state_size(&self) -> usize {
+        self.hidden()
+    }
+
+    fn step(&self, input: &Matrix<f64>, state: &Matrix<f64>) -> (Matrix<f64>, Self::Cache);
+
+    /// @param cost_wrt_state for the state `step` gave
+    /// @param grads the gradient of every param, in `params_mut` order, to add this step's to
+    /// @return (cost_wrt_input, cost_wrt_state for the previous state)
+    fn step_back(
+        &self,
+        cache: &Self::Cache,
+        cost_wrt_state: &Matrix<f64>,
+        grads: &mut [Matrix<f64>],
+    ) -> (Matrix<f64>, Matrix<f64>);
+
+    fn params_mut(&mut self) -> Vec<&mut Matrix<f64>>;
+
+    fn name(&self) -> String;
+}
+
+// the weights of every gate stacked: (gates * hidden x features, gates * hidden x hidden), with
+// inputs from `XavierUniform` and the recurrent weights orthogonal, as they're used every step
+fn gate_weights<R: Rng>(
+    features: usize,
+    hidden: usize,
+    gates: usize,
+    rng: &mut R,
+) -> (Matrix<f64>, Matrix<f64>) {
+    (
+        Initializer::XavierUniform.weights(features, gates * hidden, rng),
+        Initializer::Orthogonal { gain: 1.0 }.weights(hidden, gates * hidden, rng),
+    )
+}
+
+// columns start..start + width of m
+fn columns(m: &Matrix<f64>, start: usize, width: usize) -> Matrix<f64> {
+    let mut result = Matrix::new_uniform(0.0, width, m.h());
+    for i in 0..m.h() {
+        for j in 0..width {
+            result[(i, j)] = m[(i, start + j)];
+        }
+    }
+    result
+}
+
+// input * weights^T, without the transposed copy
+fn times_transposed(input: &Matrix<f64>, weights: &Matrix<f64>) -> Matrix<f64> {
+    let mut output = Matrix::new_uniform(0.0, weights.h(), input.h());
+    for i in 0..input.h() {
+        for j in 0..weights.h() {
+            output[(i, j)] = (0..input.w())
+                .map(|k| input[(i, k)] * weights[(j, k)])
+                .sum();
+        }
+    }
+    output
+}
+
+// adds cost_wrt_z^T * input to grad, and the column sums of cost_wrt_z to bias_grad
+fn add_grads(
+    grad: &mut Matrix<f64>,
+    cost_wrt_z: &Matrix<f64>,
+    input: &Matrix<f64>,
+    bias_grad: Option<&mut Matrix<f64>>,
+) {
+    for i in 0..cost_wrt_z.h() {
+        for j in 0..cost_wrt_z.w() {
+            let cost_wrt_zj = cost_wrt_z[(i, j)];
+            for k in 0..input.w() {
+                grad[(j, k)] += cost_wrt_zj * input[(i, k)];
+            }
+        }
+    }
+    if let Some(bias_grad) = bias_grad {
+        for (j, sum) in cost_wrt_z.sum_rows().iter().enumerate() {
+            bias_grad[(j, 0)] += sum;
+        }
+    }
+}
+
+/// Elman RNN: state' = tanh(input * w_x^T + state * w_h^T + b)
+pub struct RNNCell {
+    w_x: Matrix<f64>, // hidden x features
+    w_h: Matrix<f64>, // hidden x hidden
+    b: Matrix<f64>,   // col vec
+}
+
+/// (input, previous state, state)
+pub struct RNNCache(Matrix<f64>, Matrix<f64>, Matrix<f64>);
+
+impl RNNCell {
+    /// Unseeded weights, and biases of 0
+    pub fn new(features: usize, hidden: usize) -> Self {
+        Self::with_rng(features, hidden, &mut rand::thread_rng())
+    }
+
+    pub fn with_seed(features: usize, hidden: usize, seed: u64) -> Self {
+        Self::with_rng(features, hidden, &mut StdRng::seed_from_u64(seed))
+    }
+
+    fn with_rng<R: Rng>(features: usize, hidden: usize, rng: &mut R) -> Self {
+        let (w_x, w_h) = gate_weights(features, hidden, 1, rng);
+        Self {
+            w_x,
+            w_h,
+            b: Matrix::new_uniform(0.0, 1, hidden),
+        }
+    }
+}
+
+impl Cell for RNNCell {
+    type Cache = RNNCache;
+
+    fn features(&self) -> usize {
+        self.w_x.w()
+    }
+
+    fn hidden(&self) -> usize {
+        self.w_h.w()
+    }
+
+    fn step(&self, input: &Matrix<f64>, state: &Matrix<f64>) -> (Matrix<f64>, RNNCache) {
+        let z = (times_transposed(input, &self.w_x) + times_transposed(state, &self.w_h))
+            .add_to_rows(&self.b);
+        let next = z.apply(f64::tanh);
+        (next.clone(), RNNCache(input.clone(), state.clone(), next))
+    }
+
+    fn step_back(
+        &self,
+        cache: &RNNCache,
+        cost_wrt_state: &Matrix<f64>,
+        grads: &mut [Matrix<f64>],
+    ) -> (Matrix<f64>, Matrix<f64>) {
+        let RNNCache(input, previous, state) = cache;
+        let cost_wrt_z = cost_wrt_state
+            .clone()
+            .mul_element_wise(state.apply(|h| 1.0 - h * h));
+        let [w_x_grad, w_h_grad, b_grad] = grads else {
+            panic!("an RNN cell has 3 params");
+        };
+        add_grads(w_x_grad, &cost_wrt_z, input, Some(b_grad));
+        add_grads(w_h_grad, &cost_wrt_z, previous, None);
+        (&cost_wrt_z * &self.w_x, &cost_wrt_z * &self.w_h)
+    }
+
+    fn params_mut(&mut self) -> Vec<&mut Matrix<f64>> {
+        vec![&mut self.w_x, &mut self.w_h, &mut self.b]
+    }
+
+    fn name(&self) -> String {
+        String::from("rnn")
+    }
+}
+
+/// Long short-term memory (Hochreiter & Schmidhuber, 1997). Its state is the hidden state then
+/// the cell state, side by side. Gates are stacked input, forget, candidate, output, and the
+/// forget gate's biases start at 1 so the cell state is kept until learned otherwise.
+pub struct LSTMCell {
+    w_x: Matrix<f64>, // 4 * hidden x features
+    w_h: Matrix<f64>, // 4 * hidden x hidden
+    b: Matrix<f64>,   // col vec
+}
+
+/// The input, previous state, gate activations and tanh of the new cell state of one step
+pub struct LSTMCache {
+    input: Matrix<f64>,
+    previous: Matrix<f64>,
+    gates: Matrix<f64>,
+    tanh_c: Matrix<f64>,
+}
+
+impl LSTMCell {
+    /// Unseeded weights, and biases of 0 (1 for the forget gate)
+    pub fn new(features: usize, hidden: usize) -> Self {
+        Self::with_rng(features, hidden, &mut rand::thread_rng())
+    }
+
+    pub fn with_seed(features: usize, hidden: usize, seed: u64) -> Self {
+        Self::with_rng(features, hidden, &mut StdRng::seed_from_u64(seed))
+    }
+
+    fn with_rng<R: Rng>(features: usize, hidden: usize, rng: &mut R) -> Self {
+        let (w_x, w_h) = gate_weights(features, hidden, 4, rng);
+        let mut b = Matrix::new_uniform(0.0, 1, 4 * hidden);
+        for j in hidden..2 * hidden {
+            b[(j, 0)] = 1.0;
+        }
+        Self { w_x, w_h, b }
+    }
+}
+
+impl Cell for LSTMCell {
+    type Cache = LSTMCache;
+
+    fn features(&self) -> usize {
+        self.w_x.w()
+    }
+
+    fn hidden(&self) -> usize {
+        self.w_h.w()
+    }
+
+    fn state_size(&self) -> usize {
+        2 * self.hidden()
+    }
+
+    fn step(&self, input: &Matrix<f64>, state: &Matrix<f64>) -> (Matrix<f64>, LSTMCache) {
+        let hidden = self.hidden();
+        let h = columns(state, 0, hidden);
+        let mut gates = (times_transposed(input, &self.w_x) + times_transposed(&h, &self.w_h))
+            .add_to_rows(&self.b);
+        let mut next = Matrix::new_uniform(0.0, 2 * hidden, input.h());
+        let mut tanh_c = Matrix::new_uniform(0.0, hidden, input.h());
+        for i in 0..input.h() {
+            for j in 0..hidden {
+                let (input_gate, forget, candidate, output) = (
+                    Sigmoid::calc(gates[(i, j)]),
+                    Sigmoid::calc(gates[(i, hidden + j)]),
+                    gates[(i, 2 * hidden + j)].tanh(),
+                    Sigmoid::calc(gates[(i, 3 * hidden + j)]),
+                );
+                gates[(i, j)] = input_gate;
+                gates[(i, hidden + j)] = forget;
+                gates[(i, 2 * hidden + j)] = candidate;
+                gates[(i, 3 * hidden + j)] = output;
+                let c = forget * state[(i, hidden + j)] + input_gate * candidate;
+                tanh_c[(i, j)] = c.tanh();
+                next[(i, j)] = output * tanh_c[(i, j)];
+                next[(i, hidden + j)] = c;
+            }
+        }
+        let cache = LSTMCache {
+            input: input.clone(),
+            previous: state.clone(),
+            gates,
+            tanh_c,
+        };
+        (next, cache)
+    }
+
+    fn step_back(
+        &self,
+        cache: &LSTMCache,
+        cost_wrt_state: &Matrix<f64>,
+        grads: &mut [Matrix<f64>],
+    ) -> (Matrix<f64>, Matrix<f64>) {
+        let hidden = self.hidden();
+        let LSTMCache {
+            input,
+            previous,
+            gates,
+            tanh_c,
+        } = cache;
+        let cases = input.h();
+        let mut cost_wrt_gates = Matrix::new_uniform(0.0, 4 * hidden, cases);
+        let mut cost_wrt_previous = Matrix::new_uniform(0.0, 2 * hidden, cases);
+        for i in 0..cases {
+            for j in 0..hidden {
+                let (input_gate, forget, candidate, output) = (
+                    gates[(i, j)],
+                    gates[(i, hidden + j)],
+                    gates[(i, 2 * hidden + j)],
+                    gates[(i, 3 * hidden + j)],
+                );
+                let cost_wrt_h = cost_wrt_state[(i, j)];
+                let cost_wrt_c = cost_wrt_state[(i, hidden + j)]
+                    + cost_wrt_h * output * (1.0 - tanh_c[(i, j)] * tanh_c[(i, j)]);
+                let previous_c = previous[(i, hidden + j)];
+                // through each gate's activation
+                cost_wrt_gates[(i, j)] = cost_wrt_c * candidate * input_gate * (1.0 - input_gate);
+                cost_wrt_gates[(i, hidden + j)] = cost_wrt_c * previous_c * forget * (1.0 - forget);
+                cost_wrt_gates[(i, 2 * hidden + j)] =
+                    cost_wrt_c * input_gate * (1.0 - candidate * candidate);
+                cost_wrt_gates[(i, 3 * hidden + j)] =
+                    cost_wrt_h * tanh_c[(i, j)] * output * (1.0 - output);
+                cost_wrt_previous[(i, hidden + j)] = cost_wrt_c * forget;
+            }
+        }
+        let [w_x_grad, w_h_grad, b_grad] = grads else {
+            panic!("an LSTM cell has 3 params");
+        };
+        add_grads(w_x_grad, &cost_wrt_gates, input, Some(b_grad));
+        add_grads(
+            w_h_grad,
+            &cost_wrt_gates,
+            &columns(previous, 0, hidden),
+            None,
+        );
+        let cost_wrt_previous_h = &cost_wrt_gates * &self.w_h;
+        for i in 0..cases {
+            for j in 0..hidden {
+                cost_wrt_previous[(i, j)] = cost_wrt_previous_h[(i, j)];
+            }
+        }
+        (&cost_wrt_gates * &self.w_x, cost_wrt_previous)
+    }
+
+    fn params_mut(&mut self) -> Vec<&mut Matrix<f64>> {
+        vec![&mut self.w_x, &mut self.w_h, &mut self.b]
+    }
+
+    fn name(&self) -> String {
+        String::from("lstm")
+    }
+}
+
+/// Gated recurrent unit (Cho et al., 2014), with the reset gate applied after the recurrent
+/// weights:
+/// r = sigmoid(x_r + h_r), u = sigmoid(x_u + h_u), n = tanh(x_n + r * h_n),
+/// state' = (1 - u) * n + u * state,
+/// where x_* are the input's terms, biases included, and h_* the state's. Gates are stacked
+/// reset, update, candidate.
+pub struct GRUCell {
+    w_x: Matrix<f64>, // 3 * hidden x features
+    w_h: Matrix<f64>, // 3 * hidden x hidden
+    b: Matrix<f64>,   // col vec
+}
+
+/// The input, previous state, gate activations and recurrent candidate term h_n of one step
+pub struct GRUCache {
+    input: Matrix<f64>,
+    previous: Matrix<f64>,
+    gates: Matrix<f64>,
+    h_n: Matrix<f64>,
+}
+
+impl GRUCell {
+    /// Unseeded weights, and biases of 0
+    pub fn new(features: usize, hidden: usize) -> Self {
+        Self::with_rng(features, hidden, &mut rand::thread_rng())
+    }
+
+    pub fn with_seed(features: usize, hidden: usize, seed: u64) -> Self {
+        Self::with_rng(features, hidden, &mut StdRng::seed_from_u64(seed))
+    }
+
+    fn with_rng<R: Rng>(features: usize, hidden: usize, rng: &mut R) -> Self {
+        let (w_x, w_h) = gate_weights(features, hidden, 3, rng);
+        Self {
+            w_x,
+            w_h,
+            b: Matrix::new_uniform(0.0, 1, 3 * hidden),
+        }
+    }
+}
+
+impl Cell for GRUCell {
+    type Cache = GRUCache;
+
+    fn features(&self) -> usize {
+        self.w_x.w()
+    }
+
+    fn hidden(&self) -> usize {
+        self.w_h.w()
+    }
+
+    fn step(&self, input: &Matrix<f64>, state: &Matrix<f64>) -> (Matrix<f64>, GRUCache) {
+        let hidden = self.hidden();
+        let mut gates = times_transposed(input, &self.w_x).add_to_rows(&self.b);
+        let recurrent = times_transposed(state, &self.w_h);
+        let mut next = Matrix::new_uniform(0.0, hidden, input.h());
+        let mut h_n = Matrix::new_uniform(0.0, hidden, input.h());
+        for i in 0..input.h() {
+            for j in 0..hidden {
+                let reset = Sigmoid::calc(gates[(i, j)] + recurrent[(i, j)]);
+                let update = Sigmoid::calc(gates[(i, hidden + j)] + recurrent[(i, hidden + j)]);
+                h_n[(i, j)] = recurrent[(i, 2 * hidden + j)];
+                let candidate = (gates[(i, 2 * hidden + j)] + reset * h_n[(i, j)]).tanh();
+                gates[(i, j)] = reset;
+                gates[(i, hidden + j)] = update;
+                gates[(i, 2 * hidden + j)] = candidate;
+                next[(i, j)] = (1.0 - update) * candidate + update * state[(i, j)];
+            }
+        }
+        let cache = GRUCache {
+            input: input.clone(),
+            previous: state.clone(),
+            gates,
+            h_n,
+        };
+        (next, cache)
+    }
+
+    fn step_back(
+        &self,
+        cache: &GRUCache,
+        cost_wrt_state: &Matrix<f64>,
+        grads: &mut [Matrix<f64>],
+    ) -> (Matrix<f64>, Matrix<f64>) {
+        let hidden = self.hidden();
+        let GRUCache {
+            input,
+            previous,
+            gates,
+            h_n,
+        } = cache;
+        let cases = input.h();
+        // wrt the input terms x_*, then the recurrent terms h_*, which differ only for n
+        let mut cost_wrt_x = Matrix::new_uniform(0.0, 3 * hidden, cases);
+        let mut cost_wrt_recurrent = Matrix::new_uniform(0.0, 3 * hidden, cases);
+        let mut cost_wrt_previous = Matrix::new_uniform(0.0, hidden, cases);
+        for i in 0..cases {
+            for j in 0..hidden {
+                let (reset, update, candidate) = (
+                    gates[(i, j)],
+                    gates[(i, hidden + j)],
+                    gates[(i, 2 * hidden + j)],
+                );
+                let cost_wrt_next = cost_wrt_state[(i, j)];
+                let cost_wrt_n = cost_wrt_next * (1.0 - update) * (1.0 - candidate * candidate);
+                let cost_wrt_r = cost_wrt_n * h_n[(i, j)] * reset * (1.0 - reset);
+                let cost_wrt_u =
+                    cost_wrt_next * (previous[(i, j)] - candidate) * update * (1.0 - update);
+                cost_wrt_x[(i, j)] = cost_wrt_r;
+                cost_wrt_x[(i, hidden + j)] = cost_wrt_u;
+                cost_wrt_x[(i, 2 * hidden + j)] = cost_wrt_n;
+                cost_wrt_recurrent[(i, j)] = cost_wrt_r;
+                cost_wrt_recurrent[(i, hidden + j)] = cost_wrt_u;
+                cost_wrt_recurrent[(i, 2 * hidden + j)] = cost_wrt_n * reset;
+                cost_wrt_previous[(i, j)] = cost_wrt_next * update;
+            }
+        }
+        let [w_x_grad, w_h_grad, b_grad] = grads else {
+            panic!("a GRU cell has 3 params");
+        };
+        add_grads(w_x_grad, &cost_wrt_x, input, Some(b_grad));
+        add_grads(w_h_grad, &cost_wrt_recurrent, previous, None);
+        (
+            &cost_wrt_x * &self.w_x,
+            cost_wrt_previous + &cost_wrt_recurrent * &self.w_h,
+        )
+    }
+
+    fn params_mut(&mut self) -> Vec<&mut Matrix<f64>> {
+        vec![&mut self.w_x, &mut self.w_h, &mut self.b]
+    }
+
+    fn name(&self) -> String {
+        String::from("gru")
+    }
+}
+
+/// What a `Recurrent` layer outputs for every sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputMode {
+    /// The hidden state after the last step
+    ManyToOne,
+    /// The hidden state after every step, one after another
+    ManyToMany,
+}
+
+/// Runs a `Cell` over sequences of `steps` steps, starting from a state of 0, and trains it with
+/// backpropagation through time. Every case is one row holding its steps one after another, each
+/// `features` wide.
+///
+/// Sequences shorter than `steps` can be padded with a mask value: a step whose features all
+/// equal it is skipped, carrying the state over unchanged, and outputs 0 in `ManyToMany` mode.
+pub struct Recurrent<C: Cell> {
+    cell: C,
+    steps: usize,
+    output: OutputMode,
+    mask_value: Option<f64>,
+    truncate: Option<usize>,
+    // from the last forward pass
+    caches: Vec<C::Cache>,
+    masked: Vec<Vec<bool>>, // every step's, one per case
+}
+
+impl<C: Cell> Recurrent<C> {
+    /// Many to one, with no masking or truncation
+    pub fn new(cell: C, steps: usize) -> Self {
+        assert!(steps > 0, "steps must be positive");
+        Self {
+            cell,
+            steps,
+            output: OutputMode::ManyToOne,
+            mask_value: None,
+            truncate: None,
+            caches: Vec::new(),
+            masked: Vec::new(),
+        }
+    }
+
+    pub fn many_to_many(mut self) -> Self {
+        self.output = OutputMode::ManyToMany;
+        self
+    }
+
+    pub fn mask_value(mut self, value: f64) -> Self {
+        self.mask_value = Some(value);
+        self
+    }
+
+    /// Truncated BPTT: cuts the gradient every `steps` steps, counted back from the last, as if
+    /// the sequences were trained in chunks with the state carried forward but not back. A many
+    /// to one layer learns from its last `steps` inputs only.
+    pub fn truncate(mut self, steps: usize) -> Self {
+        assert!(steps > 0, "steps must be positive");
+        self.truncate = Some(steps);
+        self
+    }
+
+    pub fn cell(&self) -> &C {
+        &self.cell
+    }
+
+    pub fn output_mode(&self) -> OutputMode {
+        self.output
+    }
+
+    /// Width of every output row
+    pub fn output_size(&self) -> usize {
+        match self.output {
+            OutputMode::ManyToOne => self.cell.hidden(),
+            OutputMode::ManyToMany => self.steps * self.cell.hidden(),
+        }
+    }
+
+    fn is_masked(&self, step_input: &Matrix<f64>, i: usize) -> bool {
+        self.mask_value
+            .is_some_and(|value| (0..step_input.w()).all(|j| step_input[(i, j)] == value))
+    }
+
+    // (output, every step's cache, every step's mask)
+    fn run(&self, input: &Matrix<f64>) -> (Matrix<f64>, Vec<C::Cache>, Vec<Vec<bool>>) {
+        assert_eq!(
+            input.w(),
+            self.steps * self.cell.features(),
+            "input rows must hold {} steps of {} features",
+            self.steps,
+            self.cell.features()
+        );
+        let (cases, hidden) = (input.h(), self.cell.hidden());
+        let mut state = Matrix::new_uniform(0.0, self.cell.state_size(), cases);
+        let mut output = Matrix::new_uniform(0.0, self.output_size(), cases);
+        let mut caches = Vec::with_capacity(self.steps);
+        let mut masked = Vec::with_capacity(self.steps);
+        for step in 0..self.steps {
+            let features = self.cell.features();
+            let step_input = columns(input, step * features, features);
+            let (mut next, cache) = self.cell.step(&step_input, &state);
+            let step_masked: Vec<bool> =
+                (0..cases).map(|i| self.is_masked(&step_input, i)).collect();
+            for i in 0..cases {
+                if step_masked[i] {
+                    for j in 0..state.w() {
+                        next[(i, j)] = state[(i, j)];
+                    }
+                } else if self.output == OutputMode::ManyToMany {
+                    for j in 0..hidden {
+                        output[(i, step * hidden + j)] = next[(i, j)];
+                    }
+                }
+            }
+            state = next;
+            caches.push(cache);
+            masked.push(step_masked);
+        }
+        if self.output == OutputMode::ManyToOne {
+            for i in 0..cases {
+                for j in 0..hidden {
+                    output[(i, j)] = state[(i, j)];
+                }
+            }
+        }
+        (output, caches, masked)
+    }
+}
+
+impl<C: Cell> Layer for Recurrent<C> {
+    fn forward(&mut self, input: &Matrix<f64>) -> Matrix<f64> {
+        let (output, caches, masked) = self.run(input);
+        self.caches = caches;
+        self.masked = masked;
+        output
+    }
+
+    fn predict(&self, input: &Matrix<f64>) -> Matrix<f64> {
+        self.run(input).0
+    }
+
+    fn backward(&mut self, cost_wrt_output: &Matrix<f64>) -> (Matrix<f64>, Vec<Matrix<f64>>) {
+        let (cases, hidden, features) = (
+            cost_wrt_output.h(),
+            self.cell.hidden(),
+            self.cell.features(),
+        );
+        let state_size = self.cell.state_size();
+        let mut grads: Vec<Matrix<f64>> = self
+            .cell
+            .params_mut()
+            .iter()
+            .map(|param| Matrix::new_uniform(0.0, param.w(), param.h()))
+            .collect();
+        let mut cost_wrt_input = Matrix::new_uniform(0.0, self.steps * features, cases);
+        let mut carried = Matrix::new_uniform(0.0, state_size, cases);
+        for step in (0..self.steps).rev() {
+            let mut cost_wrt_state = carried;
+            let masked = &self.masked[step];
+            for i in 0..cases {
+                let from_output = match self.output {
+                    OutputMode::ManyToOne if step == self.steps - 1 => Some(0),
+                    OutputMode::ManyToMany if !masked[i] => Some(step * hidden),
+                    _ => None,
+                };
+                if let Some(start) = from_output {
+                    for j in 0..hidden {
+                        cost_wrt_state[(i, j)] += cost_wrt_output[(i, start + j)];
+                    }
+                }
+            }
+            // masked steps hand their state's gradient straight to the previous state
+            let mut passed = Matrix::new_uniform(0.0, state_size, cases);
+            for i in (0..cases).filter(|&i| masked[i]) {
+                for j in 0..state_size {
+                    passed[(i, j)] = cost_wrt_state[(i, j)];
+                    cost_wrt_state[(i, j)] = 0.0;
+                }
+            }
+            let (cost_wrt_step_input, cost_wrt_previous) =
+                self.cell
+                    .step_back(&self.caches[step], &cost_wrt_state, &mut grads);
+            for i in 0..cases {
+                for j in 0..features {
+                    cost_wrt_input[(i, step * features + j)] = cost_wrt_step_input[(i, j)];
+                }
+            }
+            carried = cost_wrt_previous + passed;
+            if self
+                .truncate
+                .is_some_and(|truncate| (self.steps - step).is_multiple_of(truncate))
+            {
+                carried = Matrix::new_uniform(0.0, state_size, cases);
+            }
+        }
+        (cost_wrt_input, grads)
+    }
+
+    fn params_mut(&mut self) -> Vec<&mut Matrix<f64>> {
+        self.cell.params_mut()
+    }
+
+    fn name(&self) -> String {
+        self.cell.name()
+    }
+}
@@ -0,0 +1,121 @@
+use super::{Layer, OutputKind};
+use crate::algebra::{MatLike, Matrix};
+
+/// log(softmax(row)) for every row, via log-sum-exp so large inputs can't overflow.
+pub fn log_softmax(input: &Matrix<f64>) -> Matrix<f64> {
+    let mut output = input.clone();
+    for i in 0..input.h() {
+        let max = (0..input.w())
+            .map(|j| input[(i, j)])
+            .fold(f64::NEG_INFINITY, f64::max);
+        let log_sum_exp = max
+            + (0..input.w())
+                .map(|j| (input[(i, j)] - max).exp())
+                .sum::<f64>()
+                .ln();
+        for j in 0..input.w() {
+            output[(i, j)] = input[(i, j)] - log_sum_exp;
+        }
+    }
+    output
+}
+
+/// softmax(row) for every row, so each row of the output sums to 1.
+pub fn softmax(input: &Matrix<f64>) -> Matrix<f64> {
+    log_softmax(input).apply(f64::exp)
+}
+
+// sum over j of a_ij * b_ij, for every row i
+fn row_dots(a: &Matrix<f64>, b: &Matrix<f64>) -> Vec<f64> {
+    (0..a.h())
+        .map(|i| (0..a.w()).map(|j| a[(i, j)] * b[(i, j)]).sum())
+        .collect()
+}
+
+/// Softmax over every row. Unlike `ActivationLayer`, every output depends on the whole row.
+#[derive(Default)]
+pub struct Softmax {
+    output: Matrix<f64>,
+}
+
+impl Softmax {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Layer for Softmax {
+    fn forward(&mut self, input: &Matrix<f64>) -> Matrix<f64> {
+        self.output = self.predict(input);
+        self.output.clone()
+    }
+
+    fn predict(&self, input: &Matrix<f64>) -> Matrix<f64> {
+        softmax(input)
+    }
+
+    // cost_wrt_input_j = p_j * (cost_wrt_output_j - sum over k of cost_wrt_output_k * p_k)
+    fn backward(&mut self, cost_wrt_output: &Matrix<f64>) -> (Matrix<f64>, Vec<Matrix<f64>>) {
+        let dots = row_dots(cost_wrt_output, &self.output);
+        let mut cost_wrt_input = cost_wrt_output.clone();
+        for i in 0..cost_wrt_input.h() {
+            for j in 0..cost_wrt_input.w() {
+                cost_wrt_input[(i, j)] = self.output[(i, j)] * (cost_wrt_output[(i, j)] - dots[i]);
+            }
+        }
+        (cost_wrt_input, Vec::new())
+    }
+
+    fn output_kind(&self) -> OutputKind {
+        OutputKind::Probabilities
+    }
+
+    fn name(&self) -> String {
+        String::from("softmax")
+    }
+}
+
+/// log(softmax) over every row, for use with `NegativeLogLikelihood`.
+#[derive(Default)]
+pub struct LogSoftmax {
+    output: Matrix<f64>,
+}
+
+impl LogSoftmax {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Layer for LogSoftmax {
+    fn forward(&mut self, input: &Matrix<f64>) -> Matrix<f64> {
+        self.output = self.predict(input);
+        self.output.clone()
+    }
+
+    fn predict(&self, input: &Matrix<f64>) -> Matrix<f64> {
+        log_softmax(input)
+    }
+
+    // cost_wrt_input_j = cost_wrt_output_j - p_j * sum over k of cost_wrt_output_k
+    fn backward(&mut self, cost_wrt_output: &Matrix<f64>) -> (Matrix<f64>, Vec<Matrix<f64>>) {
+        let mut cost_wrt_input = cost_wrt_output.clone();
+        for i in 0..cost_wrt_input.h() {
+            let sum: f64 = (0..cost_wrt_output.w())
+                .map(|j| cost_wrt_output[(i, j)])
+                .sum();
+            for j in 0..cost_wrt_input.w() {
+                cost_wrt_input[(i, j)] -= self.output[(i, j)].exp() * sum;
+            }
+        }
+        (cost_wrt_input, Vec::new())
+    }
+
+    fn output_kind(&self) -> OutputKind {
+        OutputKind::LogProbabilities
+    }
+
+    fn name(&self) -> String {
+        String::from("log_softmax")
+    }
+}
@@ -1,3 +1,143 @@
 pub mod activations;
+pub mod autograd;
+mod binary;
+pub mod callbacks;
+pub mod checkpoint;
 pub mod cost;
+pub mod dual;
 pub mod feedforward;
+pub mod gradcheck;
+pub mod init;
+pub mod json;
+pub mod layers;
+pub mod optim;
+pub mod regularization;
+pub mod schedule;
+pub mod sequential;
+pub mod train;
+
+use std::fmt::Display;
+use std::io;
+
+/// A name that does not match any known `ActivationKind`/`CostKind`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseKindError {
+    pub kind: &'static str,
+    pub name: String,
+}
+
+impl ParseKindError {
+    pub fn new(kind: &'static str, name: &str) -> Self {
+        Self {
+            kind,
+            name: name.to_string(),
+        }
+    }
+}
+
+impl Display for ParseKindError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown {} \"{}\"", self.kind, self.name)
+    }
+}
+
+impl std::error::Error for ParseKindError {}
+
+/// Why a model couldn't be saved or loaded.
+#[derive(Debug)]
+pub enum ModelError {
+    Io(io::Error),
+    /// The file isn't a model in a format and version this build understands
+    Format(String),
+    /// The file holds a model, but of a different kind than the one asked for
+    Mismatch {
+        field: &'static str,
+        expected: String,
+        found: String,
+    },
+    /// The named params hold NaN or an infinity, which the format can't represent
+    NonFinite(String),
+}
+
+impl ModelError {
+    pub fn format(message: impl Into<String>) -> Self {
+        ModelError::Format(message.into())
+    }
+}
+
+impl Display for ModelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModelError::Io(error) => write!(f, "io error: {}", error),
+            ModelError::Format(message) => write!(f, "invalid model file: {}", message),
+            ModelError::Mismatch {
+                field,
+                expected,
+                found,
+            } => write!(
+                f,
+                "model {} mismatch: expected \"{}\", file has \"{}\"",
+                field, expected, found
+            ),
+            ModelError::NonFinite(what) => {
+                write!(f, "can't save model: {} aren't all finite", what)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ModelError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ModelError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ModelError {
+    fn from(error: io::Error) -> Self {
+        ModelError::Io(error)
+    }
+}
+
+/// Why `Trainer::try_fit` stopped before the end of the run.
+#[derive(Debug)]
+pub enum TrainError {
+    /// A checkpoint couldn't be written
+    Checkpoint(ModelError),
+    /// A `Callback` aborted the run, at `epoch` after `step` steps
+    Aborted {
+        epoch: usize,
+        step: usize,
+        reason: String,
+    },
+}
+
+impl Display for TrainError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TrainError::Checkpoint(error) => write!(f, "couldn't write checkpoint: {}", error),
+            TrainError::Aborted {
+                epoch,
+                step,
+                reason,
+            } => write!(f, "aborted at epoch {} step {}: {}", epoch, step, reason),
+        }
+    }
+}
+
+impl std::error::Error for TrainError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TrainError::Checkpoint(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<ModelError> for TrainError {
+    fn from(error: ModelError) -> Self {
+        TrainError::Checkpoint(error)
+    }
+}
@@ -0,0 +1,362 @@
+use crate::algebra::{MatLike, Matrix};
+
+/// Turns gradients into param updates. `params` and `grads` come in the same order on every call
+/// (eg. `Trainable::params_mut` order), which is how per-param state is matched to its param.
+pub trait Optimizer {
+    fn step(&mut self, params: &mut [&mut Matrix<f64>], grads: &[Matrix<f64>], learning_rate: f64);
+
+    /// Everything accumulated by past steps, for checkpoints. Nothing by default.
+    fn state(&self) -> OptimizerState {
+        OptimizerState::default()
+    }
+
+    /// Restores what `state` returned, so the next step is the one that would have followed it.
+    fn set_state(&mut self, _state: OptimizerState) {}
+}
+
+/// What an `Optimizer` carries from one step to the next.
+#[derive(Debug, Clone, Default)]
+pub struct OptimizerState {
+    /// Steps taken, for optimizers that count them
+    pub steps: u64,
+    /// Every kind of per-param state (eg. Adam's two moments), each one matrix per param in
+    /// `step` order. Empty before the first step.
+    pub buffers: Vec<Vec<Matrix<f64>>>,
+}
+
+// the single buffer of an optimizer with one kind of per-param state
+fn single_buffer(state: OptimizerState) -> Vec<Matrix<f64>> {
+    state.buffers.into_iter().next().unwrap_or_default()
+}
+
+// zeroed state shaped like grads, made on the first step
+fn init_state(state: &mut Vec<Matrix<f64>>, grads: &[Matrix<f64>]) {
+    if state.len() != grads.len() {
+        *state = grads
+            .iter()
+            .map(|grad| Matrix::new_uniform(0.0, grad.w(), grad.h()))
+            .collect();
+    }
+}
+
+fn check_lengths(params: &[&mut Matrix<f64>], grads: &[Matrix<f64>]) {
+    assert_eq!(
+        params.len(),
+        grads.len(),
+        "number of params does not match number of grads"
+    );
+}
+
+/// p -= lr * g
+#[derive(Debug, Clone, Default)]
+pub struct Sgd;
+
+impl Optimizer for Sgd {
+    fn step(&mut self, params: &mut [&mut Matrix<f64>], grads: &[Matrix<f64>], learning_rate: f64) {
+        check_lengths(params, grads);
+        for (param, grad) in params.iter_mut().zip(grads) {
+            for (p, g) in param.iter_mut().zip(grad.iter()) {
+                *p -= learning_rate * g;
+            }
+        }
+    }
+}
+
+/// v = momentum * v + g, p -= lr * v
+#[derive(Debug, Clone)]
+pub struct Momentum {
+    pub momentum: f64,
+    velocity: Vec<Matrix<f64>>,
+}
+
+impl Momentum {
+    pub fn new(momentum: f64) -> Self {
+        Self {
+            momentum,
+            velocity: Vec::new(),
+        }
+    }
+}
+
+impl Default for Momentum {
+    fn default() -> Self {
+        Self::new(0.9)
+    }
+}
+
+impl Optimizer for Momentum {
+    fn step(&mut self, params: &mut [&mut Matrix<f64>], grads: &[Matrix<f64>], learning_rate: f64) {
+        check_lengths(params, grads);
+        init_state(&mut self.velocity, grads);
+        for ((param, grad), velocity) in params.iter_mut().zip(grads).zip(&mut self.velocity) {
+            for ((p, g), v) in param.iter_mut().zip(grad.iter()).zip(velocity.iter_mut()) {
+                *v = self.momentum * *v + g;
+                *p -= learning_rate * *v;
+            }
+        }
+    }
+
+    fn state(&self) -> OptimizerState {
+        OptimizerState {
+            steps: 0,
+            buffers: vec![self.velocity.clone()],
+        }
+    }
+
+    fn set_state(&mut self, state: OptimizerState) {
+        self.velocity = single_buffer(state);
+    }
+}
+
+/// `Momentum`, but the step is taken from where the velocity is about to carry the params:
+/// v = momentum * v + g, p -= lr * (g + momentum * v)
+#[derive(Debug, Clone)]
+pub struct Nesterov {
+    pub momentum: f64,
+    velocity: Vec<Matrix<f64>>,
+}
+
+impl Nesterov {
+    pub fn new(momentum: f64) -> Self {
+        Self {
+            momentum,
+            velocity: Vec::new(),
+        }
+    }
+}
+
+impl Default for Nesterov {
+    fn default() -> Self {
+        Self::new(0.9)
+    }
+}
+
+impl Optimizer for Nesterov {
+    fn step(&mut self, params: &mut [&mut Matrix<f64>], grads: &[Matrix<f64>], learning_rate: f64) {
+        check_lengths(params, grads);
+        init_state(&mut self.velocity, grads);
+        for ((param, grad), velocity) in params.iter_mut().zip(grads).zip(&mut self.velocity) {
+            for ((p, g), v) in param.iter_mut().zip(grad.iter()).zip(velocity.iter_mut()) {
+                *v = self.momentum * *v + g;
+                *p -= learning_rate * (g + self.momentum * *v);
+            }
+        }
+    }
+
+    fn state(&self) -> OptimizerState {
+        OptimizerState {
+            steps: 0,
+            buffers: vec![self.velocity.clone()],
+        }
+    }
+
+    fn set_state(&mut self, state: OptimizerState) {
+        self.velocity = single_buffer(state);
+    }
+}
+
+/// s = decay * s + (1 - decay) * g^2, p -= lr * g / (sqrt(s) + epsilon)
+#[derive(Debug, Clone)]
+pub struct RMSProp {
+    pub decay: f64,
+    pub epsilon: f64,
+    mean_square: Vec<Matrix<f64>>,
+}
+
+impl RMSProp {
+    pub fn new(decay: f64, epsilon: f64) -> Self {
+        Self {
+            decay,
+            epsilon,
+            mean_square: Vec::new(),
+        }
+    }
+}
+
+impl Default for RMSProp {
+    fn default() -> Self {
+        Self::new(0.9, 1e-8)
+    }
+}
+
+impl Optimizer for RMSProp {
+    fn step(&mut self, params: &mut [&mut Matrix<f64>], grads: &[Matrix<f64>], learning_rate: f64) {
+        check_lengths(params, grads);
+        init_state(&mut self.mean_square, grads);
+        for ((param, grad), mean_square) in params.iter_mut().zip(grads).zip(&mut self.mean_square)
+        {
+            for ((p, g), s) in param
+                .iter_mut()
+                .zip(grad.iter())
+                .zip(mean_square.iter_mut())
+            {
+                *s = self.decay * *s + (1.0 - self.decay) * g * g;
+                *p -= learning_rate * g / (s.sqrt() + self.epsilon);
+            }
+        }
+    }
+
+    fn state(&self) -> OptimizerState {
+        OptimizerState {
+            steps: 0,
+            buffers: vec![self.mean_square.clone()],
+        }
+    }
+
+    fn set_state(&mut self, state: OptimizerState) {
+        self.mean_square = single_buffer(state);
+    }
+}
+
+/// s += g^2, p -= lr * g / (sqrt(s) + epsilon)
+#[derive(Debug, Clone)]
+pub struct AdaGrad {
+    pub epsilon: f64,
+    sum_square: Vec<Matrix<f64>>,
+}
+
+impl AdaGrad {
+    pub fn new(epsilon: f64) -> Self {
+        Self {
+            epsilon,
+            sum_square: Vec::new(),
+        }
+    }
+}
+
+impl Default for AdaGrad {
+    fn default() -> Self {
+        Self::new(1e-10)
+    }
+}
+
+impl Optimizer for AdaGrad {
+    fn step(&mut self, params: &mut [&mut Matrix<f64>], grads: &[Matrix<f64>], learning_rate: f64) {
+        check_lengths(params, grads);
+        init_state(&mut self.sum_square, grads);
+        for ((param, grad), sum_square) in params.iter_mut().zip(grads).zip(&mut self.sum_square) {
+            for ((p, g), s) in param.iter_mut().zip(grad.iter()).zip(sum_square.iter_mut()) {
+                *s += g * g;
+                *p -= learning_rate * g / (s.sqrt() + self.epsilon);
+            }
+        }
+    }
+
+    fn state(&self) -> OptimizerState {
+        OptimizerState {
+            steps: 0,
+            buffers: vec![self.sum_square.clone()],
+        }
+    }
+
+    fn set_state(&mut self, state: OptimizerState) {
+        self.sum_square = single_buffer(state);
+    }
+}
+
+/// Bias-corrected moving averages of g (m) and g^2 (v), p -= lr * m / (sqrt(v) + epsilon)
+#[derive(Debug, Clone)]
+pub struct Adam {
+    pub beta1: f64,
+    pub beta2: f64,
+    pub epsilon: f64,
+    steps: i32,
+    first_moment: Vec<Matrix<f64>>,
+    second_moment: Vec<Matrix<f64>>,
+}
+
+impl Adam {
+    pub fn new(beta1: f64, beta2: f64, epsilon: f64) -> Self {
+        Self {
+            beta1,
+            beta2,
+            epsilon,
+            steps: 0,
+            first_moment: Vec::new(),
+            second_moment: Vec::new(),
+        }
+    }
+}
+
+impl Default for Adam {
+    fn default() -> Self {
+        Self::new(0.9, 0.999, 1e-8)
+    }
+}
+
+impl Optimizer for Adam {
+    fn step(&mut self, params: &mut [&mut Matrix<f64>], grads: &[Matrix<f64>], learning_rate: f64) {
+        check_lengths(params, grads);
+        init_state(&mut self.first_moment, grads);
+        init_state(&mut self.second_moment, grads);
+        self.steps += 1;
+        let first_correction = 1.0 - self.beta1.powi(self.steps);
+        let second_correction = 1.0 - self.beta2.powi(self.steps);
+        let moments = self.first_moment.iter_mut().zip(&mut self.second_moment);
+        for ((param, grad), (first, second)) in params.iter_mut().zip(grads).zip(moments) {
+            let elements = param
+                .iter_mut()
+                .zip(grad.iter())
+                .zip(first.iter_mut().zip(second.iter_mut()));
+            for ((p, g), (m, v)) in elements {
+                *m = self.beta1 * *m + (1.0 - self.beta1) * g;
+                *v = self.beta2 * *v + (1.0 - self.beta2) * g * g;
+                let m_hat = *m / first_correction;
+                let v_hat = *v / second_correction;
+                *p -= learning_rate * m_hat / (v_hat.sqrt() + self.epsilon);
+            }
+        }
+    }
+
+    fn state(&self) -> OptimizerState {
+        OptimizerState {
+            steps: self.steps as u64,
+            buffers: vec![self.first_moment.clone(), self.second_moment.clone()],
+        }
+    }
+
+    fn set_state(&mut self, state: OptimizerState) {
+        self.steps = state.steps as i32;
+        let mut buffers = state.buffers.into_iter();
+        self.first_moment = buffers.next().unwrap_or_default();
+        self.second_moment = buffers.next().unwrap_or_default();
+    }
+}
+
+/// `Adam` with decoupled weight decay: p -= lr * weight_decay * p before every Adam step.
+#[derive(Debug, Clone)]
+pub struct AdamW {
+    pub adam: Adam,
+    pub weight_decay: f64,
+}
+
+impl AdamW {
+    pub fn new(adam: Adam, weight_decay: f64) -> Self {
+        Self { adam, weight_decay }
+    }
+}
+
+impl Default for AdamW {
+    fn default() -> Self {
+        Self::new(Adam::default(), 0.01)
+    }
+}
+
+impl Optimizer for AdamW {
+    fn step(&mut self, params: &mut [&mut Matrix<f64>], grads: &[Matrix<f64>], learning_rate: f64) {
+        for param in params.iter_mut() {
+            for p in param.iter_mut() {
+                *p -= learning_rate * self.weight_decay * *p;
+            }
+        }
+        self.adam.step(params, grads, learning_rate);
+    }
+
+    fn state(&self) -> OptimizerState {
+        self.adam.state()
+    }
+
+    fn set_state(&mut self, state: OptimizerState) {
+        self.adam.set_state(state);
+    }
+}
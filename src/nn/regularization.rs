@@ -0,0 +1,81 @@
+use crate::algebra::{MatLike, Matrix};
+
+/// Penalty on the size of a layer's params, added to the loss:
+/// l1 * sum of |w| + l2 / 2 * sum of w^2.
+/// With l1 = 0 the l2 part is plain weight decay, its gradient being l2 * w. Biases are left
+/// alone unless `with_biases` is used.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Regularization {
+    pub l1: f64,
+    pub l2: f64,
+    pub biases: bool,
+}
+
+impl Regularization {
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    pub fn l1(strength: f64) -> Self {
+        Self {
+            l1: strength,
+            ..Self::default()
+        }
+    }
+
+    pub fn l2(strength: f64) -> Self {
+        Self {
+            l2: strength,
+            ..Self::default()
+        }
+    }
+
+    /// Splits `strength` between l1 (`l1_ratio` of it) and l2 (the rest).
+    pub fn elastic_net(strength: f64, l1_ratio: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&l1_ratio),
+            "l1_ratio must be between 0 and 1"
+        );
+        Self {
+            l1: strength * l1_ratio,
+            l2: strength * (1.0 - l1_ratio),
+            biases: false,
+        }
+    }
+
+    /// Penalises biases as well as weights.
+    pub fn with_biases(self) -> Self {
+        Self {
+            biases: true,
+            ..self
+        }
+    }
+
+    pub fn is_none(&self) -> bool {
+        self.l1 == 0.0 && self.l2 == 0.0
+    }
+
+    pub fn penalty(&self, param: &Matrix<f64>) -> f64 {
+        if self.is_none() {
+            return 0.0;
+        }
+        param
+            .iter()
+            .map(|w| self.l1 * w.abs() + 0.5 * self.l2 * w * w)
+            .sum()
+    }
+
+    /// penalty_wrt_param, taking the gradient of |w| at 0 to be 0
+    pub fn grad(&self, param: &Matrix<f64>) -> Matrix<f64> {
+        param.apply(|w| {
+            let sign = if w > 0.0 {
+                1.0
+            } else if w < 0.0 {
+                -1.0
+            } else {
+                0.0
+            };
+            self.l1 * sign + self.l2 * w
+        })
+    }
+}
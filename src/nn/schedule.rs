@@ -0,0 +1,256 @@
+use std::f64::consts::PI;
+use std::fmt::Debug;
+
+/// How far training has got, passed to `LrSchedule::learning_rate` before every step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    /// Steps taken so far, counted across epochs
+    pub step: usize,
+    pub epoch: usize,
+    pub steps_per_epoch: usize,
+}
+
+impl Progress {
+    /// Epochs done so far, including the fraction of the current one
+    pub fn epochs(&self) -> f64 {
+        self.step as f64 / self.steps_per_epoch.max(1) as f64
+    }
+}
+
+/// Picks the learning rate for every step from the base rate in `TrainConfig`. The `Debug` output
+/// is what `History` records as the schedule.
+pub trait LrSchedule: Debug {
+    fn learning_rate(&self, base: f64, progress: Progress) -> f64;
+
+    /// Called at the end of every epoch with its mean loss.
+    fn end_epoch(&mut self, _loss: f64) {}
+
+    /// Whatever `end_epoch` has accumulated, for checkpoints. Nothing by default.
+    fn state(&self) -> Vec<f64> {
+        Vec::new()
+    }
+
+    /// Restores what `state` returned.
+    fn set_state(&mut self, _state: &[f64]) {}
+}
+
+/// Always the base rate.
+#[derive(Debug, Clone, Default)]
+pub struct Constant;
+
+impl LrSchedule for Constant {
+    fn learning_rate(&self, base: f64, _: Progress) -> f64 {
+        base
+    }
+}
+
+/// Multiplies the rate by `gamma` every `epochs` epochs.
+#[derive(Debug, Clone)]
+pub struct StepDecay {
+    pub epochs: usize,
+    pub gamma: f64,
+}
+
+impl StepDecay {
+    pub fn new(epochs: usize, gamma: f64) -> Self {
+        assert!(epochs > 0, "epochs must be positive");
+        Self { epochs, gamma }
+    }
+}
+
+impl LrSchedule for StepDecay {
+    fn learning_rate(&self, base: f64, progress: Progress) -> f64 {
+        base * self.gamma.powi((progress.epoch / self.epochs) as i32)
+    }
+}
+
+/// Multiplies the rate by `gamma` every epoch.
+#[derive(Debug, Clone)]
+pub struct ExponentialDecay {
+    pub gamma: f64,
+}
+
+impl ExponentialDecay {
+    pub fn new(gamma: f64) -> Self {
+        Self { gamma }
+    }
+}
+
+impl LrSchedule for ExponentialDecay {
+    fn learning_rate(&self, base: f64, progress: Progress) -> f64 {
+        base * self.gamma.powi(progress.epoch as i32)
+    }
+}
+
+/// Cosine annealing with warm restarts (SGDR). The rate falls from the base rate to
+/// `base * min_factor` along half a cosine over `period` epochs, then jumps back up. Each period
+/// is `period_mult` times as long as the last.
+#[derive(Debug, Clone)]
+pub struct CosineAnnealing {
+    pub period: f64,
+    pub period_mult: f64,
+    pub min_factor: f64,
+}
+
+impl CosineAnnealing {
+    pub fn new(period: f64, period_mult: f64, min_factor: f64) -> Self {
+        assert!(period > 0.0, "period must be positive");
+        assert!(period_mult >= 1.0, "period_mult must be at least 1");
+        Self {
+            period,
+            period_mult,
+            min_factor,
+        }
+    }
+}
+
+impl LrSchedule for CosineAnnealing {
+    fn learning_rate(&self, base: f64, progress: Progress) -> f64 {
+        let mut t = progress.epochs();
+        let mut period = self.period;
+        while t >= period {
+            t -= period;
+            period *= self.period_mult;
+        }
+        let min = base * self.min_factor;
+        min + (base - min) * 0.5 * (1.0 + (PI * t / period).cos())
+    }
+}
+
+/// Ramps the rate linearly from 0 over the first `steps` steps, then hands over to `inner`.
+#[derive(Debug)]
+pub struct LinearWarmup {
+    pub steps: usize,
+    pub inner: Box<dyn LrSchedule>,
+}
+
+impl LinearWarmup {
+    pub fn new<S: LrSchedule + 'static>(steps: usize, inner: S) -> Self {
+        Self {
+            steps,
+            inner: Box::new(inner),
+        }
+    }
+}
+
+impl LrSchedule for LinearWarmup {
+    fn learning_rate(&self, base: f64, progress: Progress) -> f64 {
+        let rate = self.inner.learning_rate(base, progress);
+        if progress.step < self.steps {
+            rate * (progress.step + 1) as f64 / self.steps as f64
+        } else {
+            rate
+        }
+    }
+
+    fn end_epoch(&mut self, loss: f64) {
+        self.inner.end_epoch(loss);
+    }
+
+    fn state(&self) -> Vec<f64> {
+        self.inner.state()
+    }
+
+    fn set_state(&mut self, state: &[f64]) {
+        self.inner.set_state(state);
+    }
+}
+
+/// The one-cycle policy, with the base rate as the peak. Over the first `warmup_fraction` of
+/// `total_steps` the rate rises from `base / div_factor` to `base`, then it falls to
+/// `base / (div_factor * final_div_factor)`, both along half a cosine.
+#[derive(Debug, Clone)]
+pub struct OneCycle {
+    pub total_steps: usize,
+    pub warmup_fraction: f64,
+    pub div_factor: f64,
+    pub final_div_factor: f64,
+}
+
+impl OneCycle {
+    pub fn new(total_steps: usize) -> Self {
+        Self {
+            total_steps,
+            warmup_fraction: 0.3,
+            div_factor: 25.0,
+            final_div_factor: 1e4,
+        }
+    }
+}
+
+impl LrSchedule for OneCycle {
+    fn learning_rate(&self, base: f64, progress: Progress) -> f64 {
+        // goes from start to end as t goes from 0 to 1
+        let anneal = |start: f64, end: f64, t: f64| {
+            end + (start - end) * 0.5 * (1.0 + (PI * t.min(1.0)).cos())
+        };
+        let initial = base / self.div_factor;
+        let last = initial / self.final_div_factor;
+        let warmup_steps = (self.total_steps as f64 * self.warmup_fraction).max(1.0);
+        let step = progress.step as f64;
+        if step < warmup_steps {
+            anneal(initial, base, step / warmup_steps)
+        } else {
+            let remaining = (self.total_steps as f64 - warmup_steps).max(1.0);
+            anneal(base, last, (step - warmup_steps) / remaining)
+        }
+    }
+}
+
+/// Multiplies the rate by `factor` whenever the epoch loss has not improved by more than
+/// `min_delta` for `patience` epochs, down to `base * min_factor`.
+#[derive(Debug, Clone)]
+pub struct ReduceOnPlateau {
+    pub factor: f64,
+    pub patience: usize,
+    pub min_delta: f64,
+    pub min_factor: f64,
+    best: f64,
+    bad_epochs: usize,
+    current_factor: f64,
+}
+
+impl ReduceOnPlateau {
+    pub fn new(factor: f64, patience: usize) -> Self {
+        Self {
+            factor,
+            patience,
+            min_delta: 1e-4,
+            min_factor: 0.0,
+            best: f64::INFINITY,
+            bad_epochs: 0,
+            current_factor: 1.0,
+        }
+    }
+}
+
+impl LrSchedule for ReduceOnPlateau {
+    fn learning_rate(&self, base: f64, _: Progress) -> f64 {
+        base * self.current_factor
+    }
+
+    fn end_epoch(&mut self, loss: f64) {
+        if loss < self.best - self.min_delta {
+            self.best = loss;
+            self.bad_epochs = 0;
+        } else {
+            self.bad_epochs += 1;
+            if self.bad_epochs >= self.patience {
+                self.current_factor = (self.current_factor * self.factor).max(self.min_factor);
+                self.bad_epochs = 0;
+            }
+        }
+    }
+
+    fn state(&self) -> Vec<f64> {
+        vec![self.best, self.bad_epochs as f64, self.current_factor]
+    }
+
+    fn set_state(&mut self, state: &[f64]) {
+        if let [best, bad_epochs, current_factor] = *state {
+            self.best = best;
+            self.bad_epochs = bad_epochs as usize;
+            self.current_factor = current_factor;
+        }
+    }
+}
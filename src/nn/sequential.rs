@@ -0,0 +1,376 @@
+use super::activations::{Activation, ActivationFn, ReLU, Sigmoid, Softplus};
+use super::binary::read_u64;
+use super::cost::{CategoricalCrossEntropy, Cost, CostFn, NegativeLogLikelihood, SumSquared};
+use super::init::Initializer;
+use super::layers::{
+    ActivationLayer, AvgPool2D, BatchNorm, Conv2D, Dense, Dropout, Flatten, GRUCell, ImageShape,
+    LSTMCell, Layer, LayerNorm, LogSoftmax, MaxPool2D, Maxout, OutputKind, RMSNorm, RNNCell,
+    Recurrent, Softmax,
+};
+use super::train::Trainable;
+use super::ModelError;
+use crate::algebra::{MatLike, Matrix};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// A stack of `Layer`s, each with its own forward and backward pass, eg.
+/// `Sequential::new().dense(784, 128).relu().dense(128, 10).sigmoid()`. Image layers are told the
+/// shape of their input, eg. `.conv2d((1, 28, 28), 8, 3).relu().max_pool2d((8, 26, 26), 2)`.
+///
+/// `dense` and `conv2d` weights are drawn with `Initializer::for_activation` of whichever layer
+/// comes next, so `.dense(..).relu()` gets He and `.dense(..).sigmoid()` Xavier, from an RNG
+/// seeded by `with_seed`.
+pub struct Sequential {
+    layers: Vec<Box<dyn Layer>>,
+    cost: CostFn,
+    rng: StdRng,
+    // the last layer, if it was built by `dense` or `conv2d` and awaits the next layer's name
+    pending: Option<Pending>,
+}
+
+// enough to rebuild a layer with another initializer, from the same seed
+enum Pending {
+    Dense(usize, usize, u64),
+    Conv2D(ImageShape, usize, usize, u64),
+}
+
+impl Pending {
+    fn build(&self, initializer: &Initializer) -> Box<dyn Layer> {
+        match *self {
+            Pending::Dense(in_shape, out_shape, seed) => Box::new(Dense::with_initializer(
+                in_shape,
+                out_shape,
+                initializer,
+                seed,
+            )),
+            Pending::Conv2D(input, filters, kernel, seed) => Box::new(Conv2D::with_initializer(
+                input,
+                filters,
+                kernel,
+                initializer,
+                seed,
+            )),
+        }
+    }
+}
+
+impl Default for Sequential {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sequential {
+    /// An empty stack, trained with `SumSquared` until `cost` says otherwise
+    pub fn new() -> Self {
+        Self::with_rng(StdRng::from_entropy())
+    }
+
+    /// `new`, with every layer the builder methods make (but not `layer`) drawn reproducibly
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_rng(StdRng::seed_from_u64(seed))
+    }
+
+    fn with_rng(rng: StdRng) -> Self {
+        Self {
+            layers: Vec::new(),
+            cost: CostFn::of::<SumSquared>(),
+            rng,
+            pending: None,
+        }
+    }
+
+    pub fn layer<L: Layer + 'static>(mut self, layer: L) -> Self {
+        self.push(Box::new(layer));
+        self
+    }
+
+    // pushes a layer the next one picks the initializer of, drawn with `XavierUniform` until then
+    fn pending(mut self, pending: Pending) -> Self {
+        self.push(pending.build(&Initializer::XavierUniform));
+        self.pending = Some(pending);
+        self
+    }
+
+    fn push(&mut self, layer: Box<dyn Layer>) {
+        if let Some(pending) = self.pending.take() {
+            let initializer = Initializer::for_activation(&layer.name());
+            *self.layers.last_mut().unwrap() = pending.build(&initializer);
+        }
+        self.layers.push(layer);
+    }
+
+    /// Weights from `Initializer::for_activation` of the next layer
+    pub fn dense(mut self, in_shape: usize, out_shape: usize) -> Self {
+        let seed = self.rng.gen();
+        self.pending(Pending::Dense(in_shape, out_shape, seed))
+    }
+
+    pub fn batch_norm(self, features: usize) -> Self {
+        self.layer(BatchNorm::new(features))
+    }
+
+    pub fn layer_norm(self, features: usize) -> Self {
+        self.layer(LayerNorm::new(features))
+    }
+
+    pub fn rms_norm(self, features: usize) -> Self {
+        self.layer(RMSNorm::new(features))
+    }
+
+    /// Inverted dropout of `rate`, with masks drawn from an RNG seeded with `seed`
+    pub fn dropout(self, rate: f64, seed: u64) -> Self {
+        self.layer(Dropout::new(rate, seed))
+    }
+
+    /// `dense`, with weights from `initializer` seeded with `seed`
+    pub fn dense_with(
+        self,
+        in_shape: usize,
+        out_shape: usize,
+        initializer: &Initializer,
+        seed: u64,
+    ) -> Self {
+        self.layer(Dense::with_initializer(
+            in_shape,
+            out_shape,
+            initializer,
+            seed,
+        ))
+    }
+
+    /// `Maxout` of `pieces` pieces, with weights drawn like `dense_with` with `XavierUniform`
+    pub fn maxout(mut self, in_shape: usize, out_shape: usize, pieces: usize) -> Self {
+        let seed = self.rng.gen();
+        self.layer(Maxout::with_initializer(
+            in_shape,
+            out_shape,
+            pieces,
+            &Initializer::XavierUniform,
+            seed,
+        ))
+    }
+
+    /// `filters` `kernel` x `kernel` convolutions over `input` images, with a stride of 1 and no
+    /// padding, and kernels like `dense` weights. Add a `Conv2D` with `layer` for anything else.
+    pub fn conv2d(mut self, input: ImageShape, filters: usize, kernel: usize) -> Self {
+        let seed = self.rng.gen();
+        self.pending(Pending::Conv2D(input, filters, kernel, seed))
+    }
+
+    /// `conv2d`, with kernels from `initializer` seeded with `seed`
+    pub fn conv2d_with(
+        self,
+        input: ImageShape,
+        filters: usize,
+        kernel: usize,
+        initializer: &Initializer,
+        seed: u64,
+    ) -> Self {
+        self.layer(Conv2D::with_initializer(
+            input,
+            filters,
+            kernel,
+            initializer,
+            seed,
+        ))
+    }
+
+    /// Max of every `size` x `size` window of `input` images, without overlap
+    pub fn max_pool2d(self, input: ImageShape, size: usize) -> Self {
+        self.layer(MaxPool2D::new(input, size))
+    }
+
+    /// Mean of every `size` x `size` window of `input` images, without overlap
+    pub fn avg_pool2d(self, input: ImageShape, size: usize) -> Self {
+        self.layer(AvgPool2D::new(input, size))
+    }
+
+    pub fn flatten(self, input: ImageShape) -> Self {
+        self.layer(Flatten::new(input))
+    }
+
+    /// Many to one `RNNCell` over rows of `steps` steps of `features`, outputting the last of
+    /// `hidden` states. Add a `Recurrent` with `layer` for anything else.
+    pub fn rnn(mut self, features: usize, hidden: usize, steps: usize) -> Self {
+        let cell = RNNCell::with_seed(features, hidden, self.rng.gen());
+        self.layer(Recurrent::new(cell, steps))
+    }
+
+    /// `rnn`, with an `LSTMCell`
+    pub fn lstm(mut self, features: usize, hidden: usize, steps: usize) -> Self {
+        let cell = LSTMCell::with_seed(features, hidden, self.rng.gen());
+        self.layer(Recurrent::new(cell, steps))
+    }
+
+    /// `rnn`, with a `GRUCell`
+    pub fn gru(mut self, features: usize, hidden: usize, steps: usize) -> Self {
+        let cell = GRUCell::with_seed(features, hidden, self.rng.gen());
+        self.layer(Recurrent::new(cell, steps))
+    }
+
+    pub fn activation<A: Activation + 'static>(self) -> Self {
+        self.layer(ActivationLayer::of::<A>())
+    }
+
+    /// Takes an `ActivationKind` (eg. parsed from a config file) or an `ActivationFn`.
+    pub fn activation_fn<F: Into<ActivationFn>>(self, activation: F) -> Self {
+        self.layer(ActivationLayer::new(activation))
+    }
+
+    pub fn relu(self) -> Self {
+        self.activation::<ReLU>()
+    }
+
+    pub fn sigmoid(self) -> Self {
+        self.activation::<Sigmoid>()
+    }
+
+    pub fn softplus(self) -> Self {
+        self.activation::<Softplus>()
+    }
+
+    /// Row-wise softmax, best followed by `CategoricalCrossEntropy`
+    pub fn softmax(self) -> Self {
+        self.layer(Softmax::new())
+    }
+
+    /// Row-wise log softmax, best followed by `NegativeLogLikelihood`
+    pub fn log_softmax(self) -> Self {
+        self.layer(LogSoftmax::new())
+    }
+
+    pub fn cost<C: Cost + 'static>(self) -> Self {
+        self.cost_fn(CostFn::of::<C>())
+    }
+
+    /// Takes a `CostKind` (eg. parsed from a config file) or a `CostFn`.
+    pub fn cost_fn<F: Into<CostFn>>(mut self, cost: F) -> Self {
+        self.cost = cost.into();
+        self
+    }
+
+    pub fn cost_name(&self) -> &str {
+        self.cost.name()
+    }
+
+    /// Puts every layer in training mode, so `forward` (and so training) uses dropout.
+    pub fn train(&mut self) {
+        self.set_training(true);
+    }
+
+    /// Puts every layer in inference mode. `predict` always acts like this.
+    pub fn eval(&mut self) {
+        self.set_training(false);
+    }
+
+    fn set_training(&mut self, training: bool) {
+        for layer in self.layers.iter_mut() {
+            layer.set_training(training);
+        }
+    }
+
+    pub fn layers(&self) -> &[Box<dyn Layer>] {
+        &self.layers
+    }
+
+    /// @param x one case per row
+    pub fn predict(&self, x: &Matrix<f64>) -> Matrix<f64> {
+        self.layers
+            .iter()
+            .fold(x.clone(), |input, layer| layer.predict(&input))
+    }
+
+    /// `predict`, but every layer caches what its backward pass needs.
+    pub fn forward(&mut self, x: &Matrix<f64>) -> Matrix<f64> {
+        self.layers
+            .iter_mut()
+            .fold(x.clone(), |input, layer| layer.forward(&input))
+    }
+
+    // softmax then cross entropy, or log softmax then nll, has cost_wrt_logits = p * sum(y) - y,
+    // which stays finite even when p underflows, unlike going through the cost's prime
+    fn fused_output_grad(&self, pred: &Matrix<f64>, y: &Matrix<f64>) -> Option<Matrix<f64>> {
+        let cost = self.cost.name();
+        let probabilities = match self.layers.last()?.output_kind() {
+            OutputKind::Probabilities if cost == CategoricalCrossEntropy::name() => pred.clone(),
+            OutputKind::LogProbabilities if cost == NegativeLogLikelihood::name() => {
+                pred.apply(f64::exp)
+            }
+            _ => return None,
+        };
+        let mut cost_wrt_logits = probabilities;
+        for i in 0..y.h() {
+            let total: f64 = (0..y.w()).map(|j| y[(i, j)]).sum();
+            for j in 0..y.w() {
+                cost_wrt_logits[(i, j)] = cost_wrt_logits[(i, j)] * total - y[(i, j)];
+            }
+        }
+        Some(cost_wrt_logits)
+    }
+}
+
+impl Trainable for Sequential {
+    fn batch_grad(&mut self, x: &Matrix<f64>, y: &Matrix<f64>) -> (f64, Vec<Matrix<f64>>) {
+        let pred = self.forward(x);
+        let cases = x.h() as f64;
+        let cost = self.cost.calc(&pred, y) / cases;
+
+        let fused = self.fused_output_grad(&pred, y);
+        let skipped = fused.is_some() as usize;
+        let mut cost_wrt_output =
+            fused.unwrap_or_else(|| self.cost.prime(&pred, y)) * (1.0 / cases);
+        let mut grads = Vec::new();
+        // the fused grad is already wrt the input of the (param-less) last layer
+        for layer in self.layers.iter_mut().rev().skip(skipped) {
+            let (cost_wrt_input, mut layer_grads) = layer.backward(&cost_wrt_output);
+            cost_wrt_output = cost_wrt_input;
+            // layers are walked backwards, so their grads are too
+            layer_grads.reverse();
+            grads.append(&mut layer_grads);
+        }
+        grads.reverse();
+        (cost, grads)
+    }
+
+    fn batch_cost(&mut self, x: &Matrix<f64>, y: &Matrix<f64>) -> f64 {
+        self.cost.calc(&self.predict(x), y) / x.h() as f64
+    }
+
+    fn params_mut(&mut self) -> Vec<&mut Matrix<f64>> {
+        self.layers
+            .iter_mut()
+            .flat_map(|layer| layer.params_mut())
+            .collect()
+    }
+
+    fn predict(&self, x: &Matrix<f64>) -> Matrix<f64> {
+        Sequential::predict(self, x)
+    }
+
+    /// Every layer's state, each after its length as a little-endian u64
+    fn state(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for layer in self.layers.iter() {
+            let state = layer.state();
+            bytes.extend_from_slice(&(state.len() as u64).to_le_bytes());
+            bytes.extend_from_slice(&state);
+        }
+        bytes
+    }
+
+    fn load_state(&mut self, mut state: &[u8]) -> Result<(), ModelError> {
+        for layer in self.layers.iter_mut() {
+            let len = read_u64(&mut state)?;
+            if len > state.len() as u64 {
+                return Err(ModelError::format("truncated model state"));
+            }
+            let (layer_state, rest) = state.split_at(len as usize);
+            layer.load_state(layer_state)?;
+            state = rest;
+        }
+        if !state.is_empty() {
+            return Err(ModelError::format("model state is for more layers"));
+        }
+        Ok(())
+    }
+}
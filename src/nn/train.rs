@@ -0,0 +1,609 @@
+use super::callbacks::{BatchEnd, BatchGrad, Callback, EpochEnd};
+use super::checkpoint::{Checkpoint, CheckpointConfig, CheckpointEvery, EpochProgress, RngState};
+use super::optim::{Optimizer, Sgd};
+use super::schedule::{Constant, LrSchedule, Progress};
+use super::{ModelError, TrainError};
+use crate::algebra::{MatLike, Matrix};
+use crate::metrics;
+use rand::{seq::SliceRandom, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use std::borrow::Cow;
+use std::time::Instant;
+
+/// A model `Trainer` can fit. Costs are always averaged over the cases of a batch, ie.
+/// `Reduction::Mean`, whatever the cost function.
+pub trait Trainable {
+    /// @param x, y one case per row
+    /// @return (mean cost over the rows, gradient of that cost for every param in `params_mut`)
+    fn batch_grad(&mut self, x: &Matrix<f64>, y: &Matrix<f64>) -> (f64, Vec<Matrix<f64>>);
+
+    /// @param x, y one case per row
+    /// @return mean cost over the rows
+    fn batch_cost(&mut self, x: &Matrix<f64>, y: &Matrix<f64>) -> f64;
+
+    fn params_mut(&mut self) -> Vec<&mut Matrix<f64>>;
+
+    /// @param x one case per row
+    /// @return one prediction per row, in inference mode
+    fn predict(&self, x: &Matrix<f64>) -> Matrix<f64>;
+
+    /// Whatever training changes besides the params, like the RNGs of dropout layers or the
+    /// running statistics of batch norm, for checkpoints to carry. Empty by default.
+    fn state(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    /// Restores what `state` returned.
+    fn load_state(&mut self, state: &[u8]) -> Result<(), ModelError> {
+        if state.is_empty() {
+            Ok(())
+        } else {
+            Err(ModelError::format("model has no state to load"))
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TrainConfig {
+    pub epochs: usize,
+    pub batch_size: usize,
+    /// The order of the cases is shuffled every epoch if this is set
+    pub shuffle_seed: Option<u64>,
+    pub learning_rate: f64,
+}
+
+impl Default for TrainConfig {
+    fn default() -> Self {
+        Self {
+            epochs: 10,
+            batch_size: 32,
+            shuffle_seed: Some(0),
+            learning_rate: 0.01,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    /// Mean cost of every case seen during each epoch
+    pub loss: Vec<f64>,
+    /// Learning rate of the last step of each epoch
+    pub learning_rate: Vec<f64>,
+    /// `Debug` output of the `LrSchedule` used
+    pub schedule: String,
+    /// Validation score after each epoch, if there was validation data
+    pub validation: Vec<f64>,
+    /// Index (into `loss`) of the epoch with the best validation score
+    pub best_epoch: Option<usize>,
+    /// Whether `EarlyStopping` ended the run before `TrainConfig::epochs`
+    pub stopped_early: bool,
+}
+
+/// What the validation data is scored with after every epoch.
+#[derive(Debug, Clone, Copy)]
+pub enum Metric {
+    /// `Trainable::batch_cost`, lower is better
+    Loss,
+    /// `metrics::accuracy`, higher is better
+    Accuracy,
+    Custom {
+        name: &'static str,
+        /// @param pred, y one case per row
+        measure: fn(&Matrix<f64>, &Matrix<f64>) -> f64,
+        higher_is_better: bool,
+    },
+}
+
+impl Metric {
+    pub fn name(&self) -> &str {
+        match self {
+            Metric::Loss => "loss",
+            Metric::Accuracy => "accuracy",
+            Metric::Custom { name, .. } => name,
+        }
+    }
+
+    pub fn higher_is_better(&self) -> bool {
+        match self {
+            Metric::Loss => false,
+            Metric::Accuracy => true,
+            Metric::Custom {
+                higher_is_better, ..
+            } => *higher_is_better,
+        }
+    }
+
+    /// @param x, y one case per row
+    pub fn measure<M: Trainable>(&self, model: &mut M, x: &Matrix<f64>, y: &Matrix<f64>) -> f64 {
+        match self {
+            Metric::Loss => model.batch_cost(x, y),
+            Metric::Accuracy => metrics::accuracy(&model.predict(x), y),
+            Metric::Custom { measure, .. } => measure(&model.predict(x), y),
+        }
+    }
+
+    /// Whether `score` beats `best` by more than `min_delta`. A NaN score never does, and any
+    /// other beats a best that isn't finite.
+    pub fn improves(&self, score: f64, best: f64, min_delta: f64) -> bool {
+        if score.is_nan() {
+            false
+        } else if !best.is_finite() {
+            true
+        } else if self.higher_is_better() {
+            score > best + min_delta
+        } else {
+            score < best - min_delta
+        }
+    }
+}
+
+/// Ends training once the validation score hasn't improved for `patience` epochs.
+#[derive(Debug, Clone, Copy)]
+pub struct EarlyStopping {
+    pub patience: usize,
+    /// Smallest change in the score that counts as an improvement
+    pub min_delta: f64,
+    /// Put the params and state of the best epoch back into the model when training ends
+    pub restore_best: bool,
+}
+
+impl EarlyStopping {
+    /// Restores the best epoch, and counts any improvement.
+    pub fn new(patience: usize) -> Self {
+        Self {
+            patience,
+            min_delta: 0.0,
+            restore_best: true,
+        }
+    }
+}
+
+/// The best validation score of a `fit` so far, and the model that scored it
+#[derive(Debug, Clone)]
+pub struct BestEpoch {
+    /// Index into `History::loss`
+    pub epoch: usize,
+    pub score: f64,
+    /// Every param of the model after that epoch, in `Trainable::params_mut` order
+    pub params: Vec<Matrix<f64>>,
+    /// `Trainable::state` of the model after that epoch, like batch norm's running statistics
+    pub state: Vec<u8>,
+    /// Epochs since, none of which improved on it
+    pub stale_epochs: usize,
+}
+
+// held out from training to be scored every epoch
+#[derive(Debug, Clone)]
+enum Validation {
+    Given(Matrix<f64>, Matrix<f64>),
+    /// Fraction of the last cases of `fit`'s data
+    Split(f64),
+}
+
+pub struct Trainer {
+    config: TrainConfig,
+    rng: Option<ChaCha12Rng>,
+    optimizer: Box<dyn Optimizer>,
+    schedule: Box<dyn LrSchedule>,
+    step: usize,
+    epoch: usize,
+    checkpoints: Option<CheckpointConfig>,
+    validation: Option<Validation>,
+    metric: Metric,
+    early_stopping: Option<EarlyStopping>,
+    callbacks: Vec<Box<dyn Callback>>,
+    // what the next `fit` picks up from a checkpoint
+    resumed: Option<(Run, Option<EpochProgress>)>,
+}
+
+// the state of one call to `fit` that a checkpoint carries over
+struct Run {
+    // epoch the call started at
+    start: usize,
+    history: History,
+    // of the cases in the current epoch, which the next one shuffles on from
+    order: Vec<usize>,
+    best: Option<BestEpoch>,
+}
+
+impl Trainer {
+    pub fn new(config: TrainConfig) -> Self {
+        assert!(config.batch_size > 0, "batch_size must be positive");
+        Self {
+            rng: config.shuffle_seed.map(ChaCha12Rng::seed_from_u64),
+            config,
+            optimizer: Box::new(Sgd),
+            schedule: Box::new(Constant),
+            step: 0,
+            epoch: 0,
+            checkpoints: None,
+            validation: None,
+            metric: Metric::Loss,
+            early_stopping: None,
+            callbacks: Vec::new(),
+            resumed: None,
+        }
+    }
+
+    /// Replaces the default `Constant` schedule. Steps and epochs are counted across calls to
+    /// `fit`.
+    pub fn schedule<S: LrSchedule + 'static>(mut self, schedule: S) -> Self {
+        self.schedule = Box::new(schedule);
+        self
+    }
+
+    /// Replaces the default `Sgd`.
+    pub fn optimizer<O: Optimizer + 'static>(mut self, optimizer: O) -> Self {
+        self.optimizer = Box::new(optimizer);
+        self
+    }
+
+    pub fn config(&self) -> &TrainConfig {
+        &self.config
+    }
+
+    /// Writes a `Checkpoint` into `config.dir` as often as `config.every` says while fitting.
+    pub fn checkpoints(mut self, config: CheckpointConfig) -> Self {
+        self.checkpoints = Some(config);
+        self
+    }
+
+    /// Scores `metric` on `x` and `y` after every epoch.
+    /// @param x, y one case per row
+    pub fn validation(mut self, x: Matrix<f64>, y: Matrix<f64>) -> Self {
+        assert_eq!(x.h(), y.h(), "x and y have a different number of cases");
+        self.validation = Some(Validation::Given(x, y));
+        self
+    }
+
+    /// Holds the last `fraction` of the cases passed to `fit` out of training, and scores `metric`
+    /// on them after every epoch.
+    pub fn validation_split(mut self, fraction: f64) -> Self {
+        assert!(
+            0.0 < fraction && fraction < 1.0,
+            "validation fraction must be between 0 and 1"
+        );
+        self.validation = Some(Validation::Split(fraction));
+        self
+    }
+
+    /// Replaces the default `Metric::Loss` as the validation score.
+    pub fn metric(mut self, metric: Metric) -> Self {
+        self.metric = metric;
+        self
+    }
+
+    /// Needs `validation` or `validation_split`.
+    pub fn early_stopping(mut self, early_stopping: EarlyStopping) -> Self {
+        self.early_stopping = Some(early_stopping);
+        self
+    }
+
+    /// Adds `callback` to the ones told about every batch and epoch, in the order added. Nothing
+    /// is printed without one, eg. `ProgressLine`.
+    pub fn callback<C: Callback + 'static>(mut self, callback: C) -> Self {
+        self.callbacks.push(Box::new(callback));
+        self
+    }
+
+    pub fn step(&self) -> usize {
+        self.step
+    }
+
+    pub fn epoch(&self) -> usize {
+        self.epoch
+    }
+
+    /// Restores `model`'s params and state, and this trainer's counters, optimizer, schedule and
+    /// shuffling from `checkpoint`. The next `fit` then carries on the interrupted one where it
+    /// was left, and given the same config and data ends exactly as if it had never stopped.
+    pub fn resume<M: Trainable>(
+        &mut self,
+        model: &mut M,
+        checkpoint: Checkpoint,
+    ) -> Result<(), ModelError> {
+        let mut params = model.params_mut();
+        let shapes = |shapes: Vec<(usize, usize)>| format!("{:?}", shapes);
+        let expected: Vec<(usize, usize)> = params.iter().map(|p| (p.w(), p.h())).collect();
+        let found: Vec<(usize, usize)> = checkpoint.params.iter().map(|p| (p.w(), p.h())).collect();
+        if expected != found {
+            return Err(ModelError::Mismatch {
+                field: "param shapes",
+                expected: shapes(expected),
+                found: shapes(found),
+            });
+        }
+        if let Some(best) = &checkpoint.best {
+            let found: Vec<(usize, usize)> = best.params.iter().map(|p| (p.w(), p.h())).collect();
+            if expected != found {
+                return Err(ModelError::Mismatch {
+                    field: "best param shapes",
+                    expected: shapes(expected),
+                    found: shapes(found),
+                });
+            }
+        }
+        for (param, saved) in params.iter_mut().zip(checkpoint.params) {
+            **param = saved;
+        }
+        // the best epoch's state is only loaded when training ends, so check it loads now
+        if let Some(best) = &checkpoint.best {
+            model.load_state(&best.state)?;
+        }
+        model.load_state(&checkpoint.model)?;
+        self.step = checkpoint.step;
+        self.epoch = checkpoint.epoch;
+        self.rng = checkpoint.rng.map(|rng| rng.to_rng());
+        self.optimizer.set_state(checkpoint.optimizer);
+        self.schedule.set_state(&checkpoint.schedule);
+        let run = Run {
+            start: checkpoint.run_start,
+            history: checkpoint.history,
+            order: checkpoint.order,
+            best: checkpoint.best,
+        };
+        self.resumed = Some((run, checkpoint.progress));
+        Ok(())
+    }
+
+    /// `resume` from the checkpoint file at `path`
+    pub fn resume_from<M: Trainable, P: AsRef<std::path::Path>>(
+        &mut self,
+        model: &mut M,
+        path: P,
+    ) -> Result<(), ModelError> {
+        self.resume(model, Checkpoint::load(path)?)
+    }
+
+    /// Trains `model` from its current params for `config.epochs` passes over `x`, or what's left
+    /// of them after `resume`, or until `early_stopping` ends it.
+    /// @param x, y one case per row
+    /// @panics if a checkpoint can't be written or a callback aborts, see `try_fit`
+    pub fn fit<M: Trainable>(
+        &mut self,
+        model: &mut M,
+        x: &Matrix<f64>,
+        y: &Matrix<f64>,
+    ) -> History {
+        self.try_fit(model, x, y)
+            .unwrap_or_else(|error| panic!("training failed: {}", error))
+    }
+
+    /// `fit`, stopping at the first checkpoint that can't be written or callback that aborts.
+    /// Without either this never fails.
+    pub fn try_fit<M: Trainable>(
+        &mut self,
+        model: &mut M,
+        x: &Matrix<f64>,
+        y: &Matrix<f64>,
+    ) -> Result<History, TrainError> {
+        assert_eq!(x.h(), y.h(), "x and y have a different number of cases");
+        assert!(
+            self.early_stopping.is_none() || self.validation.is_some(),
+            "early stopping needs validation data"
+        );
+        let (x, y, validation) = match &self.validation {
+            Some(Validation::Split(fraction)) => {
+                assert!(x.h() >= 2, "too few cases to hold some out for validation");
+                let held_out = ((x.h() as f64 * fraction).round() as usize).clamp(1, x.h() - 1);
+                let train: Vec<usize> = (0..x.h() - held_out).collect();
+                let test: Vec<usize> = (x.h() - held_out..x.h()).collect();
+                let validation = (x.select_rows(&test), y.select_rows(&test));
+                let (x, y) = (x.select_rows(&train), y.select_rows(&train));
+                (Cow::Owned(x), Cow::Owned(y), Some(validation))
+            }
+            Some(Validation::Given(x_val, y_val)) => (
+                Cow::Borrowed(x),
+                Cow::Borrowed(y),
+                Some((x_val.clone(), y_val.clone())),
+            ),
+            None => (Cow::Borrowed(x), Cow::Borrowed(y), None),
+        };
+        let (mut run, mut resumed_progress) = match self.resumed.take() {
+            Some((run, progress)) => {
+                assert_eq!(run.order.len(), x.h(), "resumed with a different dataset");
+                (run, progress)
+            }
+            None => {
+                let history = History {
+                    schedule: format!("{:?}", self.schedule),
+                    ..History::default()
+                };
+                let run = Run {
+                    start: self.epoch,
+                    history,
+                    order: (0..x.h()).collect(),
+                    best: None,
+                };
+                (run, None)
+            }
+        };
+        let steps_per_epoch = x.h().div_ceil(self.config.batch_size);
+        while self.epoch < run.start + self.config.epochs {
+            if self.should_stop(&run) {
+                run.history.stopped_early = true;
+                break;
+            }
+            let started = Instant::now();
+            let epoch = self.epoch;
+            self.notify(|callback| callback.on_epoch_begin(epoch))?;
+            let mut progress = match resumed_progress.take() {
+                Some(progress) => progress,
+                None => {
+                    if let Some(rng) = self.rng.as_mut() {
+                        run.order.shuffle(rng);
+                    }
+                    EpochProgress {
+                        next_batch: 0,
+                        total_loss: 0.0,
+                        learning_rate: self.config.learning_rate,
+                    }
+                }
+            };
+            while progress.next_batch < steps_per_epoch {
+                let start = progress.next_batch * self.config.batch_size;
+                let end = (start + self.config.batch_size).min(run.order.len());
+                let batch = &run.order[start..end];
+                let index = progress.next_batch;
+                self.notify(|callback| callback.on_batch_begin(epoch, index))?;
+                let (loss, grads) = model.batch_grad(&x.select_rows(batch), &y.select_rows(batch));
+                let batch_grad = BatchGrad {
+                    epoch,
+                    batch: index,
+                    step: self.step,
+                    loss,
+                    grads: &grads,
+                };
+                self.notify(|callback| callback.on_batch_grad(&batch_grad))?;
+                progress.total_loss += loss * batch.len() as f64;
+                let at = Progress {
+                    step: self.step,
+                    epoch: self.epoch,
+                    steps_per_epoch,
+                };
+                progress.learning_rate = self.schedule.learning_rate(self.config.learning_rate, at);
+                self.optimizer
+                    .step(&mut model.params_mut(), &grads, progress.learning_rate);
+                self.step += 1;
+                progress.next_batch += 1;
+                let batch_end = BatchEnd {
+                    epoch,
+                    batch: index,
+                    batches: steps_per_epoch,
+                    step: self.step,
+                    cases: batch.len(),
+                    loss,
+                    learning_rate: progress.learning_rate,
+                };
+                self.notify(|callback| callback.on_batch_end(&batch_end))?;
+                if let Some(CheckpointEvery::Steps(n)) = self.checkpoint_every() {
+                    if self.step.is_multiple_of(n) {
+                        self.write_checkpoint(model, &run, Some(&progress))?;
+                    }
+                }
+            }
+            let epoch_loss = progress.total_loss / x.h() as f64;
+            self.schedule.end_epoch(epoch_loss);
+            self.epoch += 1;
+            run.history.loss.push(epoch_loss);
+            run.history.learning_rate.push(progress.learning_rate);
+            if let Some((x_val, y_val)) = &validation {
+                let score = self.metric.measure(model, x_val, y_val);
+                run.history.validation.push(score);
+                self.update_best(model, &mut run, score);
+            }
+            // a copy, as callbacks borrow self mutably
+            let metric = self.metric;
+            let epoch_end = EpochEnd {
+                epoch,
+                epochs: run.start + self.config.epochs,
+                step: self.step,
+                cases: x.h(),
+                loss: epoch_loss,
+                learning_rate: progress.learning_rate,
+                validation: (validation.is_some())
+                    .then(|| (metric.name(), *run.history.validation.last().unwrap())),
+                seconds: started.elapsed().as_secs_f64(),
+            };
+            self.notify(|callback| callback.on_epoch_end(&epoch_end))?;
+            if let Some(CheckpointEvery::Epochs(n)) = self.checkpoint_every() {
+                if self.epoch.is_multiple_of(n) {
+                    self.write_checkpoint(model, &run, None)?;
+                }
+            }
+        }
+        if self.should_stop(&run) {
+            run.history.stopped_early = true;
+        }
+        if let Some(best) = run.best {
+            run.history.best_epoch = Some(best.epoch);
+            if self
+                .early_stopping
+                .is_some_and(|stopping| stopping.restore_best)
+            {
+                for (param, best) in model.params_mut().into_iter().zip(best.params) {
+                    *param = best;
+                }
+                model
+                    .load_state(&best.state)
+                    .expect("the model's own state, or one resume already loaded");
+            }
+        }
+        Ok(run.history)
+    }
+
+    // calls hook on every callback, stopping at the first to abort
+    fn notify<F>(&mut self, mut hook: F) -> Result<(), TrainError>
+    where
+        F: FnMut(&mut dyn Callback) -> Result<(), String>,
+    {
+        for callback in self.callbacks.iter_mut() {
+            hook(callback.as_mut()).map_err(|reason| TrainError::Aborted {
+                epoch: self.epoch,
+                step: self.step,
+                reason,
+            })?;
+        }
+        Ok(())
+    }
+
+    fn update_best<M: Trainable>(&self, model: &mut M, run: &mut Run, score: f64) {
+        let min_delta = self
+            .early_stopping
+            .map_or(0.0, |stopping| stopping.min_delta);
+        let epoch = run.history.loss.len() - 1;
+        match run.best.as_mut() {
+            Some(best) if !self.metric.improves(score, best.score, min_delta) => {
+                best.stale_epochs += 1;
+            }
+            // nothing to compare later scores with
+            None if score.is_nan() => {}
+            _ => {
+                run.best = Some(BestEpoch {
+                    epoch,
+                    score,
+                    params: model.params_mut().into_iter().map(|p| p.clone()).collect(),
+                    state: model.state(),
+                    stale_epochs: 0,
+                })
+            }
+        }
+    }
+
+    fn should_stop(&self, run: &Run) -> bool {
+        match (self.early_stopping, run.best.as_ref()) {
+            (Some(stopping), Some(best)) => best.stale_epochs >= stopping.patience,
+            _ => false,
+        }
+    }
+
+    fn checkpoint_every(&self) -> Option<CheckpointEvery> {
+        self.checkpoints.as_ref().map(|config| config.every)
+    }
+
+    fn write_checkpoint<M: Trainable>(
+        &self,
+        model: &mut M,
+        run: &Run,
+        progress: Option<&EpochProgress>,
+    ) -> Result<(), ModelError> {
+        let Some(config) = self.checkpoints.as_ref() else {
+            return Ok(());
+        };
+        let checkpoint = Checkpoint {
+            step: self.step,
+            epoch: self.epoch,
+            run_start: run.start,
+            rng: self.rng.as_ref().map(RngState::of),
+            order: run.order.clone(),
+            progress: progress.cloned(),
+            history: run.history.clone(),
+            best: run.best.clone(),
+            params: model.params_mut().into_iter().map(|p| p.clone()).collect(),
+            model: model.state(),
+            optimizer: self.optimizer.state(),
+            schedule: self.schedule.state(),
+        };
+        checkpoint.save_rotating(config)
+    }
+}
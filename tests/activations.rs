@@ -0,0 +1,46 @@
+#[cfg(test)]
+mod tests {
+    use ml::nn::activations::*;
+    const ERROR_MARGIN: f64 = 1e-9;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() <= ERROR_MARGIN, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn known_values() {
+        assert_close(normal_cdf(0.0), 0.5);
+        assert_close(normal_cdf(1.0), 0.841_344_746_068_542_9);
+        assert_close(normal_cdf(-5.0), 2.866_515_718_791_939e-7);
+        assert!((normal_cdf(-10.0) / 7.619_853_024_160_527e-24 - 1.0).abs() < 1e-9);
+        assert_close(GELU::calc(1.0), 0.841_344_746_068_542_9);
+        assert!((GELUTanh::calc(1.0) - GELU::calc(1.0)).abs() < 1e-3);
+        assert_close(SELU::calc(-1.0), -1.111_330_737_812_562_2);
+        assert_close(LeakyReLU::calc(-2.0), -0.02);
+        assert_close(ELU::calc(-1.0), (-1.0_f64).exp() - 1.0);
+        assert_close(Mish::calc(1.0), 0.865_098_388_287_191_3);
+        assert_close(Swish::calc(2.0), 2.0 * Sigmoid::calc(2.0));
+        assert_close(HardSigmoid::calc(1.5), 0.75);
+        assert_close(HardTanh::calc(-3.0), -1.0);
+        assert_close(Softsign::calc(3.0), 0.75);
+        assert_close(Identity::calc(-2.5), -2.5);
+    }
+
+    #[test]
+    fn stable_at_extremes() {
+        for kind in ActivationKind::ALL {
+            for x in [-1000.0, -40.0, 40.0, 1000.0] {
+                assert!(kind.calc(x).is_finite(), "{}({})", kind, x);
+                assert!(kind.prime(x).is_finite(), "{}'({})", kind, x);
+            }
+        }
+        assert_eq!(Sigmoid::calc(-1000.0), 0.0);
+        assert_eq!(Sigmoid::calc(1000.0), 1.0);
+        assert_eq!(Softplus::calc(1000.0), 1000.0);
+        assert_eq!(Softplus::calc(-1000.0), 0.0);
+        // no cutoff, so small values keep their precision
+        assert!((Softplus::calc(-500.0) / (-500.0_f64).exp() - 1.0).abs() < 1e-12);
+        assert_eq!(Mish::calc(-1000.0), -0.0);
+        assert_close(GELU::calc(-40.0), 0.0);
+    }
+}
@@ -0,0 +1,60 @@
+#[cfg(test)]
+mod tests {
+    use ml::algebra::{MatLike, Matrix};
+    use ml::nn::autograd::Tape;
+    use ml::nn::{activations::*, cost::SumSquared, feedforward::FFNet};
+    const ERROR_MARGIN: f64 = 0.00001;
+
+    fn assert_close(a: &Matrix<f64>, b: &Matrix<f64>) {
+        assert_eq!(a.w(), b.w(), "widths do not match");
+        assert_eq!(a.h(), b.h(), "heights do not match");
+        for (x, y) in a.iter().zip(b.iter()) {
+            assert!((x - y).abs() <= ERROR_MARGIN, "{} != {}", x, y);
+        }
+    }
+
+    macro_rules! test_matches_backprop {
+        ($type: ty) => {
+            let mut net = FFNet::<$type, SumSquared>::new(vec![3, 4, 2]);
+            net.randomize_params();
+            let input = Matrix::new(vec![0.5, -1.2, 2.0], 1, 3);
+            let output = Matrix::new(vec![1.0, 0.0], 2, 1);
+            let (auto_weight, auto_bias) = net.autograd_case_grad(&input, &output);
+            let (weight, bias) = net.single_case_grad(&input, &output);
+            for i in 0..weight.len() {
+                assert_close(&auto_weight[i], &weight[i]);
+                assert_close(&auto_bias[i], &bias[i]);
+            }
+        };
+    }
+
+    #[test]
+    fn matches_backprop() {
+        test_matches_backprop!(ReLU);
+        test_matches_backprop!(Softplus);
+        test_matches_backprop!(Sigmoid);
+    }
+
+    #[test]
+    fn basic_ops() {
+        let tape = Tape::new();
+        let a = tape.var(Matrix::new(vec![1.0, 2.0, 3.0, 4.0], 2, 2));
+        let b = tape.var(Matrix::new(vec![-1.0, 0.5], 1, 2));
+        // sum(a * b) = sum over i, j of a_ij * b_j, so d/da_ij = b_j and d/db_j = sum over i of a_ij
+        let grads = (a * b).sum().backward();
+        assert_close(
+            grads.wrt(a).unwrap(),
+            &Matrix::new(vec![-1.0, 0.5, -1.0, 0.5], 2, 2),
+        );
+        assert_close(grads.wrt(b).unwrap(), &Matrix::new(vec![4.0, 6.0], 1, 2));
+
+        // a is used twice, so both contributions must be accumulated
+        let grads = (a.mul_element_wise(a) - a.transpose() * 3.0)
+            .mean()
+            .backward();
+        assert_close(
+            grads.wrt(a).unwrap(),
+            &Matrix::new(vec![-0.25, 0.25, 0.75, 1.25], 2, 2),
+        );
+    }
+}
@@ -0,0 +1,83 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::batch;
+    use ml::algebra::{MatLike, Matrix};
+    use ml::nn::gradcheck::check_model;
+    use ml::nn::init::Initializer::XavierUniform;
+    use ml::nn::layers::{BatchNorm, Layer};
+    use ml::nn::sequential::Sequential;
+    use ml::nn::train::Trainable;
+    use rand::{rngs::StdRng, SeedableRng};
+    const ERROR_MARGIN: f64 = 0.00001;
+
+    fn column_statistics(m: &Matrix<f64>, j: usize) -> (f64, f64) {
+        let cases = m.h() as f64;
+        let mean = (0..m.h()).map(|i| m[(i, j)]).sum::<f64>() / cases;
+        let variance = (0..m.h())
+            .map(|i| (m[(i, j)] - mean) * (m[(i, j)] - mean))
+            .sum::<f64>()
+            / cases;
+        (mean, variance)
+    }
+
+    #[test]
+    fn normalises() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let x = Matrix::random_normal_with(3.0, 2.0, 4, 64, &mut rng);
+        let mut layer = BatchNorm::new(4);
+        let output = layer.forward(&x);
+        for j in 0..4 {
+            let (mean, variance) = column_statistics(&output, j);
+            assert!(mean.abs() <= ERROR_MARGIN, "{}", mean);
+            assert!((variance - 1.0).abs() < 1e-3, "{}", variance);
+        }
+
+        // the running statistics settle on the data's
+        for _ in 0..200 {
+            layer.forward(&Matrix::random_normal_with(3.0, 2.0, 4, 64, &mut rng));
+        }
+        for j in 0..4 {
+            assert!((layer.running_mean()[(j, 0)] - 3.0).abs() < 0.2);
+            assert!((layer.running_variance()[(j, 0)] - 4.0).abs() < 0.5);
+        }
+
+        // and are what eval mode uses
+        layer.set_training(false);
+        let predicted = layer.predict(&x);
+        assert!(layer
+            .forward(&x)
+            .iter()
+            .zip(predicted.iter())
+            .all(|(a, b)| (a - b).abs() <= ERROR_MARGIN));
+        let (mean, _) = column_statistics(&predicted, 0);
+        assert!(mean.abs() < 0.3, "{}", mean);
+    }
+
+    #[test]
+    fn gradients() {
+        let (x, y) = batch();
+        let mut model = Sequential::new()
+            .dense_with(3, 4, &XavierUniform, 1)
+            .batch_norm(4)
+            .sigmoid()
+            .dense_with(4, 2, &XavierUniform, 2);
+        // gamma and beta away from 1 and 0
+        *model.params_mut()[2] = Matrix::new(vec![0.5, 1.5, -0.8, 1.2], 1, 4);
+        *model.params_mut()[3] = Matrix::new(vec![0.1, -0.2, 0.3, 0.0], 1, 4);
+        // batch norm cancels out the bias before it, so that gradient is 0, and only rounding
+        // error is left for a relative error to compare
+        let (_, grads) = model.batch_grad(&x, &y);
+        assert!(grads[1].iter().all(|g| g.abs() < 1e-10), "{:?}", grads[1]);
+        let errors = check_model(&mut model, &x, &y, 1e-6);
+        for (param, error) in errors.into_iter().enumerate() {
+            assert!(param == 1 || error < 1e-5, "param {}: {}", param, error);
+        }
+
+        model.eval();
+        for error in check_model(&mut model, &x, &y, 1e-6) {
+            assert!(error < 1e-5, "{}", error);
+        }
+    }
+}
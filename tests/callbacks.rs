@@ -0,0 +1,193 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{dataset, net};
+    use ml::algebra::MatLike;
+    use ml::nn::callbacks::*;
+    use ml::nn::json::Json;
+    use ml::nn::train::{TrainConfig, Trainer};
+    use ml::nn::TrainError;
+    use std::cell::RefCell;
+    use std::io::Write;
+    use std::rc::Rc;
+
+    // 20 cases in batches of 8 makes 3 batches an epoch
+    fn trainer() -> Trainer {
+        Trainer::new(TrainConfig {
+            epochs: 2,
+            batch_size: 8,
+            shuffle_seed: Some(1),
+            learning_rate: 0.5,
+        })
+    }
+
+    // a writer the test can still read after handing it to a callback
+    #[derive(Clone, Default)]
+    struct Shared(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Shared {
+        fn text(&self) -> String {
+            String::from_utf8(self.0.borrow().clone()).unwrap()
+        }
+    }
+
+    struct Recorder {
+        events: Rc<RefCell<Vec<String>>>,
+        batches: Rc<RefCell<Vec<BatchEnd>>>,
+        losses: Rc<RefCell<Vec<f64>>>,
+    }
+
+    impl Callback for Recorder {
+        fn on_epoch_begin(&mut self, epoch: usize) -> Result<(), String> {
+            self.events.borrow_mut().push(format!("epoch {}", epoch));
+            Ok(())
+        }
+
+        fn on_batch_begin(&mut self, epoch: usize, batch: usize) -> Result<(), String> {
+            self.events
+                .borrow_mut()
+                .push(format!("batch {} {}", epoch, batch));
+            Ok(())
+        }
+
+        fn on_batch_end(&mut self, batch: &BatchEnd) -> Result<(), String> {
+            self.events.borrow_mut().push("batch end".to_string());
+            self.batches.borrow_mut().push(*batch);
+            Ok(())
+        }
+
+        fn on_epoch_end(&mut self, epoch: &EpochEnd) -> Result<(), String> {
+            self.events.borrow_mut().push("epoch end".to_string());
+            self.losses.borrow_mut().push(epoch.loss);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn hooks() {
+        let (x, y) = dataset();
+        let recorder = Recorder {
+            events: Rc::default(),
+            batches: Rc::default(),
+            losses: Rc::default(),
+        };
+        let (events, batches, losses) = (
+            recorder.events.clone(),
+            recorder.batches.clone(),
+            recorder.losses.clone(),
+        );
+        let history = trainer().callback(recorder).fit(&mut net(3), &x, &y);
+
+        let mut expected = Vec::new();
+        for epoch in 0..2 {
+            expected.push(format!("epoch {}", epoch));
+            for batch in 0..3 {
+                expected.push(format!("batch {} {}", epoch, batch));
+                expected.push("batch end".to_string());
+            }
+            expected.push("epoch end".to_string());
+        }
+        assert_eq!(*events.borrow(), expected);
+
+        let batches = batches.borrow();
+        let cases: Vec<usize> = batches.iter().map(|batch| batch.cases).collect();
+        assert_eq!(cases, vec![8, 8, 4, 8, 8, 4]);
+        let steps: Vec<usize> = batches.iter().map(|batch| batch.step).collect();
+        assert_eq!(steps, (1..=6).collect::<Vec<_>>());
+        assert!(batches.iter().all(|batch| batch.batches == 3));
+        assert_eq!(*losses.borrow(), history.loss);
+    }
+
+    #[test]
+    fn nan_guard_aborts() {
+        let (mut x, y) = dataset();
+        x[(13, 1)] = f64::NAN;
+        let mut trainer = trainer().callback(NanGuard);
+        let mut aborted = net(3);
+        match trainer.try_fit(&mut aborted, &x, &y) {
+            Err(TrainError::Aborted {
+                epoch: 0, reason, ..
+            }) => assert!(reason.contains("NaN"), "{}", reason),
+            other => panic!("expected an abort, got {:?}", other.map(|h| h.loss)),
+        }
+        // the case with the NaN is in one batch of the first epoch, which never took its step
+        assert!(trainer.step() < 3);
+        assert!(aborted
+            .params_mut()
+            .into_iter()
+            .all(|param| param.iter().all(|p| p.is_finite())));
+
+        let history = Trainer::new(TrainConfig::default()).callback(NanGuard).fit(
+            &mut net(3),
+            &dataset().0,
+            &dataset().1,
+        );
+        assert_eq!(history.loss.len(), 10);
+    }
+
+    #[test]
+    fn logs_metrics() {
+        let (x, y) = dataset();
+        let dir = std::env::temp_dir();
+        let csv = dir.join(format!("ml-callbacks-{}.csv", std::process::id()));
+        let jsonl = dir.join(format!("ml-callbacks-{}.jsonl", std::process::id()));
+        let history = trainer()
+            .validation(x.clone(), y.clone())
+            .callback(MetricsLogger::create(&csv, LogFormat::Csv).unwrap())
+            .callback(MetricsLogger::create(&jsonl, LogFormat::JsonLines).unwrap())
+            .fit(&mut net(3), &x, &y);
+
+        let text = std::fs::read_to_string(&csv).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "epoch,step,loss,learning_rate,validation,seconds");
+        assert_eq!(lines.len(), 3);
+        let row: Vec<&str> = lines[2].split(',').collect();
+        assert_eq!(row[..2], ["1", "6"]);
+        assert_eq!(row[2].parse::<f64>().unwrap(), history.loss[1]);
+        assert_eq!(row[4].parse::<f64>().unwrap(), history.validation[1]);
+
+        let text = std::fs::read_to_string(&jsonl).unwrap();
+        let rows: Vec<Json> = text.lines().map(|l| Json::parse(l).unwrap()).collect();
+        assert_eq!(rows.len(), 2);
+        for (epoch, row) in rows.iter().enumerate() {
+            assert_eq!(row.get("epoch").unwrap().as_usize(), Some(epoch));
+            assert_eq!(row.get("loss").unwrap().as_f64(), Some(history.loss[epoch]));
+            assert_eq!(row.get("metric").unwrap().as_str(), Some("loss"));
+        }
+        std::fs::remove_file(csv).unwrap();
+        std::fs::remove_file(jsonl).unwrap();
+    }
+
+    #[test]
+    fn progress_lines() {
+        let (x, y) = dataset();
+        let out = Shared::default();
+        trainer()
+            .validation_split(0.2)
+            .callback(ProgressLine::new(out.clone()).every_batches(2))
+            .fit(&mut net(3), &x, &y);
+        // 16 cases left to train on, in 2 batches an epoch
+        let text = out.text();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 4, "{}", text);
+        assert!(
+            lines[0].starts_with("epoch 1  batch 2/2  loss "),
+            "{}",
+            text
+        );
+        assert!(lines[1].starts_with("epoch 1/2  loss "), "{}", text);
+        assert!(lines[1].contains("val_loss "), "{}", text);
+        assert!(lines[3].starts_with("epoch 2/2"), "{}", text);
+    }
+}
@@ -0,0 +1,211 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{dataset, net};
+    use ml::algebra::{MatLike, Matrix};
+    use ml::nn::checkpoint::{Checkpoint, CheckpointConfig, CheckpointEvery};
+    use ml::nn::optim::Adam;
+    use ml::nn::schedule::ReduceOnPlateau;
+    use ml::nn::sequential::Sequential;
+    use ml::nn::train::{History, TrainConfig, Trainable, Trainer};
+    use ml::nn::{activations::*, cost::SumSquared, feedforward::FFNet, ModelError};
+    use std::path::PathBuf;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("ml-checkpoint-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    // 20 cases in batches of 3 makes 7 steps an epoch, the last one short
+    fn trainer() -> Trainer {
+        Trainer::new(TrainConfig {
+            epochs: 6,
+            batch_size: 3,
+            shuffle_seed: Some(3),
+            learning_rate: 0.05,
+        })
+        .optimizer(Adam::default())
+        .schedule(ReduceOnPlateau::new(0.5, 1))
+    }
+
+    fn params(net: &mut FFNet<Sigmoid, SumSquared>) -> Vec<Vec<u64>> {
+        net.params_mut()
+            .into_iter()
+            .map(|p| p.iter().map(|x| x.to_bits()).collect())
+            .collect()
+    }
+
+    fn assert_same_history(a: &History, b: &History) {
+        let bits = |xs: &[f64]| xs.iter().map(|x| x.to_bits()).collect::<Vec<_>>();
+        assert_eq!(bits(&a.loss), bits(&b.loss));
+        assert_eq!(bits(&a.learning_rate), bits(&b.learning_rate));
+        assert_eq!(a.schedule, b.schedule);
+    }
+
+    fn uninterrupted() -> (Vec<Vec<u64>>, History) {
+        let (x, y) = dataset();
+        let mut net = net(1);
+        let history = trainer().fit(&mut net, &x, &y);
+        (params(&mut net), history)
+    }
+
+    fn resumed(path: &PathBuf) -> (Vec<Vec<u64>>, History) {
+        let (x, y) = dataset();
+        // different initial params, which the checkpoint must overwrite
+        let mut net = net(2);
+        let mut trainer = trainer();
+        trainer.resume_from(&mut net, path).unwrap();
+        let history = trainer.fit(&mut net, &x, &y);
+        (params(&mut net), history)
+    }
+
+    #[test]
+    fn resumes_exactly() {
+        let (x, y) = dataset();
+        let (expected_params, expected_history) = uninterrupted();
+        for (name, every) in [
+            ("steps", CheckpointEvery::Steps(5)),
+            ("epochs", CheckpointEvery::Epochs(2)),
+        ] {
+            let dir = temp_dir(name);
+            let mut net = net(1);
+            let history = trainer()
+                .checkpoints(CheckpointConfig::new(&dir, every).keep(100))
+                .fit(&mut net, &x, &y);
+            // checkpointing doesn't change the run
+            assert_eq!(params(&mut net), expected_params);
+            assert_same_history(&history, &expected_history);
+
+            let checkpoints = Checkpoint::list(&dir).unwrap();
+            assert!(checkpoints.len() >= 3, "{:?}", checkpoints);
+            for path in checkpoints.iter() {
+                let (params, history) = resumed(path);
+                assert_eq!(params, expected_params, "resumed from {:?}", path);
+                assert_same_history(&history, &expected_history);
+            }
+            std::fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+
+    // dropout masks and batch norm's running statistics are both state outside the params
+    fn stateful(seed: u64, dropout_seed: u64) -> Sequential {
+        Sequential::with_seed(seed)
+            .dense(2, 4)
+            .batch_norm(4)
+            .sigmoid()
+            .dropout(0.3, dropout_seed)
+            .dense(4, 1)
+            .sigmoid()
+    }
+
+    fn bits(model: &mut Sequential, x: &Matrix<f64>) -> (Vec<Vec<u64>>, Vec<u64>) {
+        let params = model
+            .params_mut()
+            .into_iter()
+            .map(|p| p.iter().map(|x| x.to_bits()).collect())
+            .collect();
+        let pred = model.predict(x).iter().map(|x| x.to_bits()).collect();
+        (params, pred)
+    }
+
+    #[test]
+    fn resumes_model_state() {
+        let (x, y) = dataset();
+        let dir = temp_dir("model_state");
+        let mut model = stateful(1, 5);
+        let history = trainer()
+            .checkpoints(CheckpointConfig::new(&dir, CheckpointEvery::Steps(5)).keep(100))
+            .fit(&mut model, &x, &y);
+        let expected = bits(&mut model, &x);
+
+        for path in Checkpoint::list(&dir).unwrap() {
+            let mut model = stateful(2, 6);
+            let mut trainer = trainer();
+            trainer.resume_from(&mut model, &path).unwrap();
+            let resumed = trainer.fit(&mut model, &x, &y);
+            assert!(bits(&mut model, &x) == expected, "resumed from {:?}", path);
+            assert_same_history(&resumed, &history);
+        }
+
+        // nor can one with the same params but no dropout
+        let latest = Checkpoint::latest(&dir).unwrap().unwrap();
+        let mut plain = Sequential::new()
+            .dense(2, 4)
+            .batch_norm(4)
+            .sigmoid()
+            .dense(4, 1)
+            .sigmoid();
+        let error = trainer().resume_from(&mut plain, &latest).unwrap_err();
+        assert!(matches!(error, ModelError::Format(_)), "{}", error);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn mid_epoch_state() {
+        let (x, y) = dataset();
+        let dir = temp_dir("mid_epoch");
+        let mut net = net(1);
+        let mut trainer = trainer()
+            .checkpoints(CheckpointConfig::new(&dir, CheckpointEvery::Steps(10)).keep(100));
+        trainer.fit(&mut net, &x, &y);
+        assert_eq!((trainer.step(), trainer.epoch()), (42, 6));
+
+        let checkpoint = Checkpoint::load(dir.join(Checkpoint::file_name(10))).unwrap();
+        assert_eq!((checkpoint.step, checkpoint.epoch), (10, 1));
+        let progress = checkpoint.progress.unwrap();
+        assert_eq!(progress.next_batch, 3);
+        let mut order = checkpoint.order.clone();
+        order.sort();
+        assert_eq!(order, (0..20).collect::<Vec<_>>());
+        assert_eq!(checkpoint.history.loss.len(), 1);
+        assert!(checkpoint.rng.is_some());
+        assert_eq!(checkpoint.optimizer.steps, 10);
+        assert_eq!(checkpoint.optimizer.buffers.len(), 2);
+        assert_eq!(checkpoint.params.len(), 4);
+        assert_eq!(checkpoint.schedule.len(), 3);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rotates() {
+        let (x, y) = dataset();
+        let dir = temp_dir("rotates");
+        let mut net = net(1);
+        trainer()
+            .checkpoints(CheckpointConfig::new(&dir, CheckpointEvery::Steps(4)).keep(2))
+            .fit(&mut net, &x, &y);
+        let names: Vec<PathBuf> = [36, 40]
+            .into_iter()
+            .map(|step| dir.join(Checkpoint::file_name(step)))
+            .collect();
+        assert_eq!(Checkpoint::list(&dir).unwrap(), names);
+        assert_eq!(Checkpoint::latest(&dir).unwrap(), Some(names[1].clone()));
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(Checkpoint::latest(&dir).unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_other_models() {
+        let (x, y) = dataset();
+        let dir = temp_dir("rejects");
+        let mut net = net(1);
+        trainer()
+            .checkpoints(CheckpointConfig::new(&dir, CheckpointEvery::Epochs(3)))
+            .fit(&mut net, &x, &y);
+        let latest = Checkpoint::latest(&dir).unwrap().unwrap();
+
+        let mut other = FFNet::<Sigmoid, SumSquared>::with_seed(vec![2, 4, 1], 1);
+        let error = trainer().resume_from(&mut other, &latest).unwrap_err();
+        assert!(matches!(error, ModelError::Mismatch { .. }), "{}", error);
+
+        std::fs::write(&latest, b"MLCK").unwrap();
+        let error = trainer().resume_from(&mut net, &latest).unwrap_err();
+        assert!(matches!(error, ModelError::Io(_)), "{}", error);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,50 @@
+// Fixtures shared by the integration tests. Every test crate declares `mod common;` and uses only
+// some of them.
+#![allow(dead_code)]
+
+use ml::algebra::Matrix;
+use ml::nn::activations::{Activation, Sigmoid};
+use ml::nn::cost::SumSquared;
+use ml::nn::feedforward::FFNet;
+
+/// 20 cases of y = 0.5 + 0.3 x0 - 0.2 x1, squashed into (0, 1)
+pub fn dataset() -> (Matrix<f64>, Matrix<f64>) {
+    let x: Vec<f64> = (0..40).map(|i| ((i * 7 % 11) as f64 - 5.0) / 5.0).collect();
+    let y = x
+        .chunks(2)
+        .map(|case| Sigmoid::calc(0.5 + 0.3 * case[0] - 0.2 * case[1]))
+        .collect();
+    (Matrix::new(x, 2, 20), Matrix::new(y, 1, 20))
+}
+
+/// A net to fit `dataset` with
+pub fn net(seed: u64) -> FFNet<Sigmoid, SumSquared> {
+    FFNet::with_seed(vec![2, 3, 1], seed)
+}
+
+/// 4 cases of 3 features, for checking layers on
+pub fn inputs() -> Matrix<f64> {
+    Matrix::new(
+        vec![
+            0.5, -1.0, 2.0, 0.0, 0.3, -0.7, 1.5, 1.5, -2.5, 0.1, 0.2, 0.3,
+        ],
+        3,
+        4,
+    )
+}
+
+/// `inputs`, with 2 targets in (0, 1) for every case
+pub fn batch() -> (Matrix<f64>, Matrix<f64>) {
+    let y = Matrix::new(vec![0.1, 0.9, 0.4, 0.6, 0.8, 0.2, 0.5, 0.5], 2, 4);
+    (inputs(), y)
+}
+
+/// `inputs`, with a distribution over 3 classes for every case
+pub fn classes() -> (Matrix<f64>, Matrix<f64>) {
+    let y = Matrix::new(
+        vec![1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.2, 0.3, 0.5],
+        3,
+        4,
+    );
+    (inputs(), y)
+}
@@ -0,0 +1,203 @@
+#[cfg(test)]
+mod tests {
+    use ml::algebra::{MatLike, Matrix};
+    use ml::nn::gradcheck::check_model;
+    use ml::nn::init::Initializer::{self, XavierUniform};
+    use ml::nn::layers::{AvgPool2D, Conv2D, Flatten, Layer, MaxPool2D};
+    use ml::nn::sequential::Sequential;
+    use rand::{rngs::StdRng, SeedableRng};
+    const ERROR_MARGIN: f64 = 0.00001;
+
+    fn assert_rows(actual: &Matrix<f64>, expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len(), "{:?}", actual);
+        for (a, e) in actual.iter().zip(expected) {
+            assert!(
+                (a - e).abs() <= ERROR_MARGIN,
+                "{:?} != {:?}",
+                actual,
+                expected
+            );
+        }
+    }
+
+    // 1 x 3 x 3, then the same image negated
+    fn images() -> Matrix<f64> {
+        let image = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0];
+        let negated = image.map(|x| -x);
+        Matrix::new([image, negated].concat(), 9, 2)
+    }
+
+    #[test]
+    fn convolves() {
+        // the top left cell plus the bottom right one, and the sum of each window
+        let kernels = Matrix::new(vec![1.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0, 1.0], 4, 2);
+        let conv = Conv2D::with_initializer((1, 3, 3), 2, 2, &Initializer::Given(kernels), 0);
+        assert_eq!(conv.output_shape(), (2, 2, 2));
+        let output = conv.predict(&images());
+        assert_rows(
+            &output,
+            &[
+                6.0, 8.0, 12.0, 14.0, 12.0, 16.0, 24.0, 28.0, //
+                -6.0, -8.0, -12.0, -14.0, -12.0, -16.0, -24.0, -28.0,
+            ],
+        );
+
+        // a padded 5 x 5 image sampled every other cell
+        let ones = Initializer::Constant(1.0);
+        let conv = Conv2D::with_initializer((1, 3, 3), 1, 3, &ones, 0)
+            .padding(1)
+            .stride(2);
+        assert_eq!(conv.output_shape(), (1, 2, 2));
+        let output = conv.predict(&images().clone_row(0));
+        assert_rows(&output, &[12.0, 16.0, 24.0, 28.0]);
+
+        // every filter spans every channel
+        let mut conv = Conv2D::with_initializer((2, 3, 3), 1, 3, &ones, 0);
+        assert_eq!(conv.output_shape(), (1, 1, 1));
+        let both = Matrix::new(images().iter().copied().collect(), 18, 1);
+        assert_rows(&conv.forward(&both), &[0.0]);
+        *conv.params_mut()[1] = Matrix::new(vec![0.5], 1, 1);
+        assert_rows(&conv.predict(&both), &[0.5]);
+    }
+
+    #[test]
+    fn pools() {
+        let x = images();
+        let mut max = MaxPool2D::new((1, 3, 3), 2).stride(1);
+        assert_eq!(max.output_shape(), (1, 2, 2));
+        let output = max.forward(&x);
+        assert_rows(&output, &[5.0, 6.0, 8.0, 9.0, -1.0, -2.0, -4.0, -5.0]);
+        // every output's gradient goes to its window's largest input
+        let (cost_wrt_input, grads) = max.backward(&Matrix::new_uniform(1.0, 4, 2));
+        assert!(grads.is_empty());
+        assert_rows(
+            &cost_wrt_input,
+            &[
+                0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 0.0, 1.0, 1.0, //
+                1.0, 1.0, 0.0, 1.0, 1.0, 0.0, 0.0, 0.0, 0.0,
+            ],
+        );
+
+        // padding is never the largest, even of negative inputs
+        let max = MaxPool2D::new((1, 3, 3), 2).padding(1);
+        assert_eq!(max.output_shape(), (1, 2, 2));
+        assert_rows(&max.predict(&x.clone_row(1)), &[-1.0, -2.0, -4.0, -5.0]);
+
+        let mut avg = AvgPool2D::new((1, 3, 3), 2).stride(1);
+        let output = avg.forward(&x.clone_row(0));
+        assert_rows(&output, &[3.0, 4.0, 6.0, 7.0]);
+        let (cost_wrt_input, _) = avg.backward(&Matrix::new_uniform(1.0, 4, 1));
+        assert_rows(
+            &cost_wrt_input,
+            &[0.25, 0.5, 0.25, 0.5, 1.0, 0.5, 0.25, 0.5, 0.25],
+        );
+        // padding counts as zeroes
+        let avg = AvgPool2D::new((1, 3, 3), 2).padding(1);
+        assert_rows(&avg.predict(&x.clone_row(0)), &[0.25, 1.25, 2.75, 7.0]);
+
+        let flatten = Flatten::new((1, 2, 2));
+        assert_eq!(flatten.features(), 4);
+        assert_rows(&flatten.predict(&output), &[3.0, 4.0, 6.0, 7.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "input rows must hold (1, 4, 4) images")]
+    fn rejects_other_shapes() {
+        Conv2D::new((1, 4, 4), 2, 3).predict(&images());
+    }
+
+    #[test]
+    fn kernels_scaled_by_fan() {
+        let mut rng = StdRng::seed_from_u64(0);
+        // fan_in = 16 * 9, fan_out = 32 * 9
+        let kernels = Initializer::HeNormal.kernels(16, 32, 3, &mut rng);
+        assert_eq!((kernels.w(), kernels.h()), (144, 32));
+        let mean = kernels.iter().sum::<f64>() / kernels.len() as f64;
+        let variance =
+            kernels.iter().map(|k| (k - mean) * (k - mean)).sum::<f64>() / kernels.len() as f64;
+        assert!((variance / (2.0 / 144.0) - 1.0).abs() < 0.1, "{}", variance);
+
+        let kernels = Initializer::XavierUniform.kernels(16, 32, 3, &mut rng);
+        let limit = (6.0 / (144.0 + 288.0_f64)).sqrt();
+        let largest = kernels.iter().fold(0.0_f64, |a, k| a.max(k.abs()));
+        assert!(largest <= limit && largest > 0.9 * limit, "{}", largest);
+    }
+
+    #[test]
+    fn gradients() {
+        let mut rng = StdRng::seed_from_u64(1);
+        // 3 cases of 2 x 5 x 5
+        let x = Matrix::random_with(-1.0, 1.0, 50, 3, &mut rng);
+        let y = Matrix::random_with(0.0, 1.0, 2, 3, &mut rng);
+        let mut models = [
+            Sequential::new()
+                .layer(
+                    Conv2D::with_initializer((2, 5, 5), 3, 3, &XavierUniform, 1)
+                        .padding(1)
+                        .stride(2),
+                )
+                .sigmoid()
+                .conv2d_with((3, 3, 3), 2, 2, &XavierUniform, 2)
+                .flatten((2, 2, 2))
+                .dense_with(8, 2, &XavierUniform, 3),
+            Sequential::new()
+                .conv2d_with((2, 5, 5), 3, 2, &XavierUniform, 4)
+                .sigmoid()
+                .max_pool2d((3, 4, 4), 2)
+                .conv2d_with((3, 2, 2), 2, 1, &XavierUniform, 5)
+                .layer(AvgPool2D::new((2, 2, 2), 2).padding(1).stride(1))
+                .flatten((2, 3, 3))
+                .dense_with(18, 2, &XavierUniform, 6)
+                .sigmoid(),
+        ];
+        for model in models.iter_mut() {
+            for error in check_model(model, &x, &y, 1e-6) {
+                assert!(error < 1e-5, "{}", error);
+            }
+        }
+    }
+
+    #[test]
+    #[ignore = "needs data/mnist_small.csv"]
+    fn cnn_on_mnist() {
+        use ml::data::{DataType, Dataset};
+        use ml::metrics;
+        use ml::nn::cost::CategoricalCrossEntropy;
+        use ml::nn::train::{TrainConfig, Trainer};
+
+        let mut data = Dataset::from_csv(&String::from("data/mnist_small.csv"));
+        let y_keys = data.one_hot_encode(
+            "label",
+            &(0..10)
+                .map(|i| DataType::Numerical(i as f64))
+                .collect::<Vec<_>>(),
+        );
+        let x_keys: Vec<String> = data
+            .keys()
+            .iter()
+            .filter(|&value| value != "label")
+            .cloned()
+            .collect();
+        // the 784 pixel rows, scaled to [0, 1], are 1 x 28 x 28 images
+        let x = data.to_matrix(&x_keys).apply(|pixel| pixel / 255.0);
+        let y = data.to_matrix(&y_keys);
+        let mut model = Sequential::new()
+            .conv2d_with((1, 28, 28), 4, 3, &Initializer::HeNormal, 0)
+            .relu()
+            .max_pool2d((4, 26, 26), 2)
+            .flatten((4, 13, 13))
+            .dense_with(676, 10, &XavierUniform, 1)
+            .softmax()
+            .cost::<CategoricalCrossEntropy>();
+        let history = Trainer::new(TrainConfig {
+            epochs: 1,
+            batch_size: 8,
+            shuffle_seed: Some(0),
+            learning_rate: 0.05,
+        })
+        .fit(&mut model, &x, &y);
+        assert_eq!(history.loss.len(), 1);
+        // better than guessing one of the 10 digits
+        assert!(metrics::accuracy(&model.predict(&x), &y) > 0.1);
+    }
+}
@@ -0,0 +1,158 @@
+#[cfg(test)]
+mod tests {
+    use ml::algebra::{MatLike, Matrix};
+    use ml::nn::activations::{Activation, Sigmoid};
+    use ml::nn::cost::*;
+    use ml::nn::gradcheck::{check_cost, relative_error};
+    const EPSILON: f64 = 1e-6;
+    const TOLERANCE: f64 = 1e-5;
+    const ERROR_MARGIN: f64 = 1e-9;
+
+    // probabilities, and nowhere near a kink of the other costs
+    fn pred() -> Matrix<f64> {
+        Matrix::new(vec![0.1, 0.35, 0.8, 0.6, 0.25, 0.45], 3, 2)
+    }
+
+    // used as targets, labels of ±1 for the hinge losses, or counts for PoissonNLL
+    fn actual(kind: CostKind) -> Matrix<f64> {
+        match kind {
+            CostKind::Hinge | CostKind::SquaredHinge => {
+                Matrix::new(vec![1.0, -1.0, 1.0, -1.0, 1.0, -1.0], 3, 2)
+            }
+            CostKind::PoissonNLL => Matrix::new(vec![0.0, 1.0, 3.0, 2.0, 0.0, 1.0], 3, 2),
+            _ => Matrix::new(vec![0.0, 0.3, 0.7, 1.0, 0.0, 0.0], 3, 2),
+        }
+    }
+
+    fn check_cost_fn(cost: &CostFn, pred: &Matrix<f64>, actual: &Matrix<f64>) -> f64 {
+        let analytic = cost.prime(pred, actual);
+        let mut nudged = pred.clone();
+        let mut worst = 0_f64;
+        for i in 0..pred.h() {
+            for j in 0..pred.w() {
+                nudged[(i, j)] = pred[(i, j)] + EPSILON;
+                let plus = cost.calc(&nudged, actual);
+                nudged[(i, j)] = pred[(i, j)] - EPSILON;
+                let minus = cost.calc(&nudged, actual);
+                nudged[(i, j)] = pred[(i, j)];
+                let numeric = (plus - minus) / (2.0 * EPSILON);
+                worst = worst.max(relative_error(analytic[(i, j)], numeric));
+            }
+        }
+        worst
+    }
+
+    #[test]
+    fn primes() {
+        for &kind in CostKind::ALL {
+            let error = check_cost_fn(&kind.into(), &pred(), &actual(kind));
+            assert!(error < TOLERANCE, "{}: {}", kind, error);
+        }
+        // either side of huber's delta
+        let far = Matrix::new(vec![3.0, -2.5, 0.2, 0.9, 4.0, -0.5], 3, 2);
+        let actual = actual(CostKind::Huber);
+        assert!(check_cost::<Huber>(&far, &actual, EPSILON) < TOLERANCE);
+        assert!(check_cost::<LogCosh>(&far, &actual, EPSILON) < TOLERANCE);
+        assert!(check_cost::<BinaryCrossEntropyWithLogits>(&far, &actual, EPSILON) < TOLERANCE);
+        assert!(check_cost_fn(&CostFn::huber(0.5), &far, &actual) < TOLERANCE);
+        assert!(check_cost_fn(&CostFn::quantile(0.9), &far, &actual) < TOLERANCE);
+    }
+
+    #[test]
+    fn known_values() {
+        let pred = Matrix::new(vec![1.0, 4.0], 2, 1);
+        let actual = Matrix::new(vec![2.0, 2.0], 2, 1);
+        let cases: [(CostFn, f64); 6] = [
+            (CostFn::of::<SumSquared>(), 5.0),
+            (CostFn::of::<MeanSquared>(), 2.5),
+            (CostFn::of::<MeanAbsolute>(), 1.5),
+            (CostFn::of::<Huber>(), (0.5 + 1.5) / 2.0),
+            (CostFn::quantile(0.9), (0.9 + 0.2) / 2.0),
+            (CostFn::huber(3.0), (0.5 + 2.0) / 2.0),
+        ];
+        for (cost, expected) in cases {
+            let value = cost.calc(&pred, &actual);
+            assert!((value - expected).abs() <= ERROR_MARGIN, "{:?}", cost);
+        }
+
+        let p = Matrix::new(vec![0.25, 0.75], 2, 1);
+        let y = Matrix::new(vec![0.5, 0.5], 2, 1);
+        let kl = KLDivergence::calc(&p, &y);
+        assert!((kl - 0.5 * (2.0_f64.ln() + (2.0_f64 / 3.0).ln())).abs() <= ERROR_MARGIN);
+        let same = KLDivergence::calc(&y, &y);
+        assert!(same.abs() <= ERROR_MARGIN);
+    }
+
+    #[test]
+    fn logits_match_probabilities() {
+        let logits = Matrix::new(vec![-2.0, 0.5, 3.0, 1.0], 2, 2);
+        let actual = Matrix::new(vec![0.0, 1.0, 1.0, 0.0], 2, 2);
+        let probabilities = logits.apply(Sigmoid::calc);
+        let a = BinaryCrossEntropyWithLogits::calc(&logits, &actual);
+        let b = BinaryCrossEntropy::calc(&probabilities, &actual);
+        assert!((a - b).abs() <= ERROR_MARGIN);
+
+        // saturated sigmoids, where BinaryCrossEntropy can only clamp
+        let extreme = Matrix::new(vec![1000.0, -1000.0], 2, 1);
+        let wrong = Matrix::new(vec![0.0, 1.0], 2, 1);
+        let cost = BinaryCrossEntropyWithLogits::calc(&extreme, &wrong);
+        assert!((cost - 1000.0).abs() <= ERROR_MARGIN);
+        let prime = BinaryCrossEntropyWithLogits::prime(&extreme, &wrong);
+        assert!(prime.iter().all(|g| g.is_finite()));
+        assert!(LogCosh::calc(&extreme, &wrong).is_finite());
+    }
+
+    #[test]
+    fn reductions() {
+        let pred = Matrix::new(vec![1.0, 4.0, 0.0, 0.0], 2, 2);
+        let actual = Matrix::new(vec![2.0, 2.0, 0.0, 1.0], 2, 2);
+        let per_case = MeanSquared::reduce(&pred, &actual, Reduction::None);
+        assert_eq!(per_case, vec![2.5, 0.5]);
+        assert_eq!(
+            MeanSquared::reduce(&pred, &actual, Reduction::Sum),
+            vec![3.0]
+        );
+        assert_eq!(
+            MeanSquared::reduce(&pred, &actual, Reduction::Mean),
+            vec![1.5]
+        );
+        assert_eq!(Reduction::default(), Reduction::Mean);
+        let cost: CostFn = CostKind::MeanSquared.into();
+        assert_eq!(cost.reduce(&pred, &actual, Reduction::None), per_case);
+
+        let prime = MeanSquared::prime(&pred, &actual);
+        let mean = Reduction::Mean.apply_prime(prime.clone());
+        assert!(zip_close(&mean, &(prime.clone() * 0.5)));
+        assert!(zip_close(
+            &Reduction::Sum.apply_prime(prime.clone()),
+            &prime
+        ));
+    }
+
+    #[test]
+    fn parameters_in_names() {
+        let pred = Matrix::new(vec![1.0, 4.0], 2, 1);
+        let actual = Matrix::new(vec![2.0, 2.0], 2, 1);
+        for cost in [
+            CostFn::huber(0.5),
+            CostFn::quantile(0.9),
+            CostKind::Huber.into(),
+        ] {
+            let parsed: CostFn = cost.to_string().parse().unwrap();
+            assert_eq!(parsed.name(), cost.name());
+            assert_eq!(parsed.calc(&pred, &actual), cost.calc(&pred, &actual));
+        }
+        assert_eq!(CostFn::huber(0.5).to_string(), "huber(0.5)");
+        assert_eq!(CostFn::quantile(0.9).name(), "quantile(0.9)");
+        assert_ne!(CostFn::huber(3.0).name(), CostFn::of::<Huber>().name());
+        for name in ["huber(-1)", "quantile(1.5)", "huber(x)", "log_cosh(2)"] {
+            assert!(name.parse::<CostFn>().is_err(), "{}", name);
+        }
+    }
+
+    fn zip_close(a: &Matrix<f64>, b: &Matrix<f64>) -> bool {
+        a.iter()
+            .zip(b.iter())
+            .all(|(a, b)| (a - b).abs() <= ERROR_MARGIN)
+    }
+}
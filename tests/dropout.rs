@@ -0,0 +1,171 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{batch, inputs};
+    use ml::algebra::{MatLike, Matrix};
+    use ml::nn::layers::{Dropout, Layer};
+    use ml::nn::sequential::Sequential;
+    use ml::nn::train::{TrainConfig, Trainable, Trainer};
+    use ml::nn::{activations::*, cost::SumSquared, feedforward::FFNet};
+    use rand::{rngs::StdRng, SeedableRng};
+    const ERROR_MARGIN: f64 = 0.00001;
+
+    fn mean_and_variance(m: &Matrix<f64>) -> (f64, f64) {
+        let mean = m.iter().sum::<f64>() / m.len() as f64;
+        let variance = m.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / m.len() as f64;
+        (mean, variance)
+    }
+
+    #[test]
+    fn keeps_expected_value() {
+        let ones = Matrix::new_uniform(1.0, 20, 500);
+        let mut dropout = Dropout::new(0.3, 0);
+        let output = dropout.forward(&ones);
+        let dropped = output.iter().filter(|&&x| x == 0.0).count() as f64 / ones.len() as f64;
+        assert!((dropped - 0.3).abs() < 0.02, "{}", dropped);
+        assert!(output
+            .iter()
+            .all(|&x| x == 0.0 || (x - 1.0 / 0.7).abs() <= ERROR_MARGIN));
+        assert!((mean_and_variance(&output).0 - 1.0).abs() < 0.03);
+
+        let output = Dropout::gaussian(0.3, 0).forward(&ones);
+        let (mean, variance) = mean_and_variance(&output);
+        assert!((mean - 1.0).abs() < 0.03, "{}", mean);
+        assert!((variance - 0.3 / 0.7).abs() < 0.03, "{}", variance);
+
+        // alpha dropout keeps SELU's normalised activations normalised
+        let mut rng = StdRng::seed_from_u64(1);
+        let normal = Matrix::random_normal_with(0.0, 1.0, 20, 500, &mut rng);
+        let activated = normal.apply(SELU::calc);
+        let output = Dropout::alpha(0.2, 0).forward(&activated);
+        let (before, after) = (mean_and_variance(&activated), mean_and_variance(&output));
+        assert!(
+            (before.0 - after.0).abs() < 0.05,
+            "{:?} {:?}",
+            before,
+            after
+        );
+        assert!(
+            (before.1 - after.1).abs() < 0.05,
+            "{:?} {:?}",
+            before,
+            after
+        );
+    }
+
+    #[test]
+    fn seeded_and_reuses_mask() {
+        let x = Matrix::random(-1.0, 1.0, 8, 6);
+        let (mut a, mut b) = (Dropout::new(0.5, 42), Dropout::new(0.5, 42));
+        let output = a.forward(&x);
+        assert!(output.iter().zip(b.forward(&x).iter()).all(|(a, b)| a == b));
+        // later masks differ from the first
+        assert!(a.forward(&x).iter().zip(output.iter()).any(|(a, b)| a != b));
+
+        let mut dropout = Dropout::new(0.5, 7);
+        let output = dropout.forward(&x);
+        let (cost_wrt_input, grads) = dropout.backward(&Matrix::new_uniform(1.0, 8, 6));
+        assert!(grads.is_empty());
+        for (o, g) in output.iter().zip(cost_wrt_input.iter()) {
+            assert_eq!(*o == 0.0, *g == 0.0);
+        }
+
+        dropout.set_training(false);
+        assert!(dropout
+            .forward(&x)
+            .iter()
+            .zip(x.iter())
+            .all(|(a, b)| a == b));
+        assert!(dropout
+            .predict(&x)
+            .iter()
+            .zip(x.iter())
+            .all(|(a, b)| a == b));
+    }
+
+    #[test]
+    fn train_and_eval() {
+        let x = inputs();
+        let mut model = Sequential::new()
+            .dense(3, 16)
+            .relu()
+            .dropout(0.5, 0)
+            .dense(16, 2);
+        let predicted = model.predict(&x);
+        assert!(model
+            .forward(&x)
+            .iter()
+            .zip(predicted.iter())
+            .any(|(a, b)| (a - b).abs() > ERROR_MARGIN));
+        model.eval();
+        assert!(model
+            .forward(&x)
+            .iter()
+            .zip(predicted.iter())
+            .all(|(a, b)| (a - b).abs() <= ERROR_MARGIN));
+
+        let mut net = FFNet::<Sigmoid, SumSquared>::new(vec![3, 16, 2]);
+        net.randomize_params();
+        net.set_dropout(0, Dropout::new(0.5, 0));
+        let input = x.clone_row(0).transpose().clone();
+        let predicted = net.predict(&x.clone_row(0));
+        assert!(net.is_training());
+        let dropped = net.pred_single(input.clone()).clone();
+        assert!(dropped
+            .iter()
+            .zip(predicted.iter())
+            .any(|(a, b)| (a - b).abs() > ERROR_MARGIN));
+        net.eval();
+        let single = net.pred_single(input).clone();
+        assert!(single
+            .iter()
+            .zip(predicted.iter())
+            .all(|(a, b)| (a - b).abs() <= ERROR_MARGIN));
+    }
+
+    #[test]
+    fn ffnet_gradients() {
+        let (x, y) = batch();
+        let mut nets: Vec<FFNet<Sigmoid, SumSquared>> =
+            (0..2).map(|_| FFNet::new(vec![3, 6, 2])).collect();
+        nets[0].randomize_params();
+        let params: Vec<Matrix<f64>> = nets[0]
+            .params_mut()
+            .into_iter()
+            .map(|p| p.clone())
+            .collect();
+        for (autograd, net) in nets.iter_mut().enumerate() {
+            for (to, from) in net.params_mut().into_iter().zip(params.iter()) {
+                *to = from.clone();
+            }
+            net.set_dropout(0, Dropout::new(0.5, 3));
+            net.set_autograd(autograd == 1);
+        }
+        // same masks, so hand-written backprop and autograd still agree
+        let (a, b) = (nets[0].batch_grad(&x, &y), nets[1].batch_grad(&x, &y));
+        assert!((a.0 - b.0).abs() <= ERROR_MARGIN);
+        for (a, b) in a.1.iter().zip(b.1.iter()) {
+            assert!(a
+                .iter()
+                .zip(b.iter())
+                .all(|(a, b)| (a - b).abs() <= ERROR_MARGIN));
+        }
+
+        // and a seeded run can be repeated exactly
+        let losses: Vec<Vec<f64>> = (0..2)
+            .map(|_| {
+                let mut net = FFNet::<Sigmoid, SumSquared>::with_seed(vec![3, 6, 2], 4);
+                net.set_dropout(0, Dropout::new(0.2, 9));
+                Trainer::new(TrainConfig {
+                    epochs: 5,
+                    batch_size: 2,
+                    ..TrainConfig::default()
+                })
+                .fit(&mut net, &x, &y)
+                .loss
+            })
+            .collect();
+        assert_eq!(losses[0], losses[1]);
+    }
+}
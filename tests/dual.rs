@@ -0,0 +1,75 @@
+#[cfg(test)]
+mod tests {
+    use ml::nn::activations::{Activation, AutoActivation, Sigmoid};
+    use ml::nn::dual::{gradient, hessian_vector_product, Dual, HyperDual, Real};
+    use ml::nn::gradcheck::check_activation;
+    const ERROR_MARGIN: f64 = 0.00001;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() <= ERROR_MARGIN, "{} != {}", a, b);
+    }
+
+    // x * sigmoid(x), with no hand-written prime
+    struct Swish;
+    impl AutoActivation for Swish {
+        fn eval<R: Real>(x: R) -> R {
+            x / ((-x).exp() + 1.0)
+        }
+    }
+
+    #[test]
+    fn auto_activation() {
+        for x in [-3.0, -0.5, 0.0, 0.8, 4.0] {
+            assert_close(Swish::calc(x), x * Sigmoid::calc(x));
+            assert_close(Swish::prime(x), Sigmoid::calc(x) + x * Sigmoid::prime(x));
+        }
+        assert!(check_activation::<Swish>(&[-3.0, -0.5, 0.8, 4.0], 1e-6) < 1e-5);
+    }
+
+    type Check = (fn(Dual) -> Dual, f64);
+
+    #[test]
+    fn transcendental() {
+        let x = 0.7;
+        let checks: [Check; 6] = [
+            (|x| x.exp(), x.exp()),
+            (|x| x.ln(), 1.0 / x),
+            (|x| x.sqrt(), 0.5 / x.sqrt()),
+            (|x| x.tanh(), 1.0 - x.tanh() * x.tanh()),
+            (|x| x.powi(3), 3.0 * x * x),
+            (|x| x.sin() * x.cos(), (2.0 * x).cos()),
+        ];
+        for (function, expected) in checks {
+            assert_close(function(Dual::variable(x)).du, expected);
+        }
+    }
+
+    #[test]
+    fn second_derivative() {
+        let x = 1.3;
+        assert_close((HyperDual::variable(x).sin()).e12, -x.sin());
+        assert_close(HyperDual::variable(x).powi(4).e12, 12.0 * x * x);
+        assert_close(
+            Swish::second_derivative(x),
+            2.0 * Sigmoid::prime(x) + x * Sigmoid::prime(x) * (1.0 - 2.0 * Sigmoid::calc(x)),
+        );
+    }
+
+    #[test]
+    fn multivariate() {
+        // f(x, y) = x^2 y + y^3
+        fn f<R: Real>(v: &[R]) -> R {
+            v[0] * v[0] * v[1] + v[1].powi(3)
+        }
+        let (x, y) = (1.5, -2.0);
+        let grad = gradient(f, &[x, y]);
+        assert_close(grad[0], 2.0 * x * y);
+        assert_close(grad[1], x * x + 3.0 * y * y);
+
+        // H = [[2y, 2x], [2x, 6y]]
+        let v = [0.3, -1.1];
+        let hv = hessian_vector_product(f, &[x, y], &v);
+        assert_close(hv[0], 2.0 * y * v[0] + 2.0 * x * v[1]);
+        assert_close(hv[1], 2.0 * x * v[0] + 6.0 * y * v[1]);
+    }
+}
@@ -1,23 +1,33 @@
+mod common;
+
 #[cfg(test)]
 mod tests {
-    use ml::algebra::Matrix;
-    use ml::data::Dataset;
-    use ml::nn::{activations::*, cost::SumSquared, feedforward::FFNet};
+    use crate::common::inputs;
+    use ml::algebra::{MatLike, Matrix};
+    use ml::data::{DataType, Dataset};
+    use ml::metrics;
+    use ml::nn::cost::{CategoricalCrossEntropy, SumSquared};
+    use ml::nn::gradcheck::check_model;
+    use ml::nn::init::Initializer;
+    use ml::nn::sequential::Sequential;
+    use ml::nn::train::{TrainConfig, Trainer};
+    use ml::nn::{activations::*, feedforward::FFNet};
     const ERROR_MARGIN: f64 = 0.00001;
 
     macro_rules! test_with_activation {
         ($type: ty, $expected: expr, $input: ident) => {
             let mut net = FFNet::<$type, SumSquared>::new(vec![2, 1]);
+            net.initialize_all(&Initializer::Constant(1.0), 0);
             let result = net.pred_single($input.clone());
             assert!(
-                result[0] <= $expected + ERROR_MARGIN,
-                "result[0] = {}",
-                result[0]
+                result[(0, 0)] <= $expected + ERROR_MARGIN,
+                "result[(0, 0)] = {}",
+                result[(0, 0)]
             );
             assert!(
-                result[0] >= $expected - ERROR_MARGIN,
-                "result[0] = {}",
-                result[0]
+                result[(0, 0)] >= $expected - ERROR_MARGIN,
+                "result[(0, 0)] = {}",
+                result[(0, 0)]
             );
         };
     }
@@ -26,16 +36,37 @@ mod tests {
     fn computation() {
         let input: Matrix<f64> = Matrix::new(vec![-1.9, 2.5], 1, 2);
         let expected: Matrix<f64> = Matrix::new(vec![0.6, 1.03748_f64, 0.64565_f64], 1, 3);
-        test_with_activation!(ReLU, expected[0], input);
-        test_with_activation!(Softplus, expected[1], input);
-        test_with_activation!(Sigmoid, expected[2], input);
+        test_with_activation!(ReLU, expected[(0, 0)], input);
+        test_with_activation!(Softplus, expected[(1, 0)], input);
+        test_with_activation!(Sigmoid, expected[(2, 0)], input);
+    }
+
+    #[test]
+    fn batch_prediction() {
+        let mut net = FFNet::<Sigmoid, SumSquared>::new(vec![3, 4, 2]);
+        net.randomize_params();
+        let x = inputs();
+        let batch = net.predict(&x);
+        assert_eq!((batch.w(), batch.h()), (2, 4));
+        for i in 0..x.h() {
+            let single = net.pred_single(x.clone_row(i).transpose().clone());
+            for j in 0..2 {
+                assert!((single[(0, j)] - batch[(i, j)]).abs() <= ERROR_MARGIN);
+            }
+        }
     }
 
     #[test]
+    #[ignore = "needs data/mnist_small.csv"]
     fn training_works() {
         let train_path = String::from("data/mnist_small.csv");
         let mut data = Dataset::from_csv(&train_path);
-        let y_keys = data.one_hot_encode("label", 1);
+        let y_keys = data.one_hot_encode(
+            "label",
+            &(0..10)
+                .map(|i| DataType::Numerical(i as f64))
+                .collect::<Vec<_>>(),
+        );
         let x_keys: Vec<String> = data
             .keys()
             .iter()
@@ -44,9 +75,25 @@ mod tests {
             .collect();
         let x = data.to_matrix(&x_keys);
         let y = data.to_matrix(&y_keys);
-        println!("{}", y);
-        let mut net: FFNet<ReLU, SumSquared> = FFNet::new(vec![784_usize, 2_usize, 10_usize]);
-        net.sgd(&x, &y, 4, 0.1);
-        panic!();
+        let mut model = Sequential::new()
+            .dense(784, 16)
+            .relu()
+            .dense(16, 10)
+            .softmax()
+            .cost::<CategoricalCrossEntropy>();
+        let history = Trainer::new(TrainConfig {
+            epochs: 1,
+            batch_size: 4,
+            shuffle_seed: Some(0),
+            learning_rate: 0.1,
+        })
+        .fit(&mut model, &x, &y);
+        assert_eq!(history.loss.len(), 1);
+        // better than guessing one of the 10 digits
+        assert!(metrics::accuracy(&model.predict(&x), &y) > 0.1);
+        let (x, y) = (x.select_rows(&[0, 1]), y.select_rows(&[0, 1]));
+        for error in check_model(&mut model, &x, &y, 1e-6) {
+            assert!(error < 1e-4, "{}", error);
+        }
     }
 }
@@ -0,0 +1,79 @@
+#[cfg(test)]
+mod tests {
+    use ml::algebra::Matrix;
+    use ml::nn::gradcheck::{check_activation, check_cost, check_net, relative_error};
+    use ml::nn::{activations::*, cost::*, feedforward::FFNet};
+    const EPSILON: f64 = 1e-6;
+    const TOLERANCE: f64 = 1e-5;
+    // stays clear of ReLU's kink at 0
+    const POINTS: [f64; 6] = [-4.0, -1.3, -0.2, 0.3, 1.7, 5.0];
+
+    macro_rules! test_net {
+        ($activation: ty, $cost: ty) => {
+            let mut net = FFNet::<$activation, $cost>::with_seed(vec![3, 5, 4, 2], 0);
+            let input = Matrix::new(vec![0.7, -1.1, 1.9], 1, 3);
+            let output = Matrix::new(vec![0.25, 0.75], 2, 1);
+            for autograd in [false, true] {
+                net.set_autograd(autograd);
+                let errors = check_net(&mut net, &input, &output, EPSILON);
+                assert_eq!(errors.len(), 3);
+                for (i, error) in errors.iter().enumerate() {
+                    assert!(error.worst() < TOLERANCE, "layer {}: {:?}", i, error);
+                }
+            }
+        };
+    }
+
+    #[test]
+    fn nets() {
+        test_net!(ReLU, SumSquared);
+        test_net!(Softplus, SumSquared);
+        test_net!(Sigmoid, SumSquared);
+        test_net!(Tanh, SumSquared);
+        test_net!(Mish, SumSquared);
+    }
+
+    #[test]
+    fn activations() {
+        assert!(check_activation::<ReLU>(&POINTS, EPSILON) < TOLERANCE);
+        assert!(check_activation::<Softplus>(&POINTS, EPSILON) < TOLERANCE);
+        assert!(check_activation::<Sigmoid>(&POINTS, EPSILON) < TOLERANCE);
+        assert!(check_activation::<GELU>(&POINTS, EPSILON) < TOLERANCE);
+        // POINTS also stays clear of the hard activations' kinks at ±1 and ±3
+        for kind in ActivationKind::ALL {
+            for x in POINTS {
+                let numeric = (kind.calc(x + EPSILON) - kind.calc(x - EPSILON)) / (2.0 * EPSILON);
+                let error = relative_error(kind.prime(x), numeric);
+                assert!(error < TOLERANCE, "{} at {}: {}", kind, x, error);
+            }
+        }
+    }
+
+    #[test]
+    fn costs() {
+        let pred = Matrix::new(vec![0.1, -0.4, 2.2, 0.9, 0.0, -1.5], 3, 2);
+        let actual = Matrix::new(vec![0.0, 1.0, 0.0, 1.0, 0.0, 0.0], 3, 2);
+        assert!(check_cost::<SumSquared>(&pred, &actual, EPSILON) < TOLERANCE);
+    }
+
+    struct WrongPrime;
+    impl Activation for WrongPrime {
+        fn calc(x: f64) -> f64 {
+            x * x
+        }
+        fn prime(x: f64) -> f64 {
+            x
+        }
+    }
+
+    #[test]
+    fn catches_wrong_prime() {
+        assert!(check_activation::<WrongPrime>(&POINTS, EPSILON) > 0.1);
+        let mut net = FFNet::<WrongPrime, SumSquared>::new(vec![3, 2]);
+        net.randomize_params();
+        let input = Matrix::new(vec![0.7, -1.1, 1.9], 1, 3);
+        let output = Matrix::new(vec![0.25, 0.75], 2, 1);
+        let errors = check_net(&mut net, &input, &output, EPSILON);
+        assert!(errors[0].worst() > 0.1, "{:?}", errors[0]);
+    }
+}
@@ -0,0 +1,158 @@
+#[cfg(test)]
+mod tests {
+    use ml::algebra::{MatLike, Matrix};
+    use ml::nn::init::Initializer;
+    use ml::nn::layers::PReLU;
+    use ml::nn::sequential::Sequential;
+    use ml::nn::train::Trainable;
+    use ml::nn::{activations::*, cost::SumSquared, feedforward::FFNet};
+    use rand::{rngs::StdRng, SeedableRng};
+    const ERROR_MARGIN: f64 = 0.00001;
+
+    fn std_dev(m: &Matrix<f64>) -> f64 {
+        let mean = m.iter().sum::<f64>() / m.len() as f64;
+        (m.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / m.len() as f64).sqrt()
+    }
+
+    #[test]
+    fn scaled_by_fan() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let (fan_in, fan_out) = (400, 200);
+        let expected = [
+            (Initializer::XavierUniform, (2.0 / 600.0_f64).sqrt()),
+            (Initializer::XavierNormal, (2.0 / 600.0_f64).sqrt()),
+            (Initializer::HeUniform, (2.0 / 400.0_f64).sqrt()),
+            (Initializer::HeNormal, (2.0 / 400.0_f64).sqrt()),
+            (Initializer::LeCunUniform, (1.0 / 400.0_f64).sqrt()),
+            (Initializer::LeCunNormal, (1.0 / 400.0_f64).sqrt()),
+        ];
+        for (initializer, expected) in expected {
+            let weights = initializer.weights(fan_in, fan_out, &mut rng);
+            assert_eq!((weights.w(), weights.h()), (fan_in, fan_out));
+            let actual = std_dev(&weights);
+            assert!((actual / expected - 1.0).abs() < 0.02, "{:?}", initializer);
+        }
+        let limit = (6.0 / 400.0_f64).sqrt();
+        let weights = Initializer::HeUniform.weights(fan_in, fan_out, &mut rng);
+        assert!(weights.iter().all(|w| w.abs() <= limit));
+    }
+
+    #[test]
+    fn orthogonal() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for (in_shape, out_shape) in [(6, 4), (4, 6), (5, 5)] {
+            let weights =
+                Initializer::Orthogonal { gain: 2.0 }.weights(in_shape, out_shape, &mut rng);
+            // the short side's vectors are orthogonal, each with length gain
+            let gram = if in_shape >= out_shape {
+                &weights * weights.new_transposed()
+            } else {
+                weights.new_transposed() * &weights
+            };
+            for i in 0..gram.h() {
+                for j in 0..gram.w() {
+                    let expected = if i == j { 4.0 } else { 0.0 };
+                    assert!((gram[(i, j)] - expected).abs() <= ERROR_MARGIN);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fixed() {
+        let mut rng = StdRng::seed_from_u64(0);
+        assert!(Initializer::Zeros
+            .weights(3, 2, &mut rng)
+            .iter()
+            .all(|&w| w == 0.0));
+        let constant = Initializer::Constant(0.5).weights(3, 2, &mut rng);
+        assert!(constant.iter().all(|&w| w == 0.5));
+        let given = Matrix::new(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], 3, 2);
+        let weights = Initializer::Given(given.clone()).weights(3, 2, &mut rng);
+        assert!(weights.iter().zip(given.iter()).all(|(a, b)| a == b));
+    }
+
+    #[test]
+    #[should_panic(expected = "out_shape x in_shape")]
+    fn given_wrong_shape() {
+        let given = Matrix::new(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], 2, 3);
+        Initializer::Given(given).weights(3, 2, &mut StdRng::seed_from_u64(0));
+    }
+
+    #[test]
+    fn defaults_and_seeds() {
+        assert!(matches!(
+            Initializer::for_activation(ReLU::name()),
+            Initializer::HeNormal
+        ));
+        assert!(matches!(
+            Initializer::for_activation(SELU::name()),
+            Initializer::LeCunNormal
+        ));
+        assert!(matches!(
+            Initializer::for_activation(Sigmoid::name()),
+            Initializer::XavierUniform
+        ));
+
+        let params = |net: &mut FFNet<ReLU, SumSquared>| -> Vec<Matrix<f64>> {
+            net.params_mut().into_iter().map(|p| p.clone()).collect()
+        };
+        let mut a = FFNet::<ReLU, SumSquared>::with_seed(vec![50, 40, 3], 7);
+        let mut b = FFNet::<ReLU, SumSquared>::with_seed(vec![50, 40, 3], 7);
+        let mut c = FFNet::<ReLU, SumSquared>::with_seed(vec![50, 40, 3], 8);
+        let (a_params, b_params, c_params) = (params(&mut a), params(&mut b), params(&mut c));
+        assert!(a_params[0]
+            .iter()
+            .zip(b_params[0].iter())
+            .all(|(a, b)| a == b));
+        assert!(a_params[0]
+            .iter()
+            .zip(c_params[0].iter())
+            .any(|(a, b)| a != b));
+        // He normal for relu, and no bias
+        assert!((std_dev(&a_params[0]) / (2.0 / 50.0_f64).sqrt() - 1.0).abs() < 0.05);
+        assert!(a_params[1].iter().all(|&b| b == 0.0));
+
+        // per layer
+        a.initialize(1, &Initializer::Orthogonal { gain: 1.0 }, 0);
+        let after = params(&mut a);
+        assert!(after[0].iter().zip(a_params[0].iter()).all(|(a, b)| a == b));
+        assert!(after[2].iter().zip(a_params[2].iter()).any(|(a, b)| a != b));
+    }
+
+    #[test]
+    fn sequential_defaults_and_seeds() {
+        let params = |model: &mut Sequential| -> Vec<Matrix<f64>> {
+            model.params_mut().into_iter().map(|p| p.clone()).collect()
+        };
+        let model = |seed: u64| {
+            Sequential::with_seed(seed)
+                .dense(200, 100)
+                .relu()
+                .dense(100, 200)
+                .sigmoid()
+                .conv2d((2, 20, 20), 50, 3)
+                .activation::<SELU>()
+        };
+        let (mut a, mut b, mut c) = (model(7), model(7), model(8));
+        let mut prelu = Sequential::with_seed(7)
+            .dense(200, 100)
+            .layer(PReLU::new(100));
+        let (a_params, b_params, c_params) = (params(&mut a), params(&mut b), params(&mut c));
+        for (a, b) in a_params.iter().zip(b_params.iter()) {
+            assert!(a.iter().zip(b.iter()).all(|(a, b)| a == b));
+        }
+        assert!(a_params[0]
+            .iter()
+            .zip(c_params[0].iter())
+            .any(|(a, b)| a != b));
+        // picked by the activation after each layer
+        assert!((std_dev(&a_params[0]) / (2.0 / 200.0_f64).sqrt() - 1.0).abs() < 0.05);
+        assert!(params(&mut prelu)[0]
+            .iter()
+            .zip(a_params[0].iter())
+            .all(|(a, b)| a == b));
+        assert!((std_dev(&a_params[2]) / (2.0 / 300.0_f64).sqrt() - 1.0).abs() < 0.05);
+        assert!((std_dev(&a_params[4]) / (1.0 / 18.0_f64).sqrt() - 1.0).abs() < 0.05);
+    }
+}
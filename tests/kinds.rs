@@ -0,0 +1,103 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::batch;
+    use ml::algebra::{MatLike, Matrix};
+    use ml::nn::activations::{Activation, ActivationFn, ActivationKind, ReLU, Sigmoid};
+    use ml::nn::cost::{Cost, CostFn, CostKind, SumSquared};
+    use ml::nn::sequential::Sequential;
+    use ml::nn::train::Trainable;
+
+    #[test]
+    fn round_trip() {
+        for kind in ActivationKind::ALL {
+            assert_eq!(kind.to_string().parse::<ActivationKind>(), Ok(*kind));
+        }
+        for kind in CostKind::ALL {
+            assert_eq!(kind.to_string().parse::<CostKind>(), Ok(*kind));
+        }
+        assert_eq!("ReLU".parse::<ActivationKind>(), Ok(ActivationKind::ReLU));
+        assert_eq!(ReLU::name().parse(), Ok(ActivationKind::ReLU));
+        let error = "swoosh".parse::<ActivationKind>().unwrap_err();
+        assert_eq!(error.to_string(), "unknown activation \"swoosh\"");
+    }
+
+    #[test]
+    fn kinds_match_types() {
+        for x in [-2.0, -0.1, 0.4, 3.0] {
+            assert_eq!(ActivationKind::Sigmoid.calc(x), Sigmoid::calc(x));
+            assert_eq!(ActivationKind::Sigmoid.prime(x), Sigmoid::prime(x));
+            assert_eq!(ActivationFn::of::<ReLU>().prime(x), ReLU::prime(x));
+        }
+        let pred = Matrix::new(vec![0.2, 0.7], 2, 1);
+        let actual = Matrix::new(vec![0.0, 1.0], 2, 1);
+        assert_eq!(
+            CostKind::SumSquared.calc(&pred, &actual),
+            SumSquared::calc(&pred, &actual)
+        );
+        assert_eq!(CostFn::from(CostKind::SumSquared).name(), "sum_squared");
+    }
+
+    #[test]
+    fn configured_at_runtime() {
+        // as if read from a config file
+        let config = ["relu", "sigmoid", "sum_squared"];
+        let (x, y) = batch();
+        let mut model = Sequential::new()
+            .dense(3, 3)
+            .activation_fn(config[0].parse::<ActivationKind>().unwrap())
+            .dense(3, 2)
+            .activation_fn(config[1].parse::<ActivationKind>().unwrap())
+            .cost_fn(config[2].parse::<CostKind>().unwrap());
+        assert_eq!(model.layers()[1].name(), "relu");
+        assert_eq!(model.cost_name(), "sum_squared");
+
+        let mut typed = Sequential::new()
+            .dense(3, 3)
+            .relu()
+            .dense(3, 2)
+            .sigmoid()
+            .cost::<SumSquared>();
+        for (to, from) in typed.params_mut().into_iter().zip(model.params_mut()) {
+            *to = from.clone();
+        }
+        assert_eq!(model.batch_cost(&x, &y), typed.batch_cost(&x, &y));
+    }
+
+    #[test]
+    fn closures() {
+        let slope = 0.1;
+        let leaky = ActivationFn::new(
+            "leaky",
+            move |x| if x > 0.0 { x } else { slope * x },
+            move |x| if x > 0.0 { 1.0 } else { slope },
+        );
+        let mean_abs = CostFn::new(
+            "abs",
+            |pred, actual| {
+                pred.iter()
+                    .zip(actual.iter())
+                    .map(|(x, y)| (x - y).abs())
+                    .sum()
+            },
+            |pred, actual| {
+                Matrix::new(
+                    pred.iter()
+                        .zip(actual.iter())
+                        .map(|(x, y)| (x - y).signum())
+                        .collect(),
+                    pred.w(),
+                    pred.h(),
+                )
+            },
+        );
+        assert_eq!(leaky.calc(-2.0), -0.2);
+        let model = Sequential::new()
+            .dense(2, 1)
+            .activation_fn(leaky)
+            .cost_fn(mean_abs);
+        assert_eq!(model.layers()[1].name(), "leaky");
+        assert_eq!(model.cost_name(), "abs");
+    }
+}
@@ -0,0 +1,76 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::batch;
+    use ml::algebra::Matrix;
+    use ml::nn::gradcheck::check_model;
+    use ml::nn::init::Initializer::XavierUniform;
+    use ml::nn::layers::{Layer, LayerNorm, RMSNorm};
+    use ml::nn::sequential::Sequential;
+    use ml::nn::train::Trainable;
+    const ERROR_MARGIN: f64 = 0.00001;
+
+    #[test]
+    fn normalises_each_case() {
+        let x = Matrix::new(vec![1.0, 2.0, 3.0, 4.0, -10.0, 0.0, 10.0, 20.0], 4, 2);
+        let output = LayerNorm::new(4).forward(&x);
+        for i in 0..2 {
+            let row: Vec<f64> = (0..4).map(|j| output[(i, j)]).collect();
+            let mean = row.iter().sum::<f64>() / 4.0;
+            let variance = row.iter().map(|v| (v - mean) * (v - mean)).sum::<f64>() / 4.0;
+            assert!(mean.abs() <= ERROR_MARGIN);
+            assert!((variance - 1.0).abs() < 1e-3);
+        }
+
+        let output = RMSNorm::new(4).forward(&x);
+        for i in 0..2 {
+            let mean_square = (0..4).map(|j| output[(i, j)].powi(2)).sum::<f64>() / 4.0;
+            assert!((mean_square - 1.0).abs() <= ERROR_MARGIN);
+        }
+        // doesn't centre
+        assert!((output[(0, 0)] / output[(0, 3)] - 0.25).abs() <= ERROR_MARGIN);
+
+        // a single case works the same as in a batch
+        let layer = LayerNorm::new(4);
+        let single = layer.predict(&x.clone_row(1));
+        let batch = layer.predict(&x);
+        assert!((0..4).all(|j| (single[(0, j)] - batch[(1, j)]).abs() <= ERROR_MARGIN));
+    }
+
+    #[test]
+    fn gradients() {
+        let (x, y) = batch();
+        let mut models = [
+            Sequential::new()
+                .dense_with(3, 4, &XavierUniform, 1)
+                .layer_norm(4)
+                .sigmoid()
+                .dense_with(4, 2, &XavierUniform, 2),
+            Sequential::new()
+                .dense_with(3, 4, &XavierUniform, 3)
+                .rms_norm(4)
+                .sigmoid()
+                .dense_with(4, 2, &XavierUniform, 4),
+        ];
+        for model in models.iter_mut() {
+            // gamma (and beta) away from 1 (and 0)
+            *model.params_mut()[2] = Matrix::new(vec![0.5, 1.5, -0.8, 1.2], 1, 4);
+            if model.layers()[1].name() == "layer_norm" {
+                *model.params_mut()[3] = Matrix::new(vec![0.1, -0.2, 0.3, 0.0], 1, 4);
+            }
+            for error in check_model(model, &x, &y, 1e-6) {
+                assert!(error < 1e-5, "{}", error);
+            }
+        }
+
+        // one case at a time, as in online training
+        let mut model = Sequential::new()
+            .dense_with(3, 4, &XavierUniform, 5)
+            .layer_norm(4)
+            .dense_with(4, 2, &XavierUniform, 6);
+        for error in check_model(&mut model, &x.clone_row(2), &y.clone_row(2), 1e-6) {
+            assert!(error < 1e-5, "{}", error);
+        }
+    }
+}
@@ -0,0 +1,161 @@
+#[cfg(test)]
+mod tests {
+    use ml::algebra::{MatLike, Matrix};
+    use ml::metrics::*;
+    const ERROR_MARGIN: f64 = 0.00001;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() <= ERROR_MARGIN, "{} != {}", a, b);
+    }
+
+    // targets 0 0 1 1 2 2, predicted 0 1 1 1 2 0
+    fn three_classes() -> (Matrix<f64>, Matrix<f64>) {
+        let pred = Matrix::new(
+            vec![
+                0.7, 0.2, 0.1, //
+                0.3, 0.6, 0.1, //
+                0.1, 0.8, 0.1, //
+                0.2, 0.5, 0.3, //
+                0.1, 0.2, 0.7, //
+                0.5, 0.1, 0.4,
+            ],
+            3,
+            6,
+        );
+        let y = Matrix::new(
+            vec![
+                1.0, 0.0, 0.0, //
+                1.0, 0.0, 0.0, //
+                0.0, 1.0, 0.0, //
+                0.0, 1.0, 0.0, //
+                0.0, 0.0, 1.0, //
+                0.0, 0.0, 1.0,
+            ],
+            3,
+            6,
+        );
+        (pred, y)
+    }
+
+    fn binary() -> (Matrix<f64>, Matrix<f64>) {
+        (
+            Matrix::new(vec![0.1, 0.4, 0.35, 0.8], 1, 4),
+            Matrix::new(vec![0.0, 0.0, 1.0, 1.0], 1, 4),
+        )
+    }
+
+    #[test]
+    fn classification() {
+        let (pred, y) = three_classes();
+        assert_eq!(classes(&pred), vec![0, 1, 1, 1, 2, 0]);
+        assert_close(accuracy(&pred, &y), 4.0 / 6.0);
+        assert_close(top_k_accuracy(&pred, &y, 1), 4.0 / 6.0);
+        assert_close(top_k_accuracy(&pred, &y, 2), 1.0);
+
+        let confusion = ConfusionMatrix::new(&pred, &y);
+        let counts: Vec<f64> = confusion.to_matrix().iter().copied().collect();
+        assert_eq!(counts, vec![1.0, 1.0, 0.0, 0.0, 2.0, 0.0, 1.0, 0.0, 1.0]);
+        assert_eq!(confusion.count(2, 0), 1);
+        assert_eq!(confusion.support(1), 2);
+
+        assert_close(
+            precision(&pred, &y, Average::Macro),
+            (0.5 + 2.0 / 3.0 + 1.0) / 3.0,
+        );
+        assert_close(recall(&pred, &y, Average::Macro), 2.0 / 3.0);
+        assert_close(
+            f1_score(&pred, &y, Average::Macro),
+            (0.5 + 0.8 + 2.0 / 3.0) / 3.0,
+        );
+        for average in [Average::Micro, Average::Macro, Average::Weighted] {
+            // every class has the same support, so weighted is macro
+            assert_close(
+                recall(&pred, &y, average),
+                recall(&pred, &y, Average::Macro),
+            );
+        }
+        assert_close(precision(&pred, &y, Average::Micro), 4.0 / 6.0);
+        assert_close(f1_score(&pred, &y, Average::Micro), 4.0 / 6.0);
+
+        let expected = -[0.7_f64, 0.3, 0.8, 0.5, 0.7, 0.4]
+            .iter()
+            .map(|p| p.ln())
+            .sum::<f64>()
+            / 6.0;
+        assert_close(log_loss(&pred, &y), expected);
+    }
+
+    #[test]
+    fn binary_classification() {
+        let (pred, y) = binary();
+        // predicted 0 0 0 1
+        let confusion = ConfusionMatrix::new(&pred, &y);
+        assert_eq!(confusion.classes(), 2);
+        assert_close(confusion.precision(1), 1.0);
+        assert_close(confusion.recall(1), 0.5);
+        assert_close(accuracy(&pred, &y), 0.75);
+        // class 0: precision 2/3, recall 1; class 1: precision 1, recall 1/2
+        assert_close(
+            precision(&pred, &y, Average::Weighted),
+            (2.0 / 3.0 + 1.0) / 2.0,
+        );
+        assert_close(f1_score(&pred, &y, Average::Macro), (0.8 + 2.0 / 3.0) / 2.0);
+
+        let expected = -(0.9_f64.ln() + 0.6_f64.ln() + 0.35_f64.ln() + 0.8_f64.ln()) / 4.0;
+        assert_close(log_loss(&pred, &y), expected);
+        let sure = Matrix::new(vec![0.0, 1.0], 1, 2);
+        assert!(log_loss(&sure, &Matrix::new(vec![1.0, 0.0], 1, 2)).is_finite());
+    }
+
+    #[test]
+    fn curves() {
+        let (pred, y) = binary();
+        let roc = roc_curve(&pred, &y, 0);
+        assert_eq!(roc.x, vec![0.0, 0.0, 0.5, 0.5, 1.0]);
+        assert_eq!(roc.y, vec![0.0, 0.5, 0.5, 1.0, 1.0]);
+        assert_eq!(roc.thresholds[1..], [0.8, 0.4, 0.35, 0.1]);
+        assert_close(roc_auc(&pred, &y), 0.75);
+
+        let pr = pr_curve(&pred, &y, 0);
+        assert_eq!(pr.x, vec![0.0, 0.5, 0.5, 1.0, 1.0]);
+        assert_close(pr.y[3], 2.0 / 3.0);
+        assert_close(average_precision(&pred, &y), 0.5 + 0.5 * 2.0 / 3.0);
+
+        // tied scores make a single step
+        let tied = roc_curve(
+            &Matrix::new(vec![0.5, 0.5], 1, 2),
+            &Matrix::new(vec![1.0, 0.0], 1, 2),
+            0,
+        );
+        assert_eq!(tied.x, vec![0.0, 1.0]);
+        assert_close(tied.area(), 0.5);
+
+        // a perfect ranking of every class
+        let (pred, y) = three_classes();
+        assert_close(roc_auc(&y, &y), 1.0);
+        assert_close(average_precision(&y, &y), 1.0);
+        let auc = roc_auc(&pred, &y);
+        assert!(0.5 < auc && auc < 1.0, "{}", auc);
+    }
+
+    #[test]
+    fn regression() {
+        let pred = Matrix::new(vec![2.5, 0.0, 2.0, 8.0], 1, 4);
+        let y = Matrix::new(vec![3.0, -0.5, 2.0, 7.0], 1, 4);
+        assert_close(mse(&pred, &y), 0.375);
+        assert_close(rmse(&pred, &y), 0.375_f64.sqrt());
+        assert_close(mae(&pred, &y), 0.5);
+        assert_close(r2(&pred, &y), 0.9486081370449679);
+        assert_close(explained_variance(&pred, &y), 0.9571734475374732);
+
+        // an offset costs r2 but not explained variance
+        let offset = y.apply(|y| y + 1.0);
+        assert_close(explained_variance(&offset, &y), 1.0);
+        assert!(r2(&offset, &y) < 1.0);
+
+        // columns are scored separately, then averaged
+        let two = Matrix::new(vec![1.0, 10.0, 2.0, 20.0, 3.0, 30.0], 2, 3);
+        let two_pred = Matrix::new(vec![1.0, 12.0, 2.0, 20.0, 3.0, 28.0], 2, 3);
+        assert_close(r2(&two_pred, &two), (1.0 + (1.0 - 8.0 / 200.0)) / 2.0);
+    }
+}
@@ -0,0 +1,86 @@
+#[cfg(test)]
+mod tests {
+    use ml::algebra::{MatLike, Matrix};
+    use ml::nn::optim::*;
+    use ml::nn::train::{TrainConfig, Trainer};
+    use ml::nn::{activations::Sigmoid, cost::SumSquared, feedforward::FFNet};
+
+    // f(p) = sum over i of scale_i * (p_i - target_i)^2, badly conditioned on purpose
+    const TARGET: [f64; 3] = [1.0, -2.0, 0.5];
+    const SCALE: [f64; 3] = [1.0, 10.0, 0.1];
+
+    fn minimize<O: Optimizer>(mut optimizer: O, learning_rate: f64, steps: usize) -> Matrix<f64> {
+        let mut param = Matrix::new(vec![0.0; 3], 1, 3);
+        for _ in 0..steps {
+            let grad = Matrix::new(
+                (0..3)
+                    .map(|i| 2.0 * SCALE[i] * (param[(i, 0)] - TARGET[i]))
+                    .collect(),
+                1,
+                3,
+            );
+            optimizer.step(&mut [&mut param], &[grad], learning_rate);
+        }
+        param
+    }
+
+    fn assert_converged(param: &Matrix<f64>, tolerance: f64) {
+        for (p, t) in param.iter().zip(TARGET) {
+            assert!((p - t).abs() < tolerance, "{} != {}", p, t);
+        }
+    }
+
+    #[test]
+    fn quadratic() {
+        assert_converged(&minimize(Sgd, 0.04, 2000), 1e-3);
+        assert_converged(&minimize(Momentum::default(), 0.01, 2000), 1e-3);
+        assert_converged(&minimize(Nesterov::default(), 0.01, 2000), 1e-3);
+        assert_converged(&minimize(RMSProp::default(), 0.01, 2000), 2e-2);
+        assert_converged(&minimize(AdaGrad::default(), 0.5, 2000), 1e-3);
+        assert_converged(&minimize(Adam::default(), 0.05, 2000), 1e-3);
+        // decay pulls the minimum towards 0, so only check it ends up between the two
+        let param = minimize(AdamW::new(Adam::default(), 0.01), 0.05, 2000);
+        for (p, t) in param.iter().zip(TARGET) {
+            assert!(p.abs() < t.abs() && p * t > 0.0, "{} vs {}", p, t);
+        }
+    }
+
+    #[test]
+    fn state_is_per_param() {
+        // the same gradient history on two params must give the same result as on one
+        let mut optimizer = Adam::default();
+        let mut a = Matrix::new(vec![1.0], 1, 1);
+        let mut b = Matrix::new(vec![1.0, 2.0], 2, 1);
+        for _ in 0..5 {
+            let grads = [
+                Matrix::new(vec![a[(0, 0)]], 1, 1),
+                Matrix::new(vec![-1.0, 3.0], 2, 1),
+            ];
+            optimizer.step(&mut [&mut a, &mut b], &grads, 0.1);
+        }
+        assert_eq!(b.w(), 2);
+        let mut lone = Adam::default();
+        let mut c = Matrix::new(vec![1.0], 1, 1);
+        for _ in 0..5 {
+            let grad = Matrix::new(vec![c[(0, 0)]], 1, 1);
+            lone.step(&mut [&mut c], &[grad], 0.1);
+        }
+        assert_eq!(a[(0, 0)], c[(0, 0)]);
+    }
+
+    #[test]
+    fn trainer_uses_optimizer() {
+        let x = Matrix::new(vec![0.0, 0.0, 0.0, 1.0, 1.0, 0.0, 1.0, 1.0], 2, 4);
+        let y = Matrix::new(vec![0.1, 0.9, 0.9, 0.9], 1, 4);
+        let mut net = FFNet::<Sigmoid, SumSquared>::with_seed(vec![2, 1], 0);
+        let history = Trainer::new(TrainConfig {
+            epochs: 200,
+            batch_size: 4,
+            shuffle_seed: None,
+            learning_rate: 0.05,
+        })
+        .optimizer(Adam::default())
+        .fit(&mut net, &x, &y);
+        assert!(history.loss[199] < 0.01, "{:?}", history.loss.last());
+    }
+}
@@ -0,0 +1,86 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::batch;
+    use ml::algebra::{MatLike, Matrix};
+    use ml::nn::gradcheck::check_model;
+    use ml::nn::init::Initializer::XavierUniform;
+    use ml::nn::layers::{Layer, LearnableSwish, Maxout, PReLU};
+    use ml::nn::sequential::Sequential;
+    use ml::nn::train::{TrainConfig, Trainable, Trainer};
+    const ERROR_MARGIN: f64 = 0.00001;
+
+    #[test]
+    fn gradients() {
+        let (x, y) = batch();
+        let mut models = [
+            Sequential::new()
+                .dense_with(3, 4, &XavierUniform, 1)
+                .layer(PReLU::new(4))
+                .dense_with(4, 2, &XavierUniform, 2),
+            Sequential::new()
+                .dense_with(3, 4, &XavierUniform, 3)
+                .layer(LearnableSwish::new(4))
+                .dense_with(4, 2, &XavierUniform, 4),
+            Sequential::new()
+                .layer(Maxout::with_initializer(3, 4, 3, &XavierUniform, 6))
+                .dense_with(4, 2, &XavierUniform, 5),
+            Sequential::with_seed(7).maxout(3, 4, 3).dense(4, 2),
+        ];
+        // seeded by the model
+        let mut again = Sequential::with_seed(7).maxout(3, 4, 3).dense(4, 2);
+        assert!(again
+            .params_mut()
+            .into_iter()
+            .zip(models[3].params_mut())
+            .all(|(a, b)| a.iter().eq(b.iter())));
+        for model in models.iter_mut() {
+            for error in check_model(model, &x, &y, 1e-6) {
+                assert!(error < 1e-5, "{}", error);
+            }
+        }
+    }
+
+    #[test]
+    fn forward() {
+        let x = Matrix::new(vec![-2.0, 3.0, 1.0, -0.5], 2, 2);
+        let prelu = PReLU::new(2).predict(&x);
+        for (a, b) in prelu.iter().zip([-0.5, 3.0, 1.0, -0.125]) {
+            assert!((a - b).abs() <= ERROR_MARGIN);
+        }
+
+        let mut maxout = Maxout::new(2, 1, 2);
+        // pieces x0 and x1
+        *maxout.params_mut()[0] = Matrix::new(vec![1.0, 0.0, 0.0, 1.0], 2, 2);
+        *maxout.params_mut()[1] = Matrix::new_uniform(0.0, 1, 2);
+        let output = maxout.forward(&x);
+        assert_eq!((output.w(), output.h()), (1, 2));
+        assert_eq!(output[(0, 0)], 3.0);
+        assert_eq!(output[(1, 0)], 1.0);
+        let (cost_wrt_input, _) = maxout.backward(&Matrix::new(vec![1.0, 1.0], 1, 2));
+        assert_eq!(cost_wrt_input[(0, 0)], 0.0);
+        assert_eq!(cost_wrt_input[(0, 1)], 1.0);
+        assert_eq!(cost_wrt_input[(1, 0)], 1.0);
+    }
+
+    #[test]
+    fn trained_with_weights() {
+        // y = x below 0, so the slope should head from 0.25 towards 1
+        let x = Matrix::new((0..20).map(|i| -(i as f64) / 10.0).collect(), 1, 20);
+        let y = x.clone();
+        let mut model = Sequential::new().layer(PReLU::new(1));
+        Trainer::new(TrainConfig {
+            epochs: 20,
+            batch_size: 5,
+            shuffle_seed: Some(0),
+            learning_rate: 0.1,
+        })
+        .fit(&mut model, &x, &y);
+        let slope = model.params_mut()[0][(0, 0)];
+        assert!((slope - 1.0).abs() < 0.05, "{}", slope);
+
+        let swish = LearnableSwish::new(3);
+        assert!(swish.betas().iter().all(|&beta| beta == 1.0));
+    }
+}
@@ -0,0 +1,180 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::inputs;
+    use ml::algebra::{MatLike, Matrix};
+    use ml::nn::gradcheck::check_model;
+    use ml::nn::init::Initializer::XavierUniform;
+    use ml::nn::layers::{Cell, GRUCell, LSTMCell, Layer, RNNCell, Recurrent};
+    use ml::nn::sequential::Sequential;
+    use ml::nn::train::{TrainConfig, Trainable, Trainer};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    const ERROR_MARGIN: f64 = 0.00001;
+
+    fn assert_close(a: &Matrix<f64>, b: &Matrix<f64>) {
+        assert_eq!((a.w(), a.h()), (b.w(), b.h()));
+        for (x, y) in a.iter().zip(b.iter()) {
+            assert!((x - y).abs() <= ERROR_MARGIN, "{:?} != {:?}", a, b);
+        }
+    }
+
+    // 3 cases of 4 steps of 2 features, the last padded with 0s after 2 steps
+    fn sequences() -> Matrix<f64> {
+        let values: Vec<f64> = inputs().iter().copied().collect();
+        let mirrored = values.iter().rev().take(8).map(|v| -v);
+        let data = values.iter().copied().chain(mirrored).chain([0.0; 4]);
+        Matrix::new(data.collect(), 8, 3)
+    }
+
+    fn layers<C: Cell>(cell: impl Fn() -> C) -> [Recurrent<C>; 2] {
+        [
+            Recurrent::new(cell(), 4).mask_value(0.0),
+            Recurrent::new(cell(), 4).mask_value(0.0).many_to_many(),
+        ]
+    }
+
+    fn check<C: Cell + 'static>(layers: [Recurrent<C>; 2]) {
+        let x = sequences();
+        let y = Matrix::new(vec![0.1, 0.9, 0.4, 0.6, 0.8, 0.2], 2, 3);
+        for layer in layers {
+            let features = layer.output_size();
+            let mut model = Sequential::new()
+                .layer(layer)
+                .dense_with(features, 2, &XavierUniform, 7)
+                .sigmoid();
+            for error in check_model(&mut model, &x, &y, 1e-6) {
+                assert!(error < 1e-5, "{} {}", model.layers()[0].name(), error);
+            }
+        }
+    }
+
+    #[test]
+    fn gradients() {
+        check(layers(|| RNNCell::with_seed(2, 3, 1)));
+        check(layers(|| LSTMCell::with_seed(2, 3, 2)));
+        check(layers(|| GRUCell::with_seed(2, 3, 3)));
+    }
+
+    #[test]
+    fn steps() {
+        // state' = tanh(input * 0.5 + state * 0.25 + 0.1)
+        let mut layer = Recurrent::new(RNNCell::with_seed(1, 1, 0), 2).many_to_many();
+        for (param, value) in layer.params_mut().into_iter().zip([0.5, 0.25, 0.1]) {
+            *param = Matrix::new(vec![value], 1, 1);
+        }
+        let first = (0.5_f64 * 2.0 + 0.1).tanh();
+        let second = (-0.5 + 0.25 * first + 0.1).tanh();
+        let output = layer.predict(&Matrix::new(vec![2.0, -1.0], 2, 1));
+        assert_close(&output, &Matrix::new(vec![first, second], 2, 1));
+
+        // hidden states only; an LSTM's cell state stays inside
+        let lstm = Recurrent::new(LSTMCell::with_seed(2, 3, 0), 4);
+        assert_eq!(lstm.cell().state_size(), 6);
+        assert_eq!(lstm.output_size(), 3);
+        assert_eq!(lstm.many_to_many().output_size(), 12);
+    }
+
+    #[test]
+    fn masks() {
+        let x = sequences();
+        let padded = x.clone_row(2);
+        let short = Matrix::new(padded.iter().take(4).copied().collect(), 4, 1);
+        for seed in 0..3 {
+            let [mut many_to_one, mut many_to_many] = layers(|| GRUCell::with_seed(2, 3, seed));
+            let unpadded = Recurrent::new(GRUCell::with_seed(2, 3, seed), 2);
+            // padding carries the last real state through
+            let last = unpadded.predict(&short);
+            assert_close(&many_to_one.forward(&padded), &last);
+
+            // and outputs 0s
+            let unpadded = unpadded.many_to_many();
+            let mut expected: Vec<f64> = unpadded.predict(&short).iter().copied().collect();
+            expected.extend([0.0; 6]);
+            assert_close(
+                &many_to_many.forward(&padded),
+                &Matrix::new(expected, 12, 1),
+            );
+
+            // and padded steps get no gradient
+            let (cost_wrt_input, _) = many_to_one.backward(&Matrix::new_uniform(1.0, 3, 1));
+            assert!((4..8).all(|j| cost_wrt_input[(0, j)] == 0.0));
+            assert!((0..4).any(|j| cost_wrt_input[(0, j)] != 0.0));
+        }
+        // without a mask value, 0s are just inputs
+        let unmasked = Recurrent::new(GRUCell::with_seed(2, 3, 0), 4);
+        let masked = Recurrent::new(GRUCell::with_seed(2, 3, 0), 4).mask_value(0.0);
+        assert!(unmasked
+            .predict(&padded)
+            .iter()
+            .zip(masked.predict(&padded).iter())
+            .any(|(a, b)| (a - b).abs() > ERROR_MARGIN));
+    }
+
+    #[test]
+    fn truncates() {
+        let x = sequences().select_rows(&[0, 1]);
+        let cost_wrt_output = Matrix::new(vec![0.3, -0.2, 0.5, 0.1, 0.4, -0.6], 3, 2);
+        let backward = |mut layer: Recurrent<LSTMCell>| {
+            layer.forward(&x);
+            layer.backward(&cost_wrt_output)
+        };
+        let full = backward(Recurrent::new(LSTMCell::with_seed(2, 3, 4), 4));
+        let whole = backward(Recurrent::new(LSTMCell::with_seed(2, 3, 4), 4).truncate(4));
+        assert_close(&full.0, &whole.0);
+        for (a, b) in full.1.iter().zip(&whole.1) {
+            assert_close(a, b);
+        }
+
+        // only the last 2 steps are learned from
+        let (cost_wrt_input, grads) =
+            backward(Recurrent::new(LSTMCell::with_seed(2, 3, 4), 4).truncate(2));
+        for i in 0..2 {
+            assert!((0..4).all(|j| cost_wrt_input[(i, j)] == 0.0));
+            for j in 4..8 {
+                assert!((cost_wrt_input[(i, j)] - full.0[(i, j)]).abs() <= ERROR_MARGIN);
+            }
+        }
+        assert!(grads[0]
+            .iter()
+            .zip(full.1[0].iter())
+            .any(|(a, b)| (a - b).abs() > ERROR_MARGIN));
+    }
+
+    // trains on whether the first of 5 steps was positive
+    fn remembers<C: Cell + 'static>(layer: Recurrent<C>) {
+        let mut rng = StdRng::seed_from_u64(0);
+        let x = Matrix::new(
+            (0..5 * 32).map(|_| rng.gen_range(-1.0..1.0)).collect(),
+            5,
+            32,
+        );
+        let y = Matrix::new(
+            (0..32).map(|i| (x[(i, 0)] > 0.0) as u8 as f64).collect(),
+            1,
+            32,
+        );
+        let name = layer.name();
+        let mut model = Sequential::new()
+            .layer(layer)
+            .dense_with(4, 1, &XavierUniform, 1)
+            .sigmoid();
+        let before = model.batch_cost(&x, &y);
+        Trainer::new(TrainConfig {
+            epochs: 150,
+            batch_size: 8,
+            shuffle_seed: Some(0),
+            learning_rate: 1.0,
+        })
+        .fit(&mut model, &x, &y);
+        let after = model.batch_cost(&x, &y);
+        assert!(after < before * 0.3, "{}: {} -> {}", name, before, after);
+    }
+
+    #[test]
+    fn learns_to_remember() {
+        remembers(Recurrent::new(RNNCell::with_seed(1, 4, 0), 5));
+        remembers(Recurrent::new(LSTMCell::with_seed(1, 4, 0), 5));
+        remembers(Recurrent::new(GRUCell::with_seed(1, 4, 0), 5));
+    }
+}
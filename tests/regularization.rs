@@ -0,0 +1,93 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::batch;
+    use ml::algebra::{MatLike, Matrix};
+    use ml::nn::gradcheck::{check_model, check_net};
+    use ml::nn::regularization::Regularization;
+    use ml::nn::train::{TrainConfig, Trainable, Trainer};
+    use ml::nn::{activations::*, cost::*, feedforward::FFNet};
+    const ERROR_MARGIN: f64 = 0.00001;
+
+    #[test]
+    fn penalty() {
+        let weights = Matrix::new(vec![1.0, -2.0, 0.0, 3.0], 2, 2);
+        let expected = [
+            (Regularization::none(), 0.0),
+            (Regularization::l1(0.1), 0.6),
+            (Regularization::l2(0.1), 0.7),
+            (Regularization::elastic_net(0.2, 0.5), 1.3),
+        ];
+        for (regularization, penalty) in expected {
+            assert!((regularization.penalty(&weights) - penalty).abs() <= ERROR_MARGIN);
+        }
+        let grad = Regularization::elastic_net(0.2, 0.5).grad(&weights);
+        for (g, expected) in grad.iter().zip([0.2, -0.3, 0.0, 0.4]) {
+            assert!((g - expected).abs() <= ERROR_MARGIN);
+        }
+        assert!(!Regularization::l2(0.1).biases);
+        assert!(Regularization::l2(0.1).with_biases().biases);
+    }
+
+    #[test]
+    fn added_to_loss_and_gradients() {
+        let (x, y) = batch();
+        let mut net = FFNet::<Sigmoid, SumSquared>::new(vec![3, 4, 2]);
+        // fixed params, so the check doesn't depend on a lucky init
+        for (n, param) in net.params_mut().into_iter().enumerate() {
+            for (k, p) in param.iter_mut().enumerate() {
+                *p = ((n * 5 + k * 3) % 7) as f64 / 4.0 - 0.75;
+            }
+        }
+        let (plain, plain_grads) = net.batch_grad(&x, &y);
+
+        // per layer, biases only where asked for
+        net.set_regularization(0, Regularization::elastic_net(0.05, 0.3));
+        net.set_regularization(1, Regularization::l2(0.1).with_biases());
+        let penalty = net.penalty();
+        assert!(penalty > 0.0);
+        for autograd in [false, true] {
+            net.set_autograd(autograd);
+            let (cost, grads) = net.batch_grad(&x, &y);
+            assert!((cost - plain - penalty).abs() <= ERROR_MARGIN);
+            assert!((net.batch_cost(&x, &y) - cost).abs() <= ERROR_MARGIN);
+            // layer 0's biases aren't penalised
+            assert!(grads[1]
+                .iter()
+                .zip(plain_grads[1].iter())
+                .all(|(a, b)| (a - b).abs() <= ERROR_MARGIN));
+            assert!(grads[3]
+                .iter()
+                .zip(plain_grads[3].iter())
+                .any(|(a, b)| (a - b).abs() > ERROR_MARGIN));
+            for error in check_model(&mut net, &x, &y, 1e-6) {
+                assert!(error < 1e-5, "{}", error);
+            }
+            let errors = check_net(&mut net, x.clone_row(0).transpose(), &y.clone_row(0), 1e-6);
+            for error in errors {
+                assert!(error.worst() < 1e-5, "{:?}", error);
+            }
+        }
+    }
+
+    #[test]
+    fn shrinks_weights() {
+        let (x, y) = batch();
+        let norm = |regularization: Regularization| {
+            let mut net = FFNet::<ReLU, SumSquared>::new(vec![3, 4, 2]);
+            net.set_regularization_all(regularization);
+            Trainer::new(TrainConfig {
+                epochs: 100,
+                batch_size: 4,
+                shuffle_seed: None,
+                learning_rate: 0.05,
+            })
+            .fit(&mut net, &x, &y);
+            net.params_mut()[0].iter().map(|w| w * w).sum::<f64>()
+        };
+        let plain = norm(Regularization::none());
+        assert!(norm(Regularization::l2(0.5)) < plain * 0.5);
+        assert!(norm(Regularization::l1(0.5)) < plain * 0.5);
+    }
+}
@@ -0,0 +1,199 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::inputs;
+    use ml::algebra::{MatLike, Matrix};
+    use ml::nn::json::Json;
+    use ml::nn::{activations::*, cost::*, feedforward::FFNet, ModelError};
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("ml-save-{}-{}", std::process::id(), name))
+    }
+
+    fn same_params(a: &mut FFNet<ReLU, SumSquared>, b: &mut FFNet<ReLU, SumSquared>) -> bool {
+        a.params_mut()
+            .into_iter()
+            .zip(b.params_mut())
+            .all(|(a, b)| (a.w(), a.h()) == (b.w(), b.h()) && a.iter().eq(b.iter()))
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut net = FFNet::<ReLU, SumSquared>::with_seed(vec![3, 3, 2], 0);
+        // biases aren't 0 after training, and awkward values must survive exactly
+        *net.params_mut()[1] = Matrix::new(vec![0.1, -1e-300, 1.0 / 3.0], 1, 3);
+
+        let binary = temp_path("round_trip.bin");
+        net.save(&binary).unwrap();
+        let mut loaded = FFNet::<ReLU, SumSquared>::load(&binary).unwrap();
+        std::fs::remove_file(&binary).unwrap();
+        assert_eq!(loaded.shape(), vec![3, 3, 2]);
+        assert!(same_params(&mut net, &mut loaded));
+
+        let json = temp_path("round_trip.json");
+        net.save_json(&json).unwrap();
+        let mut loaded = FFNet::<ReLU, SumSquared>::load_json(&json).unwrap();
+        std::fs::remove_file(&json).unwrap();
+        assert!(same_params(&mut net, &mut loaded));
+
+        let x = inputs();
+        assert!(net.predict(&x).iter().eq(loaded.predict(&x).iter()));
+    }
+
+    #[test]
+    fn self_describing() {
+        let net = FFNet::<ReLU, SumSquared>::with_seed(vec![2, 1], 0);
+        let mut bytes = Vec::new();
+        net.write_to(&mut bytes).unwrap();
+        assert_eq!(&bytes[0..4], b"MLFF");
+        assert_eq!(u32::from_le_bytes(bytes[4..8].try_into().unwrap()), 1);
+        // names, shape, then 2 weights and 1 bias
+        let header = 8 + (4 + 4) + (4 + 11) + 4 + 2 * 8;
+        assert_eq!(bytes.len(), header + 3 * 8);
+
+        let json = Json::parse(&net.to_json().unwrap()).unwrap();
+        assert_eq!(json.get("activation").and_then(Json::as_str), Some("relu"));
+        assert_eq!(json.get("cost").and_then(Json::as_str), Some("sum_squared"));
+        assert_eq!(json.get("version").and_then(Json::as_usize), Some(1));
+    }
+
+    #[test]
+    fn mismatches() {
+        let net = FFNet::<Sigmoid, SumSquared>::with_seed(vec![2, 2], 0);
+        let mut bytes = Vec::new();
+        net.write_to(&mut bytes).unwrap();
+
+        let error = FFNet::<ReLU, SumSquared>::read_from(&mut bytes.as_slice())
+            .err()
+            .unwrap();
+        assert!(matches!(
+            error,
+            ModelError::Mismatch {
+                field: "activation",
+                ..
+            }
+        ));
+        assert_eq!(
+            error.to_string(),
+            "model activation mismatch: expected \"relu\", file has \"sigmoid\""
+        );
+        let error = FFNet::<Sigmoid, MeanSquared>::from_json(&net.to_json().unwrap())
+            .err()
+            .unwrap();
+        assert!(matches!(error, ModelError::Mismatch { field: "cost", .. }));
+
+        let mut future = bytes.clone();
+        future[4] = 2;
+        let error = FFNet::<Sigmoid, SumSquared>::read_from(&mut future.as_slice())
+            .err()
+            .unwrap();
+        assert!(
+            error.to_string().contains("unsupported version 2"),
+            "{}",
+            error
+        );
+        let error = FFNet::<Sigmoid, SumSquared>::read_from(&mut &b"not a model"[..])
+            .err()
+            .unwrap();
+        assert!(matches!(error, ModelError::Format(_)));
+        let truncated = &bytes[..bytes.len() - 3];
+        let error = FFNet::<Sigmoid, SumSquared>::read_from(&mut &truncated[..])
+            .err()
+            .unwrap();
+        assert!(matches!(error, ModelError::Format(_)), "{}", error);
+        let error = FFNet::<Sigmoid, SumSquared>::load(temp_path("missing.bin"))
+            .err()
+            .unwrap();
+        assert!(matches!(error, ModelError::Io(_)));
+    }
+
+    #[test]
+    fn corrupt_sizes() {
+        // sizes far too large to allocate must fail to load, not abort
+        let net = FFNet::<ReLU, SumSquared>::with_seed(vec![2, 1], 0);
+        let mut bytes = Vec::new();
+        net.write_to(&mut bytes).unwrap();
+        let shape = 8 + (4 + 4) + (4 + 11) + 4;
+        for entry in bytes[shape..shape + 16].chunks_exact_mut(8) {
+            entry.copy_from_slice(&(1u64 << 30).to_le_bytes());
+        }
+        let error = FFNet::<ReLU, SumSquared>::read_from(&mut bytes.as_slice())
+            .err()
+            .unwrap();
+        assert!(matches!(error, ModelError::Format(_)), "{}", error);
+
+        let json = net
+            .to_json()
+            .unwrap()
+            .replace("\"shape\":[2.0,1.0]", "\"shape\":[1e12,1e12]");
+        let error = FFNet::<ReLU, SumSquared>::from_json(&json).err().unwrap();
+        assert!(matches!(error, ModelError::Format(_)), "{}", error);
+    }
+
+    #[test]
+    fn crafted_shapes() {
+        // a header for `shape`, followed by no params at all
+        let header = |shape: &[u64]| {
+            let mut bytes = Vec::new();
+            FFNet::<ReLU, SumSquared>::with_seed(vec![1, 1], 0)
+                .write_to(&mut bytes)
+                .unwrap();
+            let names = 8 + (4 + 4) + (4 + 11);
+            bytes.truncate(names);
+            bytes.extend_from_slice(&(shape.len() as u32).to_le_bytes());
+            for size in shape {
+                bytes.extend_from_slice(&size.to_le_bytes());
+            }
+            bytes
+        };
+        // an empty layer leaves nothing to read, so only the shape could give it away
+        for shape in [
+            vec![4_000_000_000_000_000, 0],
+            vec![0, 4_000_000_000_000_000],
+            vec![3, 0, 2],
+            vec![1 << 62, 1 << 62],
+            vec![4],
+        ] {
+            let error = FFNet::<ReLU, SumSquared>::read_from(&mut header(&shape).as_slice())
+                .err()
+                .unwrap();
+            assert!(matches!(error, ModelError::Format(_)), "{}", error);
+        }
+
+        let json = r#"{"format":"ml-ffnet","version":1,"activation":"relu","cost":"sum_squared",
+            "shape":[4000000000000000,0],"layers":[{"weights":[],"biases":[]}]}"#;
+        let error = FFNet::<ReLU, SumSquared>::from_json(json).err().unwrap();
+        assert!(matches!(error, ModelError::Format(_)), "{}", error);
+        assert!(error.to_string().contains("positive"), "{}", error);
+    }
+
+    #[test]
+    fn non_finite() {
+        let mut net = FFNet::<ReLU, SumSquared>::with_seed(vec![2, 2], 0);
+        *net.params_mut()[1] = Matrix::new(vec![f64::NAN, f64::NEG_INFINITY], 1, 2);
+
+        // JSON can't hold them, so it isn't written at all
+        let error = net.to_json().err().unwrap();
+        assert!(
+            matches!(error, ModelError::NonFinite(ref what) if what == "layer 0 biases"),
+            "{}",
+            error
+        );
+        let json = temp_path("non_finite.json");
+        assert!(matches!(
+            net.save_json(&json),
+            Err(ModelError::NonFinite(_))
+        ));
+        assert!(!json.exists());
+
+        // the binary format keeps them bit for bit
+        let mut bytes = Vec::new();
+        net.write_to(&mut bytes).unwrap();
+        let mut loaded = FFNet::<ReLU, SumSquared>::read_from(&mut bytes.as_slice()).unwrap();
+        let biases = loaded.params_mut()[1].clone();
+        assert!(biases[(0, 0)].is_nan());
+        assert_eq!(biases[(1, 0)], f64::NEG_INFINITY);
+    }
+}
@@ -0,0 +1,85 @@
+#[cfg(test)]
+mod tests {
+    use ml::algebra::Matrix;
+    use ml::nn::schedule::*;
+    use ml::nn::train::{TrainConfig, Trainer};
+    use ml::nn::{activations::Sigmoid, cost::SumSquared, feedforward::FFNet};
+    const ERROR_MARGIN: f64 = 0.00001;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() <= ERROR_MARGIN, "{} != {}", a, b);
+    }
+
+    fn at(step: usize, steps_per_epoch: usize) -> Progress {
+        Progress {
+            step,
+            epoch: step / steps_per_epoch,
+            steps_per_epoch,
+        }
+    }
+
+    #[test]
+    fn decays() {
+        let step = StepDecay::new(2, 0.5);
+        assert_close(step.learning_rate(1.0, at(10, 10)), 1.0);
+        assert_close(step.learning_rate(1.0, at(20, 10)), 0.5);
+        assert_close(step.learning_rate(1.0, at(59, 10)), 0.25);
+
+        let exponential = ExponentialDecay::new(0.9);
+        assert_close(exponential.learning_rate(2.0, at(30, 10)), 2.0 * 0.729);
+    }
+
+    #[test]
+    fn cosine_restarts() {
+        let cosine = CosineAnnealing::new(2.0, 2.0, 0.1);
+        assert_close(cosine.learning_rate(1.0, at(0, 10)), 1.0);
+        assert_close(cosine.learning_rate(1.0, at(10, 10)), 0.55);
+        // restart after 2 epochs, then a period of 4
+        assert_close(cosine.learning_rate(1.0, at(20, 10)), 1.0);
+        assert_close(cosine.learning_rate(1.0, at(40, 10)), 0.55);
+        assert_close(cosine.learning_rate(1.0, at(60, 10)), 1.0);
+    }
+
+    #[test]
+    fn warmup_and_one_cycle() {
+        let warmup = LinearWarmup::new(4, Constant);
+        assert_close(warmup.learning_rate(1.0, at(0, 10)), 0.25);
+        assert_close(warmup.learning_rate(1.0, at(3, 10)), 1.0);
+        assert_close(warmup.learning_rate(1.0, at(8, 10)), 1.0);
+
+        let one_cycle = OneCycle::new(100);
+        assert_close(one_cycle.learning_rate(1.0, at(0, 10)), 1.0 / 25.0);
+        assert_close(one_cycle.learning_rate(1.0, at(30, 10)), 1.0);
+        assert_close(one_cycle.learning_rate(1.0, at(100, 10)), 1.0 / 25e4);
+        assert!(one_cycle.learning_rate(1.0, at(60, 10)) < 1.0);
+    }
+
+    #[test]
+    fn plateau() {
+        let mut plateau = ReduceOnPlateau::new(0.5, 2);
+        for loss in [1.0, 0.8, 0.8, 0.8] {
+            plateau.end_epoch(loss);
+        }
+        assert_close(plateau.learning_rate(1.0, at(0, 10)), 0.5);
+        plateau.end_epoch(0.1);
+        plateau.end_epoch(0.1);
+        assert_close(plateau.learning_rate(1.0, at(0, 10)), 0.5);
+    }
+
+    #[test]
+    fn recorded_in_history() {
+        let x = Matrix::new(vec![0.0, 1.0, 2.0, 3.0], 1, 4);
+        let y = Matrix::new(vec![0.2, 0.4, 0.6, 0.8], 1, 4);
+        let mut net = FFNet::<Sigmoid, SumSquared>::new(vec![1, 1]);
+        let history = Trainer::new(TrainConfig {
+            epochs: 3,
+            batch_size: 2,
+            shuffle_seed: None,
+            learning_rate: 0.1,
+        })
+        .schedule(ExponentialDecay::new(0.5))
+        .fit(&mut net, &x, &y);
+        assert_eq!(history.learning_rate, vec![0.1, 0.05, 0.025]);
+        assert!(history.schedule.contains("ExponentialDecay"));
+    }
+}
@@ -0,0 +1,79 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::batch;
+    use ml::algebra::MatLike;
+    use ml::nn::gradcheck::check_model;
+    use ml::nn::init::Initializer::XavierUniform;
+    use ml::nn::sequential::Sequential;
+    use ml::nn::train::{TrainConfig, Trainable, Trainer};
+    use ml::nn::{activations::*, cost::SumSquared, feedforward::FFNet};
+    const ERROR_MARGIN: f64 = 0.00001;
+
+    #[test]
+    fn matches_ffnet() {
+        let (x, y) = batch();
+        let mut net = FFNet::<Sigmoid, SumSquared>::new(vec![3, 4, 2]);
+        net.randomize_params();
+        let mut model = Sequential::new()
+            .dense(3, 4)
+            .sigmoid()
+            .dense(4, 2)
+            .sigmoid();
+        for (to, from) in model.params_mut().into_iter().zip(net.params_mut()) {
+            *to = from.clone();
+        }
+
+        let (a, b) = (model.predict(&x), net.predict(&x));
+        assert!(a
+            .iter()
+            .zip(b.iter())
+            .all(|(a, b)| (a - b).abs() <= ERROR_MARGIN));
+        let ((model_cost, model_grads), (net_cost, net_grads)) =
+            (model.batch_grad(&x, &y), net.batch_grad(&x, &y));
+        assert!((model_cost - net_cost).abs() <= ERROR_MARGIN);
+        for (a, b) in model_grads.iter().zip(net_grads.iter()) {
+            assert_eq!((a.w(), a.h()), (b.w(), b.h()));
+            assert!(a
+                .iter()
+                .zip(b.iter())
+                .all(|(a, b)| (a - b).abs() <= ERROR_MARGIN));
+        }
+    }
+
+    #[test]
+    fn mixed_activations() {
+        let (x, y) = batch();
+        // relu hidden layers can end in a sigmoid, or no activation at all
+        let mut model = Sequential::new()
+            .dense_with(3, 5, &XavierUniform, 1)
+            .relu()
+            .dense_with(5, 4, &XavierUniform, 2)
+            .softplus()
+            .dense_with(4, 2, &XavierUniform, 3)
+            .sigmoid();
+        assert_eq!(model.layers().len(), 6);
+        assert_eq!(model.layers()[1].name(), "relu");
+        for error in check_model(&mut model, &x, &y, 1e-6) {
+            assert!(error < 1e-5, "{}", error);
+        }
+
+        let mut linear = Sequential::new()
+            .dense_with(3, 4, &XavierUniform, 7)
+            .sigmoid()
+            .dense_with(4, 2, &XavierUniform, 8);
+        let history = Trainer::new(TrainConfig {
+            epochs: 200,
+            batch_size: 4,
+            shuffle_seed: Some(0),
+            learning_rate: 0.1,
+        })
+        .fit(&mut linear, &x, &y);
+        assert!(
+            history.loss[199] < history.loss[0] * 0.2,
+            "{:?}",
+            history.loss
+        );
+    }
+}
@@ -0,0 +1,123 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::classes;
+    use ml::algebra::{MatLike, Matrix};
+    use ml::nn::activations::{Activation, ActivationFn, Sigmoid};
+    use ml::nn::cost::{CategoricalCrossEntropy, CostFn, NegativeLogLikelihood};
+    use ml::nn::gradcheck::{check_cost, check_model};
+    use ml::nn::init::Initializer::XavierUniform;
+    use ml::nn::layers::{log_softmax, softmax, Layer, OutputKind, Softmax};
+    use ml::nn::sequential::Sequential;
+    use ml::nn::train::Trainable;
+    const ERROR_MARGIN: f64 = 0.00001;
+
+    #[test]
+    fn stable() {
+        let logits = Matrix::new(vec![1000.0, 999.0, -1000.0, 0.0, 0.0, 0.0], 3, 2);
+        let p = softmax(&logits);
+        let log_p = log_softmax(&logits);
+        assert!(p.iter().chain(log_p.iter()).all(|v| v.is_finite()));
+        for i in 0..2 {
+            let total: f64 = (0..3).map(|j| p[(i, j)]).sum();
+            assert!((total - 1.0).abs() <= ERROR_MARGIN);
+        }
+        assert!((p[(0, 0)] - 1.0 / (1.0 + (-1.0_f64).exp())).abs() <= ERROR_MARGIN);
+        assert!((log_p[(0, 1)] + (1.0 + 1.0_f64.exp()).ln()).abs() <= ERROR_MARGIN);
+        assert!((p[(1, 2)] - 1.0 / 3.0).abs() <= ERROR_MARGIN);
+    }
+
+    #[test]
+    fn fused_matches_chain() {
+        let (x, y) = classes();
+        let mut fused = Sequential::new()
+            .dense(3, 3)
+            .softmax()
+            .cost::<CategoricalCrossEntropy>();
+        // a closure cost with the same maths doesn't trigger the fused grad
+        let mut chained = Sequential::new().dense(3, 3).softmax().cost_fn(CostFn::new(
+            "cce",
+            |pred, actual| CostFn::of::<CategoricalCrossEntropy>().calc(pred, actual),
+            |pred, actual| CostFn::of::<CategoricalCrossEntropy>().prime(pred, actual),
+        ));
+        for (to, from) in chained.params_mut().into_iter().zip(fused.params_mut()) {
+            *to = from.clone();
+        }
+        let ((fused_cost, fused_grads), (chained_cost, chained_grads)) =
+            (fused.batch_grad(&x, &y), chained.batch_grad(&x, &y));
+        assert!((fused_cost - chained_cost).abs() <= ERROR_MARGIN);
+        for (a, b) in fused_grads.iter().zip(chained_grads.iter()) {
+            assert!(a
+                .iter()
+                .zip(b.iter())
+                .all(|(a, b)| (a - b).abs() <= ERROR_MARGIN));
+        }
+    }
+
+    #[test]
+    fn gradients() {
+        let (x, y) = classes();
+        let mut models = [
+            Sequential::new()
+                .dense_with(3, 4, &XavierUniform, 1)
+                .relu()
+                .dense_with(4, 3, &XavierUniform, 2)
+                .softmax()
+                .cost::<CategoricalCrossEntropy>(),
+            Sequential::new()
+                .dense_with(3, 4, &XavierUniform, 3)
+                .sigmoid()
+                .dense_with(4, 3, &XavierUniform, 4)
+                .log_softmax()
+                .cost::<NegativeLogLikelihood>(),
+            // unfused, softmax's own backward pass
+            Sequential::new()
+                .dense_with(3, 3, &XavierUniform, 5)
+                .softmax(),
+            // only the output of a real `Softmax` is fused, not of a layer with the same name
+            Sequential::new()
+                .dense_with(3, 3, &XavierUniform, 6)
+                .activation_fn(ActivationFn::new("softmax", Sigmoid::calc, Sigmoid::prime))
+                .cost::<CategoricalCrossEntropy>(),
+        ];
+        assert_eq!(
+            models[0].layers()[3].output_kind(),
+            OutputKind::Probabilities
+        );
+        assert_eq!(
+            models[1].layers()[3].output_kind(),
+            OutputKind::LogProbabilities
+        );
+        assert_eq!(models[3].layers()[1].output_kind(), OutputKind::Values);
+        for model in models.iter_mut() {
+            for error in check_model(model, &x, &y, 1e-6) {
+                assert!(error < 1e-5, "{}", error);
+            }
+        }
+        let pred = softmax(&x);
+        assert!(check_cost::<CategoricalCrossEntropy>(&pred, &y, 1e-7) < 1e-5);
+        assert!(check_cost::<NegativeLogLikelihood>(&x, &y, 1e-7) < 1e-5);
+    }
+
+    #[test]
+    fn large_logits() {
+        let x = Matrix::new(vec![1000.0, -1000.0, 1000.0, 1000.0], 2, 2);
+        let y = Matrix::new(vec![0.0, 1.0, 1.0, 0.0], 2, 2);
+        let mut model = Sequential::new()
+            .dense(2, 2)
+            .softmax()
+            .cost::<CategoricalCrossEntropy>();
+        let (cost, grads) = model.batch_grad(&x, &y);
+        assert!(cost.is_finite());
+        assert!(grads
+            .iter()
+            .flat_map(|grad| grad.iter())
+            .all(|g| g.is_finite()));
+
+        let mut layer = Softmax::new();
+        let p = layer.forward(&x);
+        assert!(p.iter().all(|v| v.is_finite()));
+        assert_eq!(layer.name(), "softmax");
+    }
+}
@@ -0,0 +1,81 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::dataset;
+    use ml::algebra::{MatLike, Matrix};
+    use ml::nn::train::{TrainConfig, Trainable, Trainer};
+    use ml::nn::{activations::*, cost::SumSquared, feedforward::FFNet};
+    const ERROR_MARGIN: f64 = 0.00001;
+
+    // fixed params, far enough from a fit that training has something to do
+    fn spread_params(net: &mut FFNet<Sigmoid, SumSquared>) {
+        for (n, param) in net.params_mut().into_iter().enumerate() {
+            let (w, h) = (param.w(), param.h());
+            let spread = (0..w * h)
+                .map(|k| ((n * 5 + k * 3) % 7) as f64 / 2.0 - 1.5)
+                .collect();
+            *param = Matrix::new(spread, w, h);
+        }
+    }
+
+    #[test]
+    fn batch_grad_is_mean_of_cases() {
+        let (x, y) = dataset();
+        let mut net = FFNet::<Sigmoid, SumSquared>::new(vec![2, 3, 1]);
+        net.randomize_params();
+        let (_, batch) = net.batch_grad(&x, &y);
+        for (param, grad) in batch.iter().enumerate() {
+            let mut mean = Matrix::new_uniform(0.0, grad.w(), grad.h());
+            for i in 0..x.h() {
+                let (weights, biases) =
+                    net.single_case_grad(x.clone_row(i).transpose(), &y.clone_row(i));
+                let case = if param % 2 == 0 { &weights } else { &biases };
+                mean = mean + &case[param / 2] * (1.0 / x.h() as f64);
+            }
+            for (a, b) in grad.iter().zip(mean.iter()) {
+                assert!((a - b).abs() <= ERROR_MARGIN, "{} != {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn loss_decreases() {
+        let (x, y) = dataset();
+        for autograd in [false, true] {
+            let mut net = FFNet::<Sigmoid, SumSquared>::new(vec![2, 3, 1]);
+            spread_params(&mut net);
+            net.set_autograd(autograd);
+            let history = Trainer::new(TrainConfig {
+                epochs: 50,
+                batch_size: 4,
+                shuffle_seed: Some(1),
+                learning_rate: 0.5,
+            })
+            .fit(&mut net, &x, &y);
+            assert_eq!(history.loss.len(), 50);
+            assert!(
+                history.loss[49] < history.loss[0] * 0.5,
+                "{:?}",
+                history.loss
+            );
+            assert!((net.batch_cost(&x, &y) - history.loss[49]).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn continues_from_current_params() {
+        let (x, y) = dataset();
+        let mut net = FFNet::<Sigmoid, SumSquared>::new(vec![2, 3, 1]);
+        net.randomize_params();
+        let before: Vec<Matrix<f64>> = net.params_mut().into_iter().map(|p| p.clone()).collect();
+        Trainer::new(TrainConfig {
+            learning_rate: 0.0,
+            ..TrainConfig::default()
+        })
+        .fit(&mut net, &x, &y);
+        for (a, b) in before.iter().zip(net.params_mut()) {
+            assert!(a.iter().zip(b.iter()).all(|(a, b)| a == b));
+        }
+    }
+}
@@ -0,0 +1,227 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{dataset, net};
+    use ml::algebra::{MatLike, Matrix};
+    use ml::nn::checkpoint::{Checkpoint, CheckpointConfig, CheckpointEvery};
+    use ml::nn::cost::{Cost, SumSquared};
+    use ml::nn::sequential::Sequential;
+    use ml::nn::train::{EarlyStopping, Metric, TrainConfig, Trainable, Trainer};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // the same cases with the opposite relationship, which gets worse the better the net fits
+    fn contrary() -> (Matrix<f64>, Matrix<f64>) {
+        let (x, y) = dataset();
+        (x, y.apply(|y| 1.0 - y))
+    }
+
+    fn trainer(epochs: usize) -> Trainer {
+        Trainer::new(TrainConfig {
+            epochs,
+            batch_size: 4,
+            shuffle_seed: Some(2),
+            learning_rate: 0.5,
+        })
+    }
+
+    #[test]
+    fn split() {
+        let (x, y) = dataset();
+        let mut net = net(5);
+        let mut trainer = trainer(3).validation_split(0.25);
+        let history = trainer.fit(&mut net, &x, &y);
+        // 15 cases left to train on, in 4 batches
+        assert_eq!(trainer.step(), 12);
+        assert_eq!(history.validation.len(), 3);
+        let held_out: Vec<usize> = (15..20).collect();
+        let score = net.batch_cost(&x.select_rows(&held_out), &y.select_rows(&held_out));
+        assert_eq!(history.validation[2], score);
+        assert!(!history.stopped_early);
+    }
+
+    #[test]
+    fn stops_and_restores_best() {
+        let (x, y) = dataset();
+        let (x_val, y_val) = contrary();
+        let mut net = net(5);
+        let history = trainer(50)
+            .validation(x_val.clone(), y_val.clone())
+            .early_stopping(EarlyStopping::new(3))
+            .fit(&mut net, &x, &y);
+        assert!(history.stopped_early);
+        let best = history.best_epoch.unwrap();
+        assert_eq!(history.loss.len(), best + 4, "{:?}", history.validation);
+        for later in history.validation[best + 1..].iter() {
+            assert!(*later >= history.validation[best]);
+        }
+        assert_eq!(net.batch_cost(&x_val, &y_val), history.validation[best]);
+    }
+
+    fn batch_norm_net() -> Sequential {
+        Sequential::with_seed(5)
+            .dense(2, 3)
+            .batch_norm(3)
+            .sigmoid()
+            .dense(3, 1)
+            .sigmoid()
+    }
+
+    #[test]
+    fn restores_best_state() {
+        let (x, y) = dataset();
+        let (x_val, y_val) = contrary();
+        let mut net = batch_norm_net();
+        let history = trainer(50)
+            .validation(x_val.clone(), y_val.clone())
+            .early_stopping(EarlyStopping::new(3))
+            .fit(&mut net, &x, &y);
+        assert!(history.stopped_early);
+        let best = history.best_epoch.unwrap();
+
+        // the same run, cut off after the best epoch
+        let mut expected = batch_norm_net();
+        trainer(best + 1).fit(&mut expected, &x, &y);
+        assert_eq!(net.state(), expected.state());
+        assert_ne!(net.state(), batch_norm_net().state());
+        assert_eq!(net.batch_cost(&x_val, &y_val), history.validation[best]);
+
+        let dir = std::env::temp_dir().join(format!("ml-best-state-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let config = CheckpointConfig::new(&dir, CheckpointEvery::Epochs(1)).keep(100);
+        trainer(50)
+            .validation(x_val.clone(), y_val.clone())
+            .early_stopping(EarlyStopping::new(3))
+            .checkpoints(config)
+            .fit(&mut batch_norm_net(), &x, &y);
+        let last = Checkpoint::list(&dir).unwrap().pop().unwrap();
+        let checkpoint = Checkpoint::load(&last).unwrap();
+        assert_eq!(checkpoint.best.unwrap().state, expected.state());
+
+        let mut resumed = Sequential::with_seed(9)
+            .dense(2, 3)
+            .batch_norm(3)
+            .sigmoid()
+            .dense(3, 1)
+            .sigmoid();
+        let mut trainer = trainer(50)
+            .validation(x_val.clone(), y_val.clone())
+            .early_stopping(EarlyStopping::new(3));
+        trainer.resume_from(&mut resumed, &last).unwrap();
+        trainer.fit(&mut resumed, &x, &y);
+        assert_eq!(resumed.state(), expected.state());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn runs_to_the_end_while_improving() {
+        let (x, y) = dataset();
+        let mut net = net(5);
+        let history = trainer(20)
+            .validation(x.clone(), y.clone())
+            .early_stopping(EarlyStopping::new(5))
+            .fit(&mut net, &x, &y);
+        assert!(!history.stopped_early);
+        assert_eq!(history.loss.len(), 20);
+        let best = history.best_epoch.unwrap();
+        assert!(history
+            .validation
+            .iter()
+            .all(|v| *v >= history.validation[best]));
+    }
+
+    #[test]
+    fn higher_is_better() {
+        let (x, y) = dataset();
+        let mut net = net(5);
+        let history = trainer(10)
+            .validation(x.clone(), y.apply(|y| (y > 0.5) as u8 as f64))
+            .metric(Metric::Accuracy)
+            .fit(&mut net, &x, &y);
+        let best = history.best_epoch.unwrap();
+        // ties go to the earliest epoch
+        let max = history.validation.iter().cloned().fold(0.0, f64::max);
+        let first = history.validation.iter().position(|v| *v == max).unwrap();
+        assert_eq!(best, first, "{:?}", history.validation);
+        assert!((0.0..=1.0).contains(&max));
+
+        assert!(Metric::Accuracy.improves(0.6, 0.5, 0.0));
+        assert!(!Metric::Accuracy.improves(0.55, 0.5, 0.1));
+        assert!(Metric::Loss.improves(0.4, 0.5, 0.0));
+        let custom = Metric::Custom {
+            name: "max_error",
+            measure: |pred, y| {
+                pred.iter()
+                    .zip(y.iter())
+                    .map(|(p, y)| (p - y).abs())
+                    .fold(0.0, f64::max)
+            },
+            higher_is_better: false,
+        };
+        assert_eq!(custom.name(), "max_error");
+        assert!(custom.measure(&mut net, &x, &y) >= 0.0);
+    }
+
+    #[test]
+    fn nan_never_best() {
+        static SCORED: AtomicUsize = AtomicUsize::new(0);
+        let (x, y) = dataset();
+        let mut net = net(5);
+        let history = trainer(10)
+            .validation(x.clone(), y.clone())
+            .metric(Metric::Custom {
+                name: "nan_first",
+                measure: |pred, y| match SCORED.fetch_add(1, Ordering::SeqCst) {
+                    0 => f64::NAN,
+                    _ => SumSquared::calc(pred, y),
+                },
+                higher_is_better: false,
+            })
+            .early_stopping(EarlyStopping::new(3))
+            .fit(&mut net, &x, &y);
+        assert!(history.validation[0].is_nan());
+        assert!(!history.stopped_early);
+        assert!(history.best_epoch.unwrap() > 0, "{:?}", history.validation);
+
+        assert!(Metric::Loss.improves(0.4, f64::NAN, 0.0));
+        assert!(Metric::Accuracy.improves(0.0, f64::NEG_INFINITY, 0.1));
+        assert!(!Metric::Loss.improves(f64::NAN, 0.4, 0.0));
+        assert!(!Metric::Accuracy.improves(f64::NAN, f64::NAN, 0.0));
+    }
+
+    #[test]
+    fn resumes_exactly() {
+        let (x, y) = dataset();
+        let (x_val, y_val) = contrary();
+        let run = |trainer: Trainer| {
+            trainer
+                .validation(x_val.clone(), y_val.clone())
+                .early_stopping(EarlyStopping::new(3))
+        };
+        let mut expected_net = net(5);
+        let expected = run(trainer(50)).fit(&mut expected_net, &x, &y);
+
+        let dir = std::env::temp_dir().join(format!("ml-validation-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let config = CheckpointConfig::new(&dir, CheckpointEvery::Epochs(1)).keep(100);
+        run(trainer(50).checkpoints(config)).fit(&mut net(5), &x, &y);
+        for path in Checkpoint::list(&dir).unwrap() {
+            let mut resumed_net = net(9);
+            let mut trainer = run(trainer(50));
+            trainer.resume_from(&mut resumed_net, &path).unwrap();
+            let history = trainer.fit(&mut resumed_net, &x, &y);
+            assert_eq!(history.loss, expected.loss, "resumed from {:?}", path);
+            assert_eq!(history.validation, expected.validation);
+            assert_eq!(history.best_epoch, expected.best_epoch);
+            assert!(history.stopped_early);
+            for (a, b) in resumed_net
+                .params_mut()
+                .into_iter()
+                .zip(expected_net.params_mut())
+            {
+                assert!(a.iter().zip(b.iter()).all(|(a, b)| a == b));
+            }
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}